@@ -0,0 +1,32 @@
+//! Compares `flatten_batch` with a serial `map` over many small documents
+//!
+//! Run with `cargo run --release --example batch --features rayon`.
+use jsonpointer_flatten::{flatten_batch, from_json};
+use serde_json::{json, Value};
+use std::time::Instant;
+
+fn main() {
+    let docs: Vec<Value> = (0..50_000)
+        .map(|id| {
+            json!({
+                "id": id,
+                "name": format!("user {}", id),
+                "address": { "city": "Springfield", "zip": format!("{:05}", id) },
+                "tags": ["a", "b", "c"],
+            })
+        })
+        .collect();
+
+    let start = Instant::now();
+    let serial: Vec<Value> = docs.iter().map(from_json).collect();
+    let serial_time = start.elapsed();
+
+    let start = Instant::now();
+    let parallel = flatten_batch(&docs);
+    let parallel_time = start.elapsed();
+
+    assert_eq!(serial, parallel);
+    println!("{} documents", docs.len());
+    println!("serial map():    {:?}", serial_time);
+    println!("flatten_batch(): {:?}", parallel_time);
+}
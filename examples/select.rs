@@ -0,0 +1,47 @@
+//! Compares `flatten_selected` with flattening a wide document and filtering
+//!
+//! Run with `cargo run --release --example select`.
+use jsonpointer_flatten::{flatten_selected, from_json, PointerPattern};
+use serde_json::{json, Map, Value};
+use std::time::Instant;
+
+fn main() {
+    let mut doc = Map::new();
+    for id in 0..100_000 {
+        doc.insert(
+            format!("field_{}", id),
+            json!({ "value": id, "labels": ["a", "b"], "meta": { "source": "import" } }),
+        );
+    }
+    doc.insert("id".to_owned(), json!(42));
+    doc.insert("user".to_owned(), json!({ "email": "jo@example.com" }));
+    let doc = Value::Object(doc);
+    let selectors: Vec<PointerPattern> = ["/id", "/user/email", "/field_500/labels/*"]
+        .iter()
+        .map(|p| p.parse().unwrap())
+        .collect();
+
+    let start = Instant::now();
+    let flat = from_json(&doc);
+    let filtered: Map<String, Value> = flat
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(ptr, _)| selectors.iter().any(|s| s.matches(ptr)))
+        .map(|(ptr, val)| (ptr.clone(), val.clone()))
+        .collect();
+    let full_time = start.elapsed();
+
+    let start = Instant::now();
+    let selected = flatten_selected(&doc, &selectors);
+    let selected_time = start.elapsed();
+
+    assert_eq!(Value::Object(filtered), selected);
+    println!(
+        "{} entries, {} selected",
+        flat.as_object().unwrap().len(),
+        selected.as_object().unwrap().len()
+    );
+    println!("from_json() + filter: {:?}", full_time);
+    println!("flatten_selected():   {:?}", selected_time);
+}
@@ -0,0 +1,223 @@
+use crate::{diff_with, normalize_numbers, DiffEntry, DiffOptions, PointerPattern};
+use serde_json::Value;
+use std::fmt::Write as _;
+
+/// Options for [`check_flat_eq`] and [`assert_json_flat_eq!`](crate::assert_json_flat_eq)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlatEqOptions {
+    diff: DiffOptions,
+    unordered_arrays: bool,
+    normalize_numbers: bool,
+}
+
+impl FlatEqOptions {
+    /// Create options for exact equality
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Don't compare pointers matching any of `patterns`, nor anything below them
+    ///
+    /// Like [`DiffOptions::ignore`], calling this again replaces the patterns.
+    pub fn ignore(mut self, patterns: Vec<PointerPattern>) -> Self {
+        self.diff = self.diff.ignore(patterns);
+        self
+    }
+
+    /// Compare numbers by value, so `1` equals `1.0`, see [`normalize_number`](crate::normalize_number)
+    pub fn normalize_numbers(mut self, normalize: bool) -> Self {
+        self.diff = self.diff.normalize_numbers(normalize);
+        self.normalize_numbers = normalize;
+        self
+    }
+
+    /// Compare arrays as multisets, ignoring the order of their elements
+    ///
+    /// Elements are sorted by their serialization on both sides before
+    /// comparing, so differences are reported at the sorted indices.
+    pub fn unordered_arrays(mut self, unordered: bool) -> Self {
+        self.unordered_arrays = unordered;
+        self
+    }
+}
+
+/// Compare two documents entry by entry, describing every difference on failure
+///
+/// The error message has one line per differing pointer, with the expected and
+/// the actual value, as used by [`assert_json_flat_eq!`](crate::assert_json_flat_eq).
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{check_flat_eq, FlatEqOptions};
+/// use serde_json::json;
+///
+/// let actual = json!({ "id": 1, "tags": ["b", "a"] });
+/// let expected = json!({ "id": 2, "tags": ["a", "b"] });
+/// let options = FlatEqOptions::new().unordered_arrays(true);
+///
+/// assert_eq!(
+///     check_flat_eq(&actual, &expected, &options),
+///     Err("JSON values differ at 1 pointer:\n  /id: expected 2, actual 1".to_owned())
+/// );
+/// ```
+pub fn check_flat_eq(
+    actual: &Value,
+    expected: &Value,
+    options: &FlatEqOptions,
+) -> Result<(), String> {
+    let diff = if options.unordered_arrays {
+        // normalized first, so numbers equal by value sort the same
+        let prepare = |value: &Value| {
+            if options.normalize_numbers {
+                sorted(&normalize_numbers(value))
+            } else {
+                sorted(value)
+            }
+        };
+        diff_with(&prepare(expected), &prepare(actual), &options.diff)
+    } else {
+        diff_with(expected, actual, &options.diff)
+    };
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "JSON values differ at {} pointer{}:",
+        diff.len(),
+        if diff.len() == 1 { "" } else { "s" }
+    );
+    for entry in diff.entries() {
+        let _ = match entry {
+            DiffEntry::Changed { pointer, old, new } => {
+                write!(message, "\n  {}: expected {}, actual {}", pointer, old, new)
+            }
+            DiffEntry::Removed { pointer, value } => {
+                write!(message, "\n  {}: missing, expected {}", pointer, value)
+            }
+            DiffEntry::Added { pointer, value } => {
+                write!(message, "\n  {}: extra, actual {}", pointer, value)
+            }
+        };
+    }
+    Err(message)
+}
+
+/// Assert that two JSON values are equal, reporting differences pointer by pointer
+///
+/// Accepts optional [`FlatEqOptions`](crate::FlatEqOptions) as a third
+/// argument and panics with the message of [`check_flat_eq`](crate::check_flat_eq).
+///
+/// # Example
+///
+/// ```should_panic
+/// use jsonpointer_flatten::assert_json_flat_eq;
+/// use serde_json::json;
+///
+/// // panics with:
+/// // JSON values differ at 2 pointers:
+/// //   /address/zip: expected "00000", actual "00001"
+/// //   /name: extra, actual "John"
+/// assert_json_flat_eq!(
+///     json!({ "address": { "zip": "00001" }, "name": "John" }),
+///     json!({ "address": { "zip": "00000" } })
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_json_flat_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_json_flat_eq!($actual, $expected, &$crate::FlatEqOptions::new())
+    };
+    ($actual:expr, $expected:expr, $options:expr $(,)?) => {
+        if let Err(message) = $crate::check_flat_eq(&$actual, &$expected, $options) {
+            panic!("{}", message)
+        }
+    };
+}
+
+/// Sort the elements of every array, children first
+fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Array(arr) => {
+            let mut elements: Vec<(String, Value)> = arr
+                .iter()
+                .map(|element| {
+                    let element = sorted(element);
+                    (element.to_string(), element)
+                })
+                .collect();
+            elements.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Array(elements.into_iter().map(|(_, element)| element).collect())
+        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), sorted(val)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn equal_values_pass() {
+        assert_json_flat_eq!(
+            json!({ "a": [1, { "b": null }] }),
+            json!({ "a": [1, { "b": null }] })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "JSON values differ at 3 pointers:\n  \
+        /a: expected 1, actual 2\n  \
+        /b: missing, expected \"x\"\n  \
+        /c: extra, actual true")]
+    fn lists_every_pointer() {
+        assert_json_flat_eq!(json!({ "a": 2, "c": true }), json!({ "a": 1, "b": "x" }),);
+    }
+
+    #[test]
+    fn ignored_and_unordered() {
+        let actual = json!({ "items": [{ "id": 2 }, { "id": 1 }], "meta": { "ts": 1 } });
+        let expected = json!({ "items": [{ "id": 1 }, { "id": 2 }], "meta": { "ts": 2 } });
+        let options = FlatEqOptions::new()
+            .ignore(vec![PointerPattern::new("/meta/ts").unwrap()])
+            .unordered_arrays(true);
+
+        assert!(check_flat_eq(&actual, &expected, &FlatEqOptions::new()).is_err());
+        assert_json_flat_eq!(actual, expected, &options);
+    }
+
+    #[test]
+    fn unordered_arrays_still_count_elements() {
+        let options = FlatEqOptions::new().unordered_arrays(true);
+
+        assert_eq!(
+            check_flat_eq(&json!([1, 1, 2]), &json!([2, 1]), &options),
+            Err("JSON values differ at 2 pointers:\n  /1: expected 2, actual 1\n  /2: extra, actual 2"
+                .to_owned())
+        );
+    }
+
+    #[test]
+    fn normalized_numbers_compare_by_value() {
+        let actual = json!({ "n": [10, 1.0, -0.0] });
+        let expected = json!({ "n": [0, 1, 1e1] });
+        let options = FlatEqOptions::new()
+            .unordered_arrays(true)
+            .normalize_numbers(true);
+
+        assert!(check_flat_eq(
+            &actual,
+            &expected,
+            &FlatEqOptions::new().unordered_arrays(true)
+        )
+        .is_err());
+        assert_json_flat_eq!(actual, expected, &options);
+    }
+}
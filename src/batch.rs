@@ -0,0 +1,106 @@
+use crate::{FlattenError, Flattener, FlattenerScratch};
+use rayon::prelude::*;
+use serde_json::Value;
+
+/// Flatten many documents in parallel on the rayon thread pool
+///
+/// The output is in input order, `result[i]` being the flattened `docs[i]`.
+/// See [`Flattener::flatten_batch`] for configured flattening.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let docs: Vec<_> = (0..100).map(|id| json!({ "id": id })).collect();
+/// let flattened = jsonpointer_flatten::flatten_batch(&docs);
+///
+/// assert_eq!(flattened[42], json!({ "": {}, "/id": 42 }));
+/// ```
+pub fn flatten_batch(docs: &[Value]) -> Vec<Value> {
+    Flattener::new().flatten_batch(docs)
+}
+
+impl Flattener {
+    /// Like [`flatten_batch`], with the options of this flattener
+    ///
+    /// Each rayon job reuses one [`FlattenerScratch`] for all of its documents.
+    pub fn flatten_batch(&self, docs: &[Value]) -> Vec<Value> {
+        docs.par_iter()
+            .map_init(
+                || FlattenerScratch::new(self),
+                |scratch, doc| scratch.flatten(doc),
+            )
+            .collect()
+    }
+
+    /// Like [`Flattener::flatten_batch`], enforcing the configured [`Limits`](crate::Limits)
+    ///
+    /// A document exceeding a limit doesn't stop the others: its error is
+    /// returned at its index, in place of the flattened document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{Flattener, Limits};
+    /// use serde_json::json;
+    ///
+    /// let docs = vec![json!([1]), json!([[2]]), json!([3])];
+    /// let flattener = Flattener::new().limits(Limits::new().max_depth(1));
+    ///
+    /// let results = flattener.try_flatten_batch(&docs);
+    /// let failed: Vec<usize> = (0..results.len()).filter(|i| results[*i].is_err()).collect();
+    ///
+    /// assert_eq!(failed, vec![1]);
+    /// ```
+    pub fn try_flatten_batch(&self, docs: &[Value]) -> Vec<Result<Value, FlattenError>> {
+        docs.par_iter()
+            .map_init(
+                || FlattenerScratch::new(self),
+                |scratch, doc| scratch.try_flatten(doc),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Limits;
+    use serde_json::json;
+
+    fn documents() -> Vec<Value> {
+        (0..1000)
+            .map(|id| json!({ "id": id, "tags": vec![id; id % 5] }))
+            .collect()
+    }
+
+    #[test]
+    fn matches_serial_flattening_in_order() {
+        let docs = documents();
+
+        let serial: Vec<Value> = docs.iter().map(crate::from_json).collect();
+
+        assert_eq!(flatten_batch(&docs), serial);
+    }
+
+    #[test]
+    fn errors_keep_their_index() {
+        let docs = documents();
+        let flattener = Flattener::new().limits(Limits::new().max_entries(5));
+
+        let results = flattener.try_flatten_batch(&docs);
+
+        assert_eq!(results.len(), docs.len());
+        for (idx, result) in results.iter().enumerate() {
+            match result {
+                Ok(value) => assert_eq!(*value, crate::from_json(&docs[idx])),
+                Err(FlattenError::EntryLimitExceeded { pointer, .. }) => {
+                    assert!(idx % 5 >= 3);
+                    assert_eq!(pointer, "/tags/2");
+                }
+                Err(error) => panic!("unexpected error {:?}", error),
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+
+/// The elements of `arr` in canonical order, see [`compare`]
+///
+/// Equal elements keep their original order.
+pub(crate) fn sorted(arr: &[Value]) -> Vec<&Value> {
+    let mut keyed: Vec<(Value, &Value)> = arr.iter().map(|val| (canonical(val), val)).collect();
+    keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+    keyed.into_iter().map(|(_, val)| val).collect()
+}
+
+/// A copy of `value` with the elements of every array in canonical order
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Array(arr) => {
+            let mut elements: Vec<Value> = arr.iter().map(canonical).collect();
+            elements.sort_by(compare);
+            Value::Array(elements)
+        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), canonical(val)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Total order over canonical values: by type, then by value
+///
+/// Types rank `null < boolean < number < string < array < object`. Numbers
+/// compare numerically, ties like `1` and `1.0` broken by their text. Strings
+/// compare by code points. Arrays compare element by element, then by length,
+/// and objects the same over their members sorted by key, a member being
+/// ordered by key, then by value.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => {
+            compare_sequences(a.iter(), b.iter(), |x, y| compare(x, y))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            let mut a: Vec<_> = a.iter().collect();
+            let mut b: Vec<_> = b.iter().collect();
+            a.sort_unstable_by_key(|(key, _)| *key);
+            b.sort_unstable_by_key(|(key, _)| *key);
+            compare_sequences(a.into_iter(), b.into_iter(), |(ka, va), (kb, vb)| {
+                ka.cmp(kb).then_with(|| compare(va, vb))
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    }
+}
+
+fn compare_numbers(a: &Number, b: &Number) -> Ordering {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a.cmp(&b);
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        return a.cmp(&b);
+    }
+    let numeric = match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => Ordering::Equal,
+    };
+    // `Display` drops the fraction of `1.0`, the serialized text keeps it
+    numeric.then_with(|| text(a).cmp(&text(b)))
+}
+
+fn text(number: &Number) -> String {
+    serde_json::to_string(number).unwrap_or_default()
+}
+
+fn compare_sequences<T>(
+    mut a: impl Iterator<Item = T>,
+    mut b: impl Iterator<Item = T>,
+    compare: impl Fn(&T, &T) -> Ordering,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) => match compare(&x, &y) {
+                Ordering::Equal => {}
+                ordering => return ordering,
+            },
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn types_rank_before_values() {
+        let arr = vec![
+            json!({}),
+            json!([]),
+            json!("a"),
+            json!(2),
+            json!(true),
+            json!(null),
+            json!(false),
+            json!(-1.5),
+        ];
+
+        assert_eq!(
+            sorted(&arr),
+            vec![
+                &json!(null),
+                &json!(false),
+                &json!(true),
+                &json!(-1.5),
+                &json!(2),
+                &json!("a"),
+                &json!([]),
+                &json!({})
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_values_compare_canonically() {
+        let arr = vec![
+            json!([2, 1]),
+            json!([1, 1]),
+            json!({ "b": 1 }),
+            json!({ "a": 2 }),
+            json!([1]),
+        ];
+
+        assert_eq!(
+            sorted(&arr),
+            vec![&arr[4], &arr[1], &arr[0], &arr[3], &arr[2]]
+        );
+        // [2, 1] and [1, 2] are the same set, so they keep their order
+        let sets = vec![json!([2, 1]), json!([1, 2])];
+        assert_eq!(sorted(&sets), vec![&sets[0], &sets[1]]);
+    }
+
+    #[test]
+    fn numbers_compare_numerically_then_by_text() {
+        let arr = vec![json!(1.0), json!(u64::MAX), json!(-3), json!(1), json!(0.5)];
+
+        assert_eq!(
+            sorted(&arr),
+            vec![&arr[2], &arr[4], &arr[3], &arr[0], &arr[1]]
+        );
+    }
+}
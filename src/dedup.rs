@@ -0,0 +1,423 @@
+use crate::error::ReferenceError;
+use crate::flattener::{entry_value, is_container};
+use crate::patch_gen::{fingerprint, len};
+use crate::pointer::{self, escape_into};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Options for [`flatten_deduplicated`] and [`expand_references`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupOptions {
+    min_bytes: usize,
+    marker_key: String,
+}
+
+impl Default for DedupOptions {
+    fn default() -> Self {
+        DedupOptions {
+            min_bytes: 32,
+            marker_key: "$ref_ptr".to_owned(),
+        }
+    }
+}
+
+impl DedupOptions {
+    /// Create options for subtrees of at least 32 bytes and `$ref_ptr` markers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only replace subtrees of at least `bytes` as compact JSON, 32 by default
+    pub fn min_bytes(mut self, bytes: usize) -> Self {
+        self.min_bytes = bytes;
+        self
+    }
+
+    /// Name the single member of reference markers `key`, `$ref_ptr` by default
+    pub fn marker_key(mut self, key: impl Into<String>) -> Self {
+        self.marker_key = key.into();
+        self
+    }
+}
+
+/// Result of [`flatten_deduplicated`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deduplicated {
+    /// The flattened entries, an object of pointer keys with reference markers
+    pub value: Value,
+    /// Number of subtrees replaced by a reference
+    pub references: usize,
+    /// How many fewer entries there are than in the fully flattened document
+    pub entries_saved: usize,
+    /// How much shorter the entries are as compact JSON than the fully flattened document
+    pub bytes_saved: usize,
+}
+
+/// Flatten `value`, replacing repeated subtrees with references to their first occurrence
+///
+/// Every object or array at least [`DedupOptions::min_bytes`] long that is
+/// equal to one already flattened gets a single entry, a marker object like
+/// `{ "$ref_ptr": "/first/occurrence" }`, instead of its own entries, as long
+/// as the marker is shorter than them. The
+/// first occurrence comes earlier in flattening order and may hold references
+/// itself. Flattened documents only ever hold empty objects, so markers can't
+/// be mistaken for entries of the document, but they can be for hand-written
+/// entries: see [`DedupOptions::marker_key`]. [`expand_references`] restores
+/// the fully flattened document.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::DedupOptions;
+/// use serde_json::json;
+///
+/// let address = json!({ "street": "1 Main St", "city": "Springfield" });
+/// let value = json!({ "billing": address, "shipping": address });
+/// let options = DedupOptions::new().min_bytes(16);
+///
+/// let deduplicated = jsonpointer_flatten::flatten_deduplicated(&value, &options);
+///
+/// assert_eq!(
+///     deduplicated.value,
+///     json!({
+///         "": {},
+///         "/billing": {},
+///         "/billing/city": "Springfield",
+///         "/billing/street": "1 Main St",
+///         "/shipping": { "$ref_ptr": "/billing" }
+///     })
+/// );
+/// assert_eq!(deduplicated.entries_saved, 2);
+/// assert_eq!(
+///     jsonpointer_flatten::expand_references(&deduplicated.value, &options),
+///     Ok(jsonpointer_flatten::from_json(&value))
+/// );
+/// ```
+pub fn flatten_deduplicated(value: &Value, options: &DedupOptions) -> Deduplicated {
+    let mut hashes = HashMap::new();
+    fingerprint(value, &mut String::new(), &mut |ptr, hash, size| {
+        if size >= options.min_bytes {
+            hashes.insert(ptr.to_owned(), hash);
+        }
+    });
+
+    let mut deduper = Deduper {
+        marker_key: &options.marker_key,
+        hashes,
+        seen: HashMap::new(),
+        entries: Map::new(),
+        pointer: String::new(),
+        references: 0,
+        entries_saved: 0,
+        bytes_saved: 0,
+    };
+    deduper.visit(value);
+    Deduplicated {
+        value: Value::Object(deduper.entries),
+        references: deduper.references,
+        entries_saved: deduper.entries_saved,
+        bytes_saved: deduper.bytes_saved,
+    }
+}
+
+/// Replace the reference markers of [`flatten_deduplicated`] output with the entries they refer to
+///
+/// The entries of the target are copied below the marker's pointer, and
+/// references among them are expanded in turn. Markers are recognized by
+/// [`DedupOptions::marker_key`] alone, so the threshold doesn't matter here.
+pub fn expand_references(
+    flattened: &Value,
+    options: &DedupOptions,
+) -> Result<Value, ReferenceError> {
+    let entries = flattened.as_object().ok_or(ReferenceError::NotAnObject)?;
+    let mut expander = Expander {
+        entries,
+        marker_key: &options.marker_key,
+        expanded: Map::new(),
+        expanding: Vec::new(),
+    };
+    for (key, value) in entries {
+        match expander.target(value) {
+            Some(target) => expander.expand(key, target)?,
+            None => {
+                expander.expanded.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(Value::Object(expander.expanded))
+}
+
+struct Deduper<'v, 'o> {
+    marker_key: &'o str,
+    /// Hash of every node at least as long as the threshold, by pointer
+    hashes: HashMap<String, u64>,
+    /// Containers flattened so far by hash, with their pointer
+    seen: HashMap<u64, Vec<(&'v Value, String)>>,
+    entries: Map<String, Value>,
+    pointer: String,
+    references: usize,
+    entries_saved: usize,
+    bytes_saved: usize,
+}
+
+impl<'v> Deduper<'v, '_> {
+    fn visit(&mut self, value: &'v Value) {
+        if !is_container(value) {
+            self.entries
+                .insert(self.pointer.clone(), entry_value(value));
+            return;
+        }
+        if let Some(hash) = self.hashes.get(&self.pointer).copied() {
+            let first = self.seen.get(&hash).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .find(|(candidate, _)| *candidate == value)
+                    .map(|(_, ptr)| ptr.clone())
+            });
+            match first {
+                Some(target) => {
+                    if self.reference(value, &target) {
+                        return;
+                    }
+                }
+                None => self
+                    .seen
+                    .entry(hash)
+                    .or_default()
+                    .push((value, self.pointer.clone())),
+            }
+        }
+        self.entries
+            .insert(self.pointer.clone(), entry_value(value));
+
+        let len = self.pointer.len();
+        match value {
+            Value::Array(arr) => {
+                for (idx, child) in arr.iter().enumerate() {
+                    let _ = write!(self.pointer, "/{}", idx);
+                    self.visit(child);
+                    self.pointer.truncate(len);
+                }
+            }
+            Value::Object(obj) => {
+                for (key, child) in obj {
+                    self.pointer.push('/');
+                    escape_into(key, &mut self.pointer);
+                    self.visit(child);
+                    self.pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Emit a reference to `target` for `value`, unless its entries are shorter
+    fn reference(&mut self, value: &Value, target: &str) -> bool {
+        let mut marker = Map::new();
+        marker.insert(self.marker_key.to_owned(), target.into());
+        let marker = Value::Object(marker);
+
+        let (entries, bytes) = flattened_len(value, &mut self.pointer.clone());
+        let marker_bytes = entry_len(&self.pointer, &marker);
+        if bytes <= marker_bytes {
+            return false;
+        }
+        self.references += 1;
+        self.entries_saved += entries - 1;
+        self.bytes_saved += bytes - marker_bytes;
+        self.entries.insert(self.pointer.clone(), marker);
+        true
+    }
+}
+
+/// Number of entries of `value` flattened at `pointer`, and their compact JSON length
+fn flattened_len(value: &Value, pointer: &mut String) -> (usize, usize) {
+    let (mut entries, mut bytes) = (1, entry_len(pointer, &entry_value(value)));
+    let parent_len = pointer.len();
+    let mut add = |child: &Value, pointer: &mut String| {
+        let (child_entries, child_bytes) = flattened_len(child, pointer);
+        entries += child_entries;
+        bytes += child_bytes;
+        pointer.truncate(parent_len);
+    };
+    match value {
+        Value::Array(arr) => {
+            for (idx, child) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                add(child, pointer);
+            }
+        }
+        Value::Object(obj) if is_container(value) => {
+            for (key, child) in obj {
+                pointer.push('/');
+                escape_into(key, pointer);
+                add(child, pointer);
+            }
+        }
+        _ => {}
+    }
+    (entries, bytes)
+}
+
+/// Compact JSON length of an entry as a member of the flattened object, with its comma
+fn entry_len(pointer: &str, value: &Value) -> usize {
+    len(&Value::from(pointer)) + 1 + len(value) + 1
+}
+
+struct Expander<'e, 'o> {
+    entries: &'e Map<String, Value>,
+    marker_key: &'o str,
+    expanded: Map<String, Value>,
+    /// Targets of the references being expanded, outermost first
+    expanding: Vec<&'e str>,
+}
+
+impl<'e> Expander<'e, '_> {
+    fn target(&self, value: &'e Value) -> Option<&'e str> {
+        match value {
+            Value::Object(obj) if obj.len() == 1 => obj.get(self.marker_key)?.as_str(),
+            _ => None,
+        }
+    }
+
+    fn expand(&mut self, pointer: &str, target: &'e str) -> Result<(), ReferenceError> {
+        pointer::validate(target)?;
+        if pointer::starts_with(pointer, target) || self.expanding.contains(&target) {
+            return Err(ReferenceError::Cycle {
+                pointer: pointer.to_owned(),
+                target: target.to_owned(),
+            });
+        }
+
+        self.expanding.push(target);
+        let mut found = false;
+        for (key, value) in self.entries {
+            if !pointer::starts_with(key, target) {
+                continue;
+            }
+            found = true;
+            let rebased = format!("{}{}", pointer, &key[target.len()..]);
+            match self.target(value) {
+                Some(inner) => self.expand(&rebased, inner)?,
+                None => {
+                    self.expanded.insert(rebased, value.clone());
+                }
+            }
+        }
+        self.expanding.pop();
+
+        if found {
+            Ok(())
+        } else {
+            Err(ReferenceError::MissingTarget {
+                pointer: pointer.to_owned(),
+                target: target.to_owned(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::from_json;
+    use serde_json::json;
+
+    fn compact_len(value: &Value) -> usize {
+        serde_json::to_string(value).unwrap().len()
+    }
+
+    #[test]
+    fn references_expand_to_the_flattened_document() {
+        let a = json!({ "name": "shared block", "tags": ["x", "y"] });
+        let b = json!(["another", "shared", "block"]);
+        // the second pair refers to the first, whose "/1/0" refers to "/0"
+        let value = json!([a, [a, b], [a, b], { "b": b }]);
+        let options = DedupOptions::new().min_bytes(20);
+
+        let deduplicated = flatten_deduplicated(&value, &options);
+
+        let flat = from_json(&value);
+        assert_eq!(deduplicated.value["/1/0"], json!({ "$ref_ptr": "/0" }));
+        assert_eq!(deduplicated.value["/2"], json!({ "$ref_ptr": "/1" }));
+        assert_eq!(deduplicated.value["/3/b"], json!({ "$ref_ptr": "/1/1" }));
+        assert_eq!(deduplicated.references, 3);
+        assert_eq!(
+            deduplicated.entries_saved,
+            flat.as_object().unwrap().len() - deduplicated.value.as_object().unwrap().len()
+        );
+        assert_eq!(
+            deduplicated.bytes_saved,
+            compact_len(&flat) - compact_len(&deduplicated.value)
+        );
+        assert_eq!(expand_references(&deduplicated.value, &options), Ok(flat));
+    }
+
+    #[test]
+    fn small_subtrees_and_leaves_are_kept() {
+        let long = "a string well above the threshold";
+        // "/b" is long enough, but a marker for it would not be shorter
+        let value = json!({ "a": [1], "b": [1], "c": long, "d": long });
+
+        let deduplicated = flatten_deduplicated(&value, &DedupOptions::new().min_bytes(3));
+
+        assert_eq!(deduplicated.value, from_json(&value));
+        assert_eq!(deduplicated.references, 0);
+        assert_eq!(
+            (deduplicated.entries_saved, deduplicated.bytes_saved),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn marker_key_is_configurable() {
+        let value = json!({ "a": { "k": "some value" }, "b": { "k": "some value" } });
+        let options = DedupOptions::new().min_bytes(1).marker_key("@ref");
+
+        let deduplicated = flatten_deduplicated(&value, &options);
+
+        assert_eq!(deduplicated.value["/b"], json!({ "@ref": "/a" }));
+        assert_eq!(
+            expand_references(&deduplicated.value, &DedupOptions::new()),
+            Ok(deduplicated.value.clone())
+        );
+        assert_eq!(
+            expand_references(&deduplicated.value, &options),
+            Ok(from_json(&value))
+        );
+    }
+
+    #[test]
+    fn invalid_references_are_rejected() {
+        let options = DedupOptions::new();
+        let expand = |entries| expand_references(&entries, &options);
+
+        assert_eq!(
+            expand(json!({ "/a": { "$ref_ptr": "/missing" } })),
+            Err(ReferenceError::MissingTarget {
+                pointer: "/a".to_owned(),
+                target: "/missing".to_owned()
+            })
+        );
+        assert_eq!(
+            expand(json!({ "/a": {}, "/a/b": { "$ref_ptr": "/a" } })),
+            Err(ReferenceError::Cycle {
+                pointer: "/a/b".to_owned(),
+                target: "/a".to_owned()
+            })
+        );
+        assert_eq!(
+            expand(json!({ "/a": { "$ref_ptr": "/b" }, "/b": { "$ref_ptr": "/a" } })),
+            Err(ReferenceError::Cycle {
+                pointer: "/a".to_owned(),
+                target: "/a".to_owned()
+            })
+        );
+        assert!(matches!(
+            expand(json!({ "/a": { "$ref_ptr": "b" } })),
+            Err(ReferenceError::InvalidPointer(_))
+        ));
+        assert_eq!(expand(json!([])), Err(ReferenceError::NotAnObject));
+    }
+}
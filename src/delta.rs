@@ -0,0 +1,268 @@
+use crate::error::DeltaError;
+use crate::{pointer, FlattenedDocument};
+use serde_json::{Map, Value};
+use std::io::{self, Read, Write};
+
+/// Version byte of the format written by [`to_delta_encoded`]
+const VERSION: u8 = 1;
+
+/// Write `doc` in a compact binary form where each key only stores what it doesn't share with the previous one
+///
+/// Flattened keys repeat long prefixes, like `/users/17/address/` before
+/// `city` and `street`, so entries are written sorted by key and each key as
+/// the length of the prefix it shares with the previous key plus the rest.
+/// [`from_delta_encoded`] reads it back. Writes are small, so wrap unbuffered
+/// writers in an [`io::BufWriter`].
+///
+/// # Format
+///
+/// All lengths and counts are unsigned LEB128 varints: 7 bits per byte, least
+/// significant group first, with the high bit set on every byte but the last.
+///
+/// | Field   | Encoding                                         |
+/// |---------|--------------------------------------------------|
+/// | version | one byte, `1`                                    |
+/// | count   | varint, number of entries                        |
+/// | entries | `count` times: shared, suffix, value             |
+///
+/// Each entry is:
+///
+/// | Field  | Encoding                                                        |
+/// |--------|-----------------------------------------------------------------|
+/// | shared | varint, bytes of the key shared with the previous key, 0 at first |
+/// | suffix | varint byte length, then the rest of the key as UTF-8             |
+/// | value  | varint byte length, then the value as compact JSON text           |
+///
+/// Keys are the escaped pointers, in strictly increasing byte order. The
+/// shared prefix never ends inside a UTF-8 sequence, so every suffix is valid
+/// UTF-8 on its own. Nothing follows the last entry.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::FlattenedDocument;
+/// use serde_json::json;
+///
+/// let doc = FlattenedDocument::from(&json!({ "user": { "name": "Jo", "nick": "jj" } }));
+///
+/// let mut encoded = Vec::new();
+/// jsonpointer_flatten::to_delta_encoded(&doc, &mut encoded).unwrap();
+///
+/// // "/user/nick" is stored as 7 shared bytes and "ick"
+/// assert_eq!(&encoded[..2], &[1, 4]);
+/// assert_eq!(jsonpointer_flatten::from_delta_encoded(&encoded[..]).unwrap(), doc);
+/// ```
+pub fn to_delta_encoded<W: Write>(doc: &FlattenedDocument, mut w: W) -> io::Result<()> {
+    let mut entries: Vec<(&String, &Value)> = doc.as_map().iter().collect();
+    entries.sort_unstable_by_key(|(key, _)| *key);
+
+    w.write_all(&[VERSION])?;
+    write_varint(&mut w, entries.len())?;
+    let mut previous = "";
+    let mut value = Vec::new();
+    for (key, entry) in entries {
+        let shared = shared_prefix(previous, key);
+        write_varint(&mut w, shared)?;
+        write_bytes(&mut w, &key.as_bytes()[shared..])?;
+        value.clear();
+        serde_json::to_writer(&mut value, entry)?;
+        write_bytes(&mut w, &value)?;
+        previous = key;
+    }
+    Ok(())
+}
+
+/// Read a document written by [`to_delta_encoded`]
+///
+/// Reading stops after the last entry, and is byte by byte, so wrap
+/// unbuffered readers in an [`io::BufReader`]. Keys must be valid pointers
+/// in increasing order, and anything else is an error.
+pub fn from_delta_encoded<R: Read>(mut r: R) -> Result<FlattenedDocument, DeltaError> {
+    let mut version = [0];
+    r.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(DeltaError::UnsupportedVersion(version[0]));
+    }
+
+    let count = read_varint(&mut r)?;
+    let mut entries = Map::new();
+    let mut previous = String::new();
+    for index in 0..count {
+        let invalid = |reason: String| DeltaError::InvalidEntry { index, reason };
+
+        let shared = read_varint(&mut r)?;
+        if !previous.is_char_boundary(shared) {
+            return Err(invalid(format!(
+                "shares {} bytes with a previous key of {}",
+                shared,
+                previous.len()
+            )));
+        }
+        let suffix = String::from_utf8(read_bytes(&mut r)?)
+            .map_err(|_| invalid("key is not valid UTF-8".to_owned()))?;
+        let mut key = previous[..shared].to_owned();
+        key.push_str(&suffix);
+        if index > 0 && key <= previous {
+            return Err(invalid(format!("key \"{}\" is out of order", key)));
+        }
+        pointer::validate(&key).map_err(|error| invalid(error.to_string()))?;
+
+        let value: Value = serde_json::from_slice(&read_bytes(&mut r)?)
+            .map_err(|error| invalid(format!("value of \"{}\": {}", key, error)))?;
+        entries.insert(key.clone(), value);
+        previous = key;
+    }
+    Ok(FlattenedDocument::from_map(entries))
+}
+
+/// Length of the longest common prefix of `a` and `b` that ends on a character boundary
+fn shared_prefix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    while !b.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+fn write_varint<W: Write>(w: &mut W, mut n: usize) -> io::Result<()> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(w, bytes.len())?;
+    w.write_all(bytes)
+}
+
+fn read_varint<R: Read>(r: &mut R) -> Result<usize, DeltaError> {
+    let mut n: usize = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        let group = (byte[0] & 0x7f) as usize;
+        if shift >= usize::BITS || (group << shift) >> shift != group {
+            return Err(DeltaError::Overflow);
+        }
+        n |= group << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+        shift += 7;
+    }
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>, DeltaError> {
+    let len = read_varint(r)?;
+    // read through `take` so a corrupt length can't allocate more than the input holds
+    let mut bytes = Vec::new();
+    r.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn encode(doc: &FlattenedDocument) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        to_delta_encoded(doc, &mut encoded).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn round_trips_exactly() {
+        let value = json!({
+            "": "root key",
+            "a~b": [1.5, -2, null, true],
+            "a/b": { "é": "ü", "éa": "x", "": {} },
+            "long": "x".repeat(300),
+            "unicode": "日本語"
+        });
+        let doc = FlattenedDocument::from(&value);
+
+        let decoded = from_delta_encoded(&encode(&doc)[..]).unwrap();
+
+        assert_eq!(decoded, doc);
+        assert_eq!(
+            from_delta_encoded(&encode(&FlattenedDocument::new())[..]).unwrap(),
+            FlattenedDocument::new()
+        );
+    }
+
+    #[test]
+    fn prefixes_end_on_character_boundaries() {
+        assert_eq!(shared_prefix("/é", "/è"), 1);
+        assert_eq!(shared_prefix("/ab", "/abc"), 3);
+        assert_eq!(shared_prefix("", "/a"), 0);
+    }
+
+    #[test]
+    fn wide_documents_shrink() {
+        let users: Vec<Value> = (0..500)
+            .map(|id| {
+                json!({
+                    "id": id,
+                    "name": format!("user {}", id),
+                    "address": { "street": "1 Main St", "city": "Springfield", "zip": "00000" },
+                    "preferences": { "notifications": { "email": true, "sms": false } }
+                })
+            })
+            .collect();
+        let doc = FlattenedDocument::from(&json!({ "users": users }));
+
+        let encoded = encode(&doc).len();
+        let json = serde_json::to_vec(&doc).unwrap().len();
+
+        // 43% of the JSON size here, as most of every key is shared with the one before
+        assert!(
+            encoded * 2 < json,
+            "{} bytes encoded, {} as JSON",
+            encoded,
+            json
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let doc = FlattenedDocument::from(&json!({ "a": 1, "b": 2 }));
+        let encoded = encode(&doc);
+
+        assert!(matches!(
+            from_delta_encoded(&[2, 0][..]),
+            Err(DeltaError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            from_delta_encoded(&encoded[..encoded.len() - 1]),
+            Err(DeltaError::Io(_))
+        ));
+        // "/a" again instead of "/b"
+        let mut repeated = encoded.clone();
+        let at = repeated.len() - 3;
+        repeated[at] = b'a';
+        assert!(matches!(
+            from_delta_encoded(&repeated[..]),
+            Err(DeltaError::InvalidEntry { index: 2, .. })
+        ));
+        assert!(matches!(
+            from_delta_encoded(&[1, 1, 0, 1, b'a', 1, b'1'][..]),
+            Err(DeltaError::InvalidEntry { index: 0, .. })
+        ));
+        assert!(matches!(
+            from_delta_encoded(
+                &[1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f][..]
+            ),
+            Err(DeltaError::Overflow)
+        ));
+    }
+}
@@ -0,0 +1,877 @@
+use crate::flattener::{entry_value, is_container};
+use crate::numbers::eq_normalized;
+use crate::{iter, pointer, PointerPattern};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write as _};
+
+/// A single difference between two flattened documents
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffEntry {
+    /// The pointer only exists in the new document
+    Added { pointer: String, value: Value },
+    /// The pointer only exists in the old document
+    Removed { pointer: String, value: Value },
+    /// The pointer exists in both documents with different values
+    Changed {
+        pointer: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl DiffEntry {
+    /// The pointer this entry is about
+    pub fn pointer(&self) -> &str {
+        match self {
+            DiffEntry::Added { pointer, .. }
+            | DiffEntry::Removed { pointer, .. }
+            | DiffEntry::Changed { pointer, .. } => pointer,
+        }
+    }
+}
+
+/// Differences between the flattened forms of two documents, see [`diff`]
+///
+/// Container entries take part like any other entry, so turning an object into
+/// an array shows up as a change of its placeholder from `{}` to `[]` next to
+/// the removed and added children.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlatDiff {
+    entries: Vec<DiffEntry>,
+    /// Entries equal in both documents below a container that turned from an
+    /// object into an array or back, which patches replacing it add again
+    retained: Vec<(String, Value)>,
+}
+
+impl FlatDiff {
+    /// All differences, ordered by [`pointer::compare`]
+    pub fn entries(&self) -> &[DiffEntry] {
+        &self.entries
+    }
+
+    /// Take all differences
+    pub fn into_entries(self) -> Vec<DiffEntry> {
+        self.entries
+    }
+
+    /// Number of differences
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether both documents are equal
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries only present in the new document
+    pub fn added(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().filter_map(|entry| match entry {
+            DiffEntry::Added { pointer, value } => Some((pointer.as_str(), value)),
+            _ => None,
+        })
+    }
+
+    /// Entries only present in the old document
+    pub fn removed(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().filter_map(|entry| match entry {
+            DiffEntry::Removed { pointer, value } => Some((pointer.as_str(), value)),
+            _ => None,
+        })
+    }
+
+    /// Entries present in both documents as `(pointer, old, new)`
+    pub fn changed(&self) -> impl Iterator<Item = (&str, &Value, &Value)> {
+        self.entries.iter().filter_map(|entry| match entry {
+            DiffEntry::Changed { pointer, old, new } => Some((pointer.as_str(), old, new)),
+            _ => None,
+        })
+    }
+
+    /// The differences taking the new document back to the old one
+    ///
+    /// Additions become removals and the other way round, and changes swap
+    /// their old and new values.
+    pub fn invert(&self) -> FlatDiff {
+        let entries = self
+            .entries
+            .iter()
+            .cloned()
+            .map(|entry| match entry {
+                DiffEntry::Added { pointer, value } => DiffEntry::Removed { pointer, value },
+                DiffEntry::Removed { pointer, value } => DiffEntry::Added { pointer, value },
+                DiffEntry::Changed { pointer, old, new } => DiffEntry::Changed {
+                    pointer,
+                    old: new,
+                    new: old,
+                },
+            })
+            .collect();
+        FlatDiff {
+            entries,
+            retained: self.retained.clone(),
+        }
+    }
+
+    /// Convert to an [RFC 6902](https://tools.ietf.org/html/rfc6902) JSON Patch
+    /// taking the old document to the new one
+    ///
+    /// Removals come first, deepest and highest index first, then replacements,
+    /// then additions in document order, parents before children. Arrays are
+    /// compared by index, so they only ever lose or gain elements at their end
+    /// and no operation shifts an index another one relies on. Containers are
+    /// added as `{}` or `[]` and filled by the operations that follow, and
+    /// entries below a removed or replaced container are left out or added
+    /// afresh, unchanged ones included when an object became an array or back.
+    pub fn to_json_patch(&self) -> Value {
+        let plan = self.patch_plan();
+        let mut operations: Vec<Value> = plan
+            .removes
+            .iter()
+            .rev()
+            .map(|ptr| remove_op(ptr))
+            .collect();
+        operations.extend(
+            plan.replaces
+                .iter()
+                .map(|(ptr, value)| replace_op(ptr, value)),
+        );
+        operations.extend(plan.adds.iter().map(|(ptr, value)| add_op(ptr, value)));
+        Value::Array(operations)
+    }
+
+    /// The operations of [`FlatDiff::to_json_patch`], grouped by kind
+    pub(crate) fn patch_plan(&self) -> PatchPlan<'_> {
+        let mut plan = PatchPlan::default();
+        // removed or replaced containers above the current entry
+        let mut dropped: Vec<&str> = Vec::new();
+        // always below a replaced container, so added in document order with the rest
+        let mut retained = self.retained.iter().peekable();
+
+        for entry in &self.entries {
+            let ptr = entry.pointer();
+            while let Some((key, value)) =
+                retained.next_if(|(key, _)| pointer::compare(key, ptr).is_lt())
+            {
+                plan.adds.push((key, value));
+            }
+            while dropped
+                .last()
+                .is_some_and(|parent| !pointer::starts_with(ptr, parent))
+            {
+                dropped.pop();
+            }
+            let in_dropped = !dropped.is_empty();
+
+            match entry {
+                DiffEntry::Removed { .. } => {
+                    if !in_dropped {
+                        plan.removes.push(ptr);
+                    }
+                    dropped.push(ptr);
+                }
+                DiffEntry::Changed { new, .. } => {
+                    if in_dropped {
+                        plan.adds.push((ptr, new));
+                    } else {
+                        plan.replaces.push((ptr, new));
+                    }
+                    dropped.push(ptr);
+                }
+                DiffEntry::Added { value, .. } => plan.adds.push((ptr, value)),
+            }
+        }
+        plan.adds
+            .extend(retained.map(|(key, value)| (key.as_str(), value)));
+        plan
+    }
+
+    /// Pointers of the entries a patch adds again although they didn't change
+    pub(crate) fn retained_pointers(&self) -> impl Iterator<Item = &str> {
+        self.retained.iter().map(|(pointer, _)| pointer.as_str())
+    }
+
+    /// The forward and reverse JSON Patches, see [`FlatDiff::to_json_patch`]
+    ///
+    /// The reverse patch is built from the old values the diff carries, so
+    /// applying the forward and then the reverse patch restores the old document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let old = json!({ "tags": ["a", "b", "c"] });
+    /// let new = json!({ "tags": ["a"], "title": "x" });
+    /// let (forward, reverse) = jsonpointer_flatten::diff(&old, &new).to_json_patch_pair();
+    ///
+    /// assert_eq!(forward, json!([
+    ///     { "op": "remove", "path": "/tags/2" },
+    ///     { "op": "remove", "path": "/tags/1" },
+    ///     { "op": "add", "path": "/title", "value": "x" }
+    /// ]));
+    ///
+    /// let mut doc = FlattenedDocument::from(&old);
+    /// doc.apply_patch(&forward).unwrap();
+    /// doc.apply_patch(&reverse).unwrap();
+    /// assert_eq!(doc.unflatten().unwrap(), old);
+    /// ```
+    pub fn to_json_patch_pair(&self) -> (Value, Value) {
+        (self.to_json_patch(), self.invert().to_json_patch())
+    }
+
+    /// Render one line per difference, ordered by [`pointer::compare`]
+    ///
+    /// Changes are written as `~ /pointer: old -> new`, additions as
+    /// `+ /pointer: value` and removals as `- /pointer`, with values as
+    /// compact JSON. Lines are separated by `\n`, without a trailing newline.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::DiffTextOptions;
+    /// use serde_json::json;
+    ///
+    /// let old = json!({ "zip": "00000", "nickname": "Jo" });
+    /// let new = json!({ "zip": "00001", "note": "a long note" });
+    /// let diff = jsonpointer_flatten::diff(&old, &new);
+    ///
+    /// assert_eq!(
+    ///     diff.to_text(&DiffTextOptions::new().max_value_len(8)),
+    ///     "- /nickname\n+ /note: \"a long ...\n~ /zip: \"00000\" -> \"00001\""
+    /// );
+    /// ```
+    pub fn to_text(&self, options: &DiffTextOptions) -> String {
+        let mut entries: Vec<&DiffEntry> = self.entries.iter().collect();
+        entries.sort_by(|a, b| pointer::compare(a.pointer(), b.pointer()));
+
+        let mut text = String::new();
+        for (idx, entry) in entries.into_iter().enumerate() {
+            if idx > 0 {
+                text.push('\n');
+            }
+            if options.color {
+                text.push_str(match entry {
+                    DiffEntry::Added { .. } => "\x1b[32m",
+                    DiffEntry::Removed { .. } => "\x1b[31m",
+                    DiffEntry::Changed { .. } => "\x1b[33m",
+                });
+            }
+            let _ = match entry {
+                DiffEntry::Added { pointer, value } => {
+                    write!(text, "+ {}: {}", pointer, options.render(value))
+                }
+                DiffEntry::Removed { pointer, .. } => write!(text, "- {}", pointer),
+                DiffEntry::Changed { pointer, old, new } => write!(
+                    text,
+                    "~ {}: {} -> {}",
+                    pointer,
+                    options.render(old),
+                    options.render(new)
+                ),
+            };
+            if options.color {
+                text.push_str("\x1b[0m");
+            }
+        }
+        text
+    }
+
+    /// Count the differences under each prefix of `depth` segments
+    ///
+    /// Pointers with fewer segments are their own prefix. Prefixes are cut
+    /// between escaped segments, so `/a~1b` never shares a group with `/a`.
+    /// Rows are sorted by [`PrefixSummary::total`], largest first, then by
+    /// [`pointer::compare`], and a grand-total row without a prefix comes last.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_json::json;
+    ///
+    /// let old = json!({ "users": { "a": 1, "b": 2 }, "a/b": 0 });
+    /// let new = json!({ "users": { "a": 3, "c": 4 }, "a/b": 1, "z": true });
+    /// let summary = jsonpointer_flatten::diff(&old, &new).summary(1);
+    ///
+    /// assert_eq!(summary[0].prefix.as_deref(), Some("/users"));
+    /// assert_eq!((summary[0].added, summary[0].removed, summary[0].changed), (1, 1, 1));
+    /// assert_eq!(summary[1].prefix.as_deref(), Some("/a~1b"));
+    /// assert_eq!(summary[3].prefix, None);
+    /// assert_eq!(summary[3].total(), 5);
+    /// ```
+    pub fn summary(&self, depth: usize) -> Vec<PrefixSummary> {
+        let mut rows: Vec<PrefixSummary> = Vec::new();
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut total = PrefixSummary::default();
+        for entry in &self.entries {
+            let prefix = prefix_of(entry.pointer(), depth);
+            let idx = *index.entry(prefix).or_insert_with(|| {
+                rows.push(PrefixSummary {
+                    prefix: Some(prefix.to_owned()),
+                    ..PrefixSummary::default()
+                });
+                rows.len() - 1
+            });
+            rows[idx].count(entry);
+            total.count(entry);
+        }
+
+        rows.sort_by(|a, b| {
+            b.total().cmp(&a.total()).then_with(|| {
+                pointer::compare(
+                    a.prefix.as_deref().unwrap_or(""),
+                    b.prefix.as_deref().unwrap_or(""),
+                )
+            })
+        });
+        rows.push(total);
+        rows
+    }
+}
+
+/// Counts of the differences under one prefix, see [`FlatDiff::summary`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PrefixSummary {
+    /// The shared prefix, `None` on the grand-total row
+    pub prefix: Option<String>,
+    /// Number of pointers only in the new document
+    pub added: usize,
+    /// Number of pointers only in the old document
+    pub removed: usize,
+    /// Number of pointers whose value changed
+    pub changed: usize,
+}
+
+impl PrefixSummary {
+    /// Number of differences of any kind
+    pub fn total(&self) -> usize {
+        self.added + self.removed + self.changed
+    }
+
+    fn count(&mut self, entry: &DiffEntry) {
+        match entry {
+            DiffEntry::Added { .. } => self.added += 1,
+            DiffEntry::Removed { .. } => self.removed += 1,
+            DiffEntry::Changed { .. } => self.changed += 1,
+        }
+    }
+}
+
+/// The first `depth` segments of `pointer`, which is escaped so splitting at `/` is segment-aware
+fn prefix_of(pointer: &str, depth: usize) -> &str {
+    match pointer.match_indices('/').nth(depth) {
+        Some((end, _)) => &pointer[..end],
+        None => pointer,
+    }
+}
+
+impl fmt::Display for FlatDiff {
+    /// Plain [`FlatDiff::to_text`] with default options
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text(&DiffTextOptions::default()))
+    }
+}
+
+/// Operations of a JSON Patch before they are serialized
+#[derive(Default)]
+pub(crate) struct PatchPlan<'a> {
+    /// Pointers to remove in document order, applied in reverse
+    pub(crate) removes: Vec<&'a str>,
+    pub(crate) replaces: Vec<(&'a str, &'a Value)>,
+    /// Values to add in document order, containers as placeholders before their children
+    pub(crate) adds: Vec<(&'a str, &'a Value)>,
+}
+
+pub(crate) fn remove_op(ptr: &str) -> Value {
+    json!({ "op": "remove", "path": ptr })
+}
+
+pub(crate) fn replace_op(ptr: &str, value: &Value) -> Value {
+    json!({ "op": "replace", "path": ptr, "value": value })
+}
+
+pub(crate) fn add_op(ptr: &str, value: &Value) -> Value {
+    json!({ "op": "add", "path": ptr, "value": value })
+}
+
+/// Options for [`FlatDiff::to_text`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffTextOptions {
+    color: bool,
+    max_value_len: Option<usize>,
+}
+
+impl DiffTextOptions {
+    /// Create options for plain text with values in full
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color lines with ANSI escapes: green additions, red removals and yellow changes
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Cut rendered values after `max` characters, marking the cut with `...`
+    pub fn max_value_len(mut self, max: usize) -> Self {
+        self.max_value_len = Some(max);
+        self
+    }
+
+    fn render(&self, value: &Value) -> String {
+        let mut rendered = value.to_string();
+        if let Some(max) = self.max_value_len {
+            if let Some((cut, _)) = rendered.char_indices().nth(max) {
+                rendered.truncate(cut);
+                rendered.push_str("...");
+            }
+        }
+        rendered
+    }
+}
+
+/// Options for [`diff_with`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    ignore: Vec<PointerPattern>,
+    normalize_numbers: bool,
+}
+
+impl DiffOptions {
+    /// Create options that compare every entry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave out pointers matching any of `patterns`, together with everything below them
+    ///
+    /// Ignored containers are not descended into. Calling this again replaces
+    /// the patterns, and an empty `Vec` compares every entry again.
+    pub fn ignore(mut self, patterns: Vec<PointerPattern>) -> Self {
+        self.ignore = patterns;
+        self
+    }
+
+    /// Compare numbers by value, see [`normalize_number`](crate::normalize_number)
+    ///
+    /// Changes still report the values as they are in the documents.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::DiffOptions;
+    /// use serde_json::json;
+    ///
+    /// let options = DiffOptions::new().normalize_numbers(true);
+    ///
+    /// assert!(jsonpointer_flatten::diff_with(&json!([1, -0.0]), &json!([1.0, 0]), &options).is_empty());
+    /// ```
+    pub fn normalize_numbers(mut self, normalize: bool) -> Self {
+        self.normalize_numbers = normalize;
+        self
+    }
+
+    pub(crate) fn is_ignored(&self, pointer: &str) -> bool {
+        self.ignore.iter().any(|pattern| pattern.matches(pointer))
+    }
+
+    /// Whether two entries are equal, by value with [`DiffOptions::normalize_numbers`]
+    pub(crate) fn eq(&self, old: &Value, new: &Value) -> bool {
+        if self.normalize_numbers {
+            eq_normalized(old, new)
+        } else {
+            old == new
+        }
+    }
+}
+
+/// Compare the flattened forms of two documents
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::DiffEntry;
+/// use serde_json::json;
+///
+/// let old = json!({ "name": "John", "zip": "00000" });
+/// let new = json!({ "zip": "00001", "phones": ["123"] });
+///
+/// let diff = jsonpointer_flatten::diff(&old, &new);
+///
+/// assert_eq!(diff.removed().collect::<Vec<_>>(), vec![("/name", &json!("John"))]);
+/// assert_eq!(diff.added().count(), 2);
+/// assert_eq!(
+///     diff.changed().collect::<Vec<_>>(),
+///     vec![("/zip", &json!("00000"), &json!("00001"))]
+/// );
+/// ```
+pub fn diff(old: &Value, new: &Value) -> FlatDiff {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+/// Compare the flattened forms of two documents with options
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{DiffOptions, PointerPattern};
+/// use serde_json::json;
+///
+/// let old = json!({ "id": 1, "meta": { "request_id": "a" } });
+/// let new = json!({ "id": 1, "meta": { "request_id": "b" } });
+/// let options = DiffOptions::new().ignore(vec![PointerPattern::new("/meta/request_id").unwrap()]);
+///
+/// assert!(jsonpointer_flatten::diff_with(&old, &new, &options).is_empty());
+/// ```
+pub fn diff_with(old: &Value, new: &Value, options: &DiffOptions) -> FlatDiff {
+    let old = flatten_for_diff(old, options);
+    let mut new = flatten_for_diff(new, options);
+    let mut entries = Vec::new();
+    // containers that are an object in one document and an array in the other
+    let rekinded: HashSet<String> = old
+        .iter()
+        .filter(|(pointer, old)| {
+            is_container(old)
+                && new
+                    .get(*pointer)
+                    .is_some_and(|new| is_container(new) && new != *old)
+        })
+        .map(|(pointer, _)| pointer.clone())
+        .collect();
+    let mut retained = Vec::new();
+
+    for (pointer, old) in old {
+        match new.remove(&pointer) {
+            Some(new) if options.eq(&old, &new) => {
+                if !rekinded.is_empty() && below_any(&pointer, &rekinded) {
+                    retained.push((pointer, new));
+                }
+            }
+            Some(new) => entries.push(DiffEntry::Changed { pointer, old, new }),
+            None => entries.push(DiffEntry::Removed {
+                pointer,
+                value: old,
+            }),
+        }
+    }
+    for (pointer, value) in new {
+        entries.push(DiffEntry::Added { pointer, value });
+    }
+
+    entries.sort_by(|a, b| pointer::compare(a.pointer(), b.pointer()));
+    retained.sort_by(|(a, _), (b, _)| pointer::compare(a, b));
+    FlatDiff { entries, retained }
+}
+
+/// Whether a strict ancestor of `ptr` is in `containers`
+fn below_any(ptr: &str, containers: &HashSet<String>) -> bool {
+    let mut parent = pointer::parent(ptr);
+    while let Some(ptr) = parent {
+        if containers.contains(ptr) {
+            return true;
+        }
+        parent = pointer::parent(ptr);
+    }
+    false
+}
+
+fn flatten_for_diff(value: &Value, options: &DiffOptions) -> Map<String, Value> {
+    let mut entries = Map::new();
+    let mut iter = iter(value);
+
+    while let Some((pointer, node)) = iter.next() {
+        if options.is_ignored(&pointer) {
+            iter.skip_children();
+            continue;
+        }
+        entries.insert(pointer, entry_value(node));
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FlattenedDocument;
+    use serde_json::json;
+
+    fn patterns(patterns: &[&str]) -> Vec<PointerPattern> {
+        patterns
+            .iter()
+            .map(|p| PointerPattern::new(p).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn diff_reports_every_kind() {
+        let old = json!({ "a": 1, "b": [1, 2], "c": { "d": true } });
+        let new = json!({ "a": 2, "b": [1], "c": [] });
+
+        let actual = diff(&old, &new);
+
+        assert_eq!(
+            actual.entries(),
+            &[
+                DiffEntry::Changed {
+                    pointer: "/a".to_owned(),
+                    old: json!(1),
+                    new: json!(2)
+                },
+                DiffEntry::Removed {
+                    pointer: "/b/1".to_owned(),
+                    value: json!(2)
+                },
+                DiffEntry::Changed {
+                    pointer: "/c".to_owned(),
+                    old: json!({}),
+                    new: json!([])
+                },
+                DiffEntry::Removed {
+                    pointer: "/c/d".to_owned(),
+                    value: json!(true)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_equal_documents_is_empty() {
+        let value = json!({ "a": [1, { "b": null }] });
+
+        assert!(diff(&value, &value.clone()).is_empty());
+    }
+
+    #[test]
+    fn ignored_fields_produce_empty_diff() {
+        let old = json!({
+            "id": 7,
+            "meta": { "request_id": "r-1" },
+            "timestamps": { "created": 1, "updated": 2 },
+            "debug": { "trace": ["a", "b"], "host": "x" }
+        });
+        let new = json!({
+            "id": 7,
+            "meta": { "request_id": "r-2" },
+            "timestamps": { "created": 3, "updated": 4 },
+            "debug": { "trace": [] }
+        });
+        let options =
+            DiffOptions::new().ignore(patterns(&["/meta/request_id", "/timestamps/*", "/debug"]));
+
+        assert!(!diff(&old, &new).is_empty());
+        assert!(diff_with(&old, &new, &options).is_empty());
+    }
+
+    #[test]
+    fn ignored_container_suppresses_added_descendants() {
+        let old = json!({ "a": 1 });
+        let new = json!({ "a": 1, "debug": { "x": [1, 2] } });
+        let options = DiffOptions::new().ignore(patterns(&["/debug"]));
+
+        assert!(diff_with(&old, &new, &options).is_empty());
+        let replaced = options.ignore(patterns(&["/a"]));
+        assert_eq!(diff_with(&old, &new, &replaced).len(), 4);
+    }
+
+    #[test]
+    fn text_snapshots() {
+        let old = json!({
+            "address": { "zip": "00000" },
+            "nickname": "Jo",
+            "phones": ["123", "456"]
+        });
+        let new = json!({
+            "address": { "zip": "00001" },
+            "phones": ["123", "456", "789"],
+            "tags": { "x": null }
+        });
+        let actual = diff(&old, &new);
+
+        assert_eq!(
+            actual.to_string(),
+            [
+                r#"~ /address/zip: "00000" -> "00001""#,
+                r#"- /nickname"#,
+                r#"+ /phones/2: "789""#,
+                r#"+ /tags: {}"#,
+                r#"+ /tags/x: null"#,
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            actual.to_text(&DiffTextOptions::new().color(true).max_value_len(3)),
+            [
+                "\x1b[33m~ /address/zip: \"00... -> \"00...\x1b[0m",
+                "\x1b[31m- /nickname\x1b[0m",
+                "\x1b[32m+ /phones/2: \"78...\x1b[0m",
+                "\x1b[32m+ /tags: {}\x1b[0m",
+                "\x1b[32m+ /tags/x: nul...\x1b[0m",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn text_of_empty_diff_and_root_change() {
+        assert_eq!(diff(&json!(1), &json!(1)).to_string(), "");
+        assert_eq!(diff(&json!(1), &json!("ü")).to_string(), r#"~ : 1 -> "ü""#);
+        assert_eq!(
+            diff(&json!("üüüü"), &json!(null)).to_text(&DiffTextOptions::new().max_value_len(3)),
+            r#"~ : "üü... -> nul..."#
+        );
+    }
+
+    #[test]
+    fn entries_are_in_numeric_order() {
+        let old = json!({ "arr": [] });
+        let new = json!({ "arr": (0..11).collect::<Vec<_>>() });
+
+        let actual = diff(&old, &new);
+        let pointers: Vec<&str> = actual.added().map(|(p, _)| p).collect();
+
+        assert_eq!(pointers.first(), Some(&"/arr/0"));
+        assert_eq!(pointers.last(), Some(&"/arr/10"));
+    }
+
+    fn assert_round_trip(old: &Value, new: &Value) {
+        let (forward, reverse) = diff(old, new).to_json_patch_pair();
+        let mut doc = crate::FlattenedDocument::from(old);
+
+        doc.apply_patch(&forward).unwrap();
+        assert_eq!(doc.unflatten().as_ref(), Ok(new), "forward {}", forward);
+        doc.apply_patch(&reverse).unwrap();
+        assert_eq!(doc.unflatten().as_ref(), Ok(old), "reverse {}", reverse);
+    }
+
+    #[test]
+    fn patch_pair_round_trips() {
+        let cases = [
+            (json!({ "a": [1, 2, 3] }), json!({ "a": [1] })),
+            (
+                json!({ "a": [[1, 2], [3, 4, 5]], "b": { "c": [true] } }),
+                json!({ "a": [[1], [3, 4, 5, 6], [7]], "b": { "c": [false, null] } }),
+            ),
+            (
+                json!({ "x": { "0": "a", "y": [1] } }),
+                json!({ "x": ["b", { "z": [] }] }),
+            ),
+            (json!([{ "a": 1 }, 2]), json!("scalar")),
+            (
+                json!({ "a": "x~/y", "b~c": {} }),
+                json!({ "a/b": [1], "b~c": [] }),
+            ),
+        ];
+
+        for (old, new) in &cases {
+            assert_round_trip(old, new);
+            assert_round_trip(new, old);
+        }
+    }
+
+    #[test]
+    fn invert_swaps_entries() {
+        let forward = diff(&json!({ "a": 1, "b": 2 }), &json!({ "b": 3, "c": 4 }));
+        let backward = diff(&json!({ "b": 3, "c": 4 }), &json!({ "a": 1, "b": 2 }));
+
+        assert_eq!(forward.invert(), backward);
+        assert_eq!(forward.invert().invert(), forward);
+    }
+
+    #[test]
+    fn kind_change_replaces_container() {
+        let old = json!({ "x": { "0": 1 } });
+        let new = json!({ "x": [2] });
+
+        assert_eq!(
+            diff(&old, &new).to_json_patch(),
+            json!([
+                { "op": "replace", "path": "/x", "value": [] },
+                { "op": "add", "path": "/x/0", "value": 2 }
+            ])
+        );
+    }
+
+    #[test]
+    fn kind_change_adds_unchanged_children_again() {
+        let old = json!({ "x": { "0": 1, "1": { "a": [true] } }, "y": 0 });
+        let new = json!({ "x": [1, { "a": [true] }, 2], "y": 0 });
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.to_json_patch(),
+            json!([
+                { "op": "replace", "path": "/x", "value": [] },
+                { "op": "add", "path": "/x/0", "value": 1 },
+                { "op": "add", "path": "/x/1", "value": {} },
+                { "op": "add", "path": "/x/1/a", "value": [] },
+                { "op": "add", "path": "/x/1/a/0", "value": true },
+                { "op": "add", "path": "/x/2", "value": 2 }
+            ])
+        );
+        let (forward, reverse) = diff.to_json_patch_pair();
+        let mut doc = FlattenedDocument::from(&old);
+        doc.apply_patch(&forward).unwrap();
+        assert_eq!(doc.unflatten(), Ok(new));
+        doc.apply_patch(&reverse).unwrap();
+        assert_eq!(doc.unflatten(), Ok(old));
+    }
+
+    #[test]
+    fn summary_groups_whole_segments() {
+        let old = json!({ "a": { "x": 1, "y": [1, 2, 3] }, "a~b": 1, "ab": 1 });
+        let new = json!({ "a": { "x": 2, "y": [1] }, "a~b": 2, "ab": 1, "": null });
+
+        let summary = diff(&old, &new).summary(2);
+        let rows: Vec<(Option<&str>, usize, usize, usize)> = summary
+            .iter()
+            .map(|row| (row.prefix.as_deref(), row.added, row.removed, row.changed))
+            .collect();
+
+        assert_eq!(
+            rows,
+            vec![
+                (Some("/a/y"), 0, 2, 0),
+                (Some("/"), 1, 0, 0),
+                (Some("/a/x"), 0, 0, 1),
+                (Some("/a~0b"), 0, 0, 1),
+                (None, 1, 2, 2),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&summary[0]).unwrap(),
+            json!({ "prefix": "/a/y", "added": 0, "removed": 2, "changed": 0 })
+        );
+    }
+
+    #[test]
+    fn summary_of_no_differences_is_a_zero_total() {
+        let value = json!({ "a": 1 });
+
+        assert_eq!(
+            diff(&value, &value).summary(1),
+            vec![PrefixSummary::default()]
+        );
+    }
+
+    #[test]
+    fn normalized_numbers_only_change_by_value() {
+        let old = json!({ "a": 1, "b": -0.0, "c": 1e2, "d": 9007199254740993u64, "e": 1.5 });
+        let new = json!({ "a": 1.0, "b": 0, "c": 100, "d": 9007199254740992.0, "e": 1.5 });
+
+        assert_eq!(diff(&old, &new).len(), 4);
+        let diff = diff_with(&old, &new, &DiffOptions::new().normalize_numbers(true));
+        assert_eq!(
+            diff.changed().collect::<Vec<_>>(),
+            vec![(
+                "/d",
+                &json!(9007199254740993u64),
+                &json!(9007199254740992.0)
+            )]
+        );
+    }
+}
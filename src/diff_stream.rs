@@ -0,0 +1,289 @@
+use crate::flattener::{entry_value, is_container};
+use crate::pointer::{compare_segments, escape_into};
+use crate::{DiffEntry, DiffOptions};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+/// Lazy iterator over the differences of two documents, see [`diff_streaming`]
+pub struct DiffStream<'v> {
+    options: DiffOptions,
+    /// Pointer of the node compared last
+    pointer: String,
+    /// The root, until it is compared
+    root: Option<(&'v Value, &'v Value)>,
+    /// Containers on the path to the current node, the innermost on top
+    stack: Vec<Frame<'v>>,
+}
+
+/// A container being compared, with the children not compared yet
+struct Frame<'v> {
+    /// Length of the container's pointer
+    len: usize,
+    children: std::vec::IntoIter<Child<'v>>,
+}
+
+/// A segment with the nodes found below it in the old and new document
+struct Child<'v> {
+    segment: Segment<'v>,
+    old: Option<&'v Value>,
+    new: Option<&'v Value>,
+}
+
+/// Unescaped segment of an array element or object member
+#[derive(Clone, Copy)]
+enum Segment<'v> {
+    Index(usize),
+    Key(&'v str),
+}
+
+impl Segment<'_> {
+    fn compare(self, other: Segment<'_>) -> Ordering {
+        match (self, other) {
+            (Segment::Index(a), Segment::Index(b)) => a.cmp(&b),
+            (Segment::Key(a), Segment::Key(b)) => compare_segments(a, b),
+            // only when a container is an object in one document and an array in the other
+            (Segment::Index(a), Segment::Key(b)) => compare_segments(&a.to_string(), b),
+            (Segment::Key(a), Segment::Index(b)) => compare_segments(a, &b.to_string()),
+        }
+    }
+}
+
+/// Lazily compare two documents without flattening either of them
+///
+/// Both trees are walked in lockstep, matching object keys and array indices.
+/// Only the pointer of the current node is held in memory, along with, for
+/// every container on the path to it, references to the children left to
+/// compare, sorted. The entries are exactly those of [`diff`](crate::diff),
+/// in the same order: the union of both documents in pre-order, siblings
+/// sorted by [`pointer::compare`](crate::pointer::compare).
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::DiffEntry;
+/// use serde_json::json;
+///
+/// let old = json!({ "name": "John", "zip": "00000" });
+/// let new = json!({ "zip": "00001" });
+///
+/// let mut entries = jsonpointer_flatten::diff_streaming(&old, &new);
+///
+/// assert_eq!(
+///     entries.next(),
+///     Some(DiffEntry::Removed { pointer: "/name".to_owned(), value: json!("John") })
+/// );
+/// assert_eq!(entries.next().unwrap().pointer(), "/zip");
+/// assert_eq!(entries.next(), None);
+/// ```
+pub fn diff_streaming<'v>(old: &'v Value, new: &'v Value) -> DiffStream<'v> {
+    diff_streaming_with(old, new, &DiffOptions::default())
+}
+
+/// Lazily compare two documents with options, see [`diff_streaming`] and [`diff_with`](crate::diff_with)
+pub fn diff_streaming_with<'v>(
+    old: &'v Value,
+    new: &'v Value,
+    options: &DiffOptions,
+) -> DiffStream<'v> {
+    DiffStream {
+        options: options.clone(),
+        pointer: String::new(),
+        root: Some((old, new)),
+        stack: Vec::new(),
+    }
+}
+
+impl<'v> DiffStream<'v> {
+    /// Compare the nodes at the current pointer, opening a frame for their children
+    fn visit(&mut self, old: Option<&'v Value>, new: Option<&'v Value>) -> Option<DiffEntry> {
+        if self.options.is_ignored(&self.pointer) {
+            return None;
+        }
+        let mut merged: Vec<Child<'v>> = children(old, true).chain(children(new, false)).collect();
+        if !merged.is_empty() {
+            // stable, so a segment present in both has its old node right before its new one
+            merged.sort_by(|a, b| a.segment.compare(b.segment));
+            merged.dedup_by(|child, kept| {
+                let same = child.segment.compare(kept.segment) == Ordering::Equal;
+                if same {
+                    kept.new = child.new;
+                }
+                same
+            });
+            self.stack.push(Frame {
+                len: self.pointer.len(),
+                children: merged.into_iter(),
+            });
+        }
+
+        match (old, new) {
+            (Some(old), Some(new)) if same_entry(old, new, &self.options) => None,
+            (Some(old), Some(new)) => Some(DiffEntry::Changed {
+                pointer: self.pointer.clone(),
+                old: entry_value(old),
+                new: entry_value(new),
+            }),
+            (Some(old), None) => Some(DiffEntry::Removed {
+                pointer: self.pointer.clone(),
+                value: entry_value(old),
+            }),
+            (None, Some(new)) => Some(DiffEntry::Added {
+                pointer: self.pointer.clone(),
+                value: entry_value(new),
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+impl<'v> Iterator for DiffStream<'v> {
+    type Item = DiffEntry;
+
+    fn next(&mut self) -> Option<DiffEntry> {
+        if let Some((old, new)) = self.root.take() {
+            if let Some(entry) = self.visit(Some(old), Some(new)) {
+                return Some(entry);
+            }
+        }
+        while let Some(frame) = self.stack.last_mut() {
+            let child = match frame.children.next() {
+                Some(child) => child,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            self.pointer.truncate(frame.len);
+            match child.segment {
+                Segment::Index(idx) => {
+                    let _ = write!(self.pointer, "/{}", idx);
+                }
+                Segment::Key(key) => {
+                    self.pointer.push('/');
+                    escape_into(key, &mut self.pointer);
+                }
+            }
+            if let Some(entry) = self.visit(child.old, child.new) {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+/// The children of a container from one side, none for leaves and missing nodes
+fn children(value: Option<&Value>, old: bool) -> impl Iterator<Item = Child<'_>> {
+    let child = move |segment, value| Child {
+        segment,
+        old: Some(value).filter(|_| old),
+        new: Some(value).filter(|_| !old),
+    };
+    let (arr, obj) = match value {
+        Some(value) if !is_container(value) => (None, None),
+        Some(Value::Array(arr)) => (Some(arr), None),
+        Some(Value::Object(obj)) => (None, Some(obj)),
+        _ => (None, None),
+    };
+    let elements = arr
+        .into_iter()
+        .flat_map(|arr| arr.iter().enumerate())
+        .map(move |(idx, val)| child(Segment::Index(idx), val));
+    let members = obj
+        .into_iter()
+        .flat_map(|obj| obj.iter())
+        .map(move |(key, val)| child(Segment::Key(key), val));
+    elements.chain(members)
+}
+
+/// Whether two nodes flatten to the same entry, without copying leaves
+fn same_entry(old: &Value, new: &Value, options: &DiffOptions) -> bool {
+    if is_container(old) || is_container(new) {
+        entry_value(old) == entry_value(new)
+    } else {
+        options.eq(old, new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Rng;
+    use crate::{diff, diff_with, PointerPattern};
+    use serde_json::json;
+
+    #[test]
+    fn matches_map_based_diff_on_random_documents() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let options = DiffOptions::new().ignore(vec![
+            PointerPattern::new("/a/*").unwrap(),
+            PointerPattern::new("/10").unwrap(),
+        ]);
+        let normalized = DiffOptions::new().normalize_numbers(true);
+
+        for _ in 0..500 {
+            let old = rng.value(4);
+            let new = if rng.below(3) == 0 {
+                rng.value(4)
+            } else {
+                rng.mutate(&old, 3)
+            };
+
+            assert_eq!(
+                diff_streaming(&old, &new).collect::<Vec<_>>(),
+                diff(&old, &new).into_entries(),
+                "{} -> {}",
+                old,
+                new
+            );
+            assert_eq!(
+                diff_streaming_with(&old, &new, &options).collect::<Vec<_>>(),
+                diff_with(&old, &new, &options).into_entries(),
+                "{} -> {}",
+                old,
+                new
+            );
+            // some integers written as doubles, which only differ without normalizing
+            let mut new = new;
+            let _ = crate::visit_mut(&mut new, |_, leaf| {
+                if let Some(int) = leaf.as_u64().filter(|_| rng.below(2) == 0) {
+                    *leaf = json!(int as f64);
+                }
+                std::ops::ControlFlow::Continue(())
+            });
+            assert_eq!(
+                diff_streaming_with(&old, &new, &normalized).collect::<Vec<_>>(),
+                diff_with(&old, &new, &normalized).into_entries(),
+                "{} -> {}",
+                old,
+                new
+            );
+        }
+
+        let (old, new) = (
+            json!({ "a": 1, "b": [-0.0] }),
+            json!({ "a": 1.0, "b": [0] }),
+        );
+        assert_eq!(diff_streaming_with(&old, &new, &normalized).count(), 0);
+        assert_eq!(diff_streaming(&old, &new).count(), 2);
+    }
+
+    #[test]
+    fn kind_change_removes_and_adds_children() {
+        let old = json!({ "x": { "10": 1, "2": 2 } });
+        let new = json!({ "x": [0, 1, 5] });
+
+        let pointers: Vec<String> = diff_streaming(&old, &new)
+            .map(|entry| entry.pointer().to_owned())
+            .collect();
+
+        assert_eq!(pointers, vec!["/x", "/x/0", "/x/1", "/x/2", "/x/10"]);
+    }
+
+    #[test]
+    fn equal_documents_yield_nothing() {
+        let value = json!({ "a": [1, { "b": null }], "c~d": "e" });
+
+        assert_eq!(diff_streaming(&value, &value).next(), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{pointer, FlattenedDocument};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Renders a [`FlattenedDocument`] as an aligned table, see [`FlattenedDocument::display_table`]
+#[derive(Debug, Clone, Copy)]
+pub struct TableDisplay<'a> {
+    document: &'a FlattenedDocument,
+    max_value_width: usize,
+}
+
+impl TableDisplay<'_> {
+    /// Elide values longer than `chars` characters with `…` (60 by default, at least 1)
+    pub fn max_value_width(mut self, chars: usize) -> Self {
+        self.max_value_width = chars.max(1);
+        self
+    }
+}
+
+impl FlattenedDocument {
+    /// Display the entries as a two-column table of pointers and values
+    ///
+    /// Rows are sorted by [`pointer::compare`](crate::pointer::compare) and the
+    /// root pointer shows as `""`. Values are rendered as JSON and elided past
+    /// [`TableDisplay::max_value_width`], and container placeholders show how
+    /// many children follow, like `{… 2 keys}` or `[… 1 item]`. Widths count
+    /// characters, so columns only line up in a terminal as long as every
+    /// character takes a single cell: wide glyphs like CJK push their row right.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "name": "Jo", "tags": ["a", "b"] }));
+    ///
+    /// assert_eq!(
+    ///     doc.display_table().to_string(),
+    ///     r#"pointer  value
+    /// -------  -----------
+    /// ""       {… 2 keys}
+    /// /name    "Jo"
+    /// /tags    [… 2 items]
+    /// /tags/0  "a"
+    /// /tags/1  "b""#
+    /// );
+    /// ```
+    pub fn display_table(&self) -> TableDisplay<'_> {
+        TableDisplay {
+            document: self,
+            max_value_width: 60,
+        }
+    }
+
+    /// [`FlattenedDocument::display_table`] with the default width, as a string
+    pub fn to_table_string(&self) -> String {
+        self.display_table().to_string()
+    }
+}
+
+impl fmt::Display for TableDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut children: HashMap<&str, usize> = HashMap::new();
+        for (key, _) in self.document {
+            if let Some(parent) = pointer::parent(key) {
+                *children.entry(parent).or_default() += 1;
+            }
+        }
+
+        let rows: Vec<(&str, String)> = self
+            .document
+            .sorted_iter()
+            .map(|(key, value)| {
+                let count = children.get(key).copied().unwrap_or(0);
+                let key = if key.is_empty() { "\"\"" } else { key };
+                (key, self.cell(value, count))
+            })
+            .collect();
+
+        let width = |text: &str| text.chars().count();
+        let pointer_width = rows
+            .iter()
+            .map(|(key, _)| width(key))
+            .chain(Some(width("pointer")))
+            .max()
+            .unwrap_or(0);
+        let value_width = rows
+            .iter()
+            .map(|(_, cell)| width(cell))
+            .chain(Some(width("value")))
+            .max()
+            .unwrap_or(0);
+
+        write!(f, "{:<w$}  value", "pointer", w = pointer_width)?;
+        write!(
+            f,
+            "\n{}  {}",
+            "-".repeat(pointer_width),
+            "-".repeat(value_width)
+        )?;
+        for (key, cell) in rows {
+            write!(f, "\n{:<w$}  {}", key, cell, w = pointer_width)?;
+        }
+        Ok(())
+    }
+}
+
+impl TableDisplay<'_> {
+    fn cell(&self, value: &Value, children: usize) -> String {
+        let placeholder = |open: char, close: char, noun: &str| {
+            let plural = if children == 1 { "" } else { "s" };
+            format!("{}… {} {}{}{}", open, children, noun, plural, close)
+        };
+        let rendered = match value {
+            Value::Object(obj) if obj.is_empty() && children > 0 => placeholder('{', '}', "key"),
+            Value::Array(arr) if arr.is_empty() && children > 0 => placeholder('[', ']', "item"),
+            _ => value.to_string(),
+        };
+        match rendered.char_indices().nth(self.max_value_width - 1) {
+            Some((cut, _)) if rendered.chars().count() > self.max_value_width => {
+                format!("{}…", &rendered[..cut])
+            }
+            _ => rendered,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn table_snapshot() {
+        let doc = FlattenedDocument::from(&json!({
+            "a/b": [10, [], { "c": null }],
+            "long": "abcdefghijklmnopqrstuvwxyz",
+            "m~n": {}
+        }));
+
+        assert_eq!(
+            doc.display_table().max_value_width(12).to_string(),
+            r#"pointer    value
+---------  ------------
+""         {… 3 keys}
+/a~1b      [… 3 items]
+/a~1b/0    10
+/a~1b/1    []
+/a~1b/2    {… 1 key}
+/a~1b/2/c  null
+/long      "abcdefghij…
+/m~0n      {}"#
+        );
+    }
+
+    #[test]
+    fn rows_follow_numeric_pointer_order_and_pad_by_characters() {
+        let entries: Vec<Value> = (0..11).map(|idx| json!(idx)).collect();
+        let doc = FlattenedDocument::from(&json!({ "é": entries }));
+
+        let table = doc.to_table_string();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines[3], "/é       [… 11 items]");
+        assert_eq!(lines[4], "/é/0     0");
+        assert_eq!(lines[13], "/é/9     9");
+        assert_eq!(lines[14], "/é/10    10");
+    }
+
+    #[test]
+    fn empty_document_has_only_the_header() {
+        assert_eq!(
+            FlattenedDocument::new().to_table_string(),
+            "pointer  value\n-------  -----"
+        );
+    }
+}
@@ -0,0 +1,1010 @@
+use crate::error::{AccessError, ConversionError, UnflattenError};
+use crate::flattener::is_container;
+use crate::pointer::{self, AsPointer};
+use crate::{Flattener, PointerPattern, UnflattenOptions};
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, Error as _};
+use serde::Serialize;
+use serde_json::{map, Map, Value};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FusedIterator;
+
+/// A flattened JSON document, mapping pointers to values
+///
+/// Serializes as a plain object of pointer keys, in the order of
+/// [`FlattenedDocument::iter`], just like [`from_json`](crate::from_json) output.
+/// Deserializing checks that every key is a valid pointer, see
+/// [`StrictDocument`] to also check that the entries form a document.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::FlattenedDocument;
+/// use serde_json::json;
+///
+/// let doc = FlattenedDocument::from(&json!({ "users": [{ "name": "John" }] }));
+///
+/// assert_eq!(doc.get("/users/0/name"), Some(&json!("John")));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct FlattenedDocument {
+    entries: Map<String, Value>,
+}
+
+impl FlattenedDocument {
+    /// Create an empty document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flatten a nested JSON value, the same as `FlattenedDocument::from(&value)`
+    ///
+    /// Converting a [`Value`] by value with [`TryFrom`] instead takes it for
+    /// flattened entries already, so this spells out which conversion is meant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    /// use std::convert::TryFrom;
+    ///
+    /// let nested = json!({ "a": { "b": 1 } });
+    ///
+    /// let doc = FlattenedDocument::from_nested(&nested);
+    ///
+    /// assert_eq!(doc.get("/a/b"), Some(&json!(1)));
+    /// assert_eq!(FlattenedDocument::try_from(serde_json::Value::from(doc.clone())), Ok(doc));
+    /// ```
+    pub fn from_nested(value: &Value) -> Self {
+        Self::from(value)
+    }
+
+    pub(crate) fn from_map(entries: Map<String, Value>) -> Self {
+        FlattenedDocument { entries }
+    }
+
+    pub(crate) fn entries_mut(&mut self) -> &mut Map<String, Value> {
+        &mut self.entries
+    }
+
+    /// Number of entries, containers included
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the document has no entries at all
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the value stored at `pointer`, see [`AsPointer`] for what it can be
+    pub fn get<P: AsPointer + ?Sized>(&self, pointer: &P) -> Option<&Value> {
+        self.entries.get(pointer.as_pointer())
+    }
+
+    /// Iterate over all `(pointer, value)` entries in map order
+    ///
+    /// That is sorted by key, or the order entries were flattened in with
+    /// serde_json's `preserve_order` feature.
+    pub fn iter(&self) -> Entries<'_> {
+        Entries {
+            map: &self.entries,
+            iter: self.entries.iter(),
+            front: 0,
+        }
+    }
+
+    /// All pointers in display order, see [`pointer::compare`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let value = json!({ "b": 0, "a": [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10] });
+    /// let doc = FlattenedDocument::from(&value);
+    ///
+    /// assert_eq!(doc.sorted_keys()[..5], ["", "/a", "/a/0", "/a/1", "/a/2"]);
+    /// assert_eq!(doc.sorted_keys()[12..], ["/a/10", "/b"]);
+    /// ```
+    pub fn sorted_keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.entries.keys().map(String::as_str).collect();
+        keys.sort_unstable_by(|a, b| pointer::compare(a, b));
+        keys
+    }
+
+    /// Iterate over all entries in display order, see [`pointer::compare`]
+    pub fn sorted_iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let mut entries: Vec<(&str, &Value)> = self.iter().collect();
+        entries.sort_unstable_by(|(a, _), (b, _)| pointer::compare(a, b));
+        entries.into_iter()
+    }
+
+    /// Borrow the underlying map
+    pub fn as_map(&self) -> &Map<String, Value> {
+        &self.entries
+    }
+
+    /// Take the underlying map
+    pub fn into_map(self) -> Map<String, Value> {
+        self.entries
+    }
+
+    /// Iterate over the entry at `prefix` and every entry below it
+    ///
+    /// Keys are kept as they are (not rebased) and matching is segment-aware,
+    /// see [`pointer::starts_with`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "user": { "id": 1 }, "username": "x" }));
+    /// let keys: Vec<&str> = doc.entries_under("/user").map(|(k, _)| k).collect();
+    ///
+    /// assert_eq!(keys, vec!["/user", "/user/id"]);
+    /// ```
+    pub fn entries_under<'a, P: AsPointer + ?Sized>(
+        &'a self,
+        prefix: &'a P,
+    ) -> impl Iterator<Item = (&'a str, &'a Value)> + 'a {
+        let prefix = prefix.as_pointer();
+        self.iter()
+            .filter(move |(key, _)| pointer::starts_with(key, prefix))
+    }
+
+    /// Like [`FlattenedDocument::entries_under`], but without the entry at `prefix` itself
+    pub fn entries_below<'a, P: AsPointer + ?Sized>(
+        &'a self,
+        prefix: &'a P,
+    ) -> impl Iterator<Item = (&'a str, &'a Value)> + 'a {
+        let prefix = prefix.as_pointer();
+        self.entries_under(prefix)
+            .filter(move |(key, _)| *key != prefix)
+    }
+
+    /// Find all entries whose pointer matches `pattern`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{FlattenedDocument, PointerPattern};
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "users": [{ "email": "a@b.c" }] }));
+    /// let pattern = PointerPattern::new("/users/*/email").unwrap();
+    ///
+    /// assert_eq!(doc.find(&pattern), vec![("/users/0/email", &json!("a@b.c"))]);
+    /// ```
+    pub fn find(&self, pattern: &PointerPattern) -> Vec<(&str, &Value)> {
+        self.iter()
+            .filter(|(key, _)| pattern.matches(key))
+            .collect()
+    }
+
+    /// Group entries by the pointer of their parent container
+    ///
+    /// The root entry has no parent and is left out. Entries keep their
+    /// iteration order within each group.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "a/b": { "c": 1 }, "d": 2 }));
+    /// let groups = doc.group_by_parent();
+    ///
+    /// assert_eq!(groups[""], vec![("/a~1b", &json!({})), ("/d", &json!(2))]);
+    /// assert_eq!(groups["/a~1b"], vec![("/a~1b/c", &json!(1))]);
+    /// ```
+    pub fn group_by_parent(&self) -> BTreeMap<&str, Vec<(&str, &Value)>> {
+        let mut groups: BTreeMap<&str, Vec<(&str, &Value)>> = BTreeMap::new();
+        for (key, value) in self.iter() {
+            if let Some(parent) = pointer::parent(key) {
+                groups.entry(parent).or_default().push((key, value));
+            }
+        }
+        groups
+    }
+
+    /// Group entries by depth, where index 0 holds the root entry
+    pub fn group_by_depth(&self) -> Vec<Vec<(&str, &Value)>> {
+        let mut groups: Vec<Vec<(&str, &Value)>> = Vec::new();
+        for (key, value) in self.iter() {
+            let depth = pointer::depth(key);
+            if groups.len() <= depth {
+                groups.resize_with(depth + 1, Vec::new);
+            }
+            groups[depth].push((key, value));
+        }
+        groups
+    }
+
+    /// Collect [`FlattenedDocument::entries_under`] into a map
+    pub fn collect_under<P: AsPointer + ?Sized>(&self, prefix: &P) -> Map<String, Value> {
+        collect(self.entries_under(prefix))
+    }
+
+    /// Collect [`FlattenedDocument::entries_below`] into a map
+    pub fn collect_below<P: AsPointer + ?Sized>(&self, prefix: &P) -> Map<String, Value> {
+        collect(self.entries_below(prefix))
+    }
+
+    /// Rebuild the nested document, see [`unflatten`](crate::unflatten)
+    pub fn unflatten(&self) -> Result<Value, UnflattenError> {
+        self.unflatten_with(&UnflattenOptions::default())
+    }
+
+    /// Rebuild the nested document, see [`unflatten_with`](crate::unflatten_with)
+    pub fn unflatten_with(&self, options: &UnflattenOptions) -> Result<Value, UnflattenError> {
+        crate::unflatten::unflatten_map(&self.entries, options)
+    }
+
+    /// Rebuild only the nested value at `prefix` from the entries under it
+    ///
+    /// Matching is segment-aware like [`FlattenedDocument::entries_under`], and
+    /// errors name pointers of this document rather than of the subtree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "id": 1, "settings": { "a/b": [true] } }));
+    ///
+    /// assert_eq!(doc.unflatten_under("/settings"), Ok(json!({ "a/b": [true] })));
+    /// assert_eq!(doc.unflatten_under("/settings/a~1b/0"), Ok(json!(true)));
+    /// ```
+    pub fn unflatten_under<P: AsPointer + ?Sized>(
+        &self,
+        prefix: &P,
+    ) -> Result<Value, UnflattenError> {
+        let prefix = prefix.as_pointer();
+        pointer::validate(prefix)?;
+        let subtree: Map<String, Value> = self
+            .entries_under(prefix)
+            .map(|(key, value)| (key[prefix.len()..].to_owned(), value.clone()))
+            .collect();
+        if subtree.is_empty() {
+            return Err(UnflattenError::NotFound {
+                pointer: prefix.to_owned(),
+            });
+        }
+        crate::unflatten::unflatten_map(&subtree, &UnflattenOptions::default())
+            .map_err(|error| error.under(prefix))
+    }
+
+    /// Deserialize the value at `pointer` into a `T`
+    ///
+    /// A leaf is deserialized as it is, and a container is first rebuilt from
+    /// the entries under it like [`FlattenedDocument::unflatten_under`] does.
+    /// Errors tell a missing pointer apart from a value of the wrong shape.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{AccessError, FlattenedDocument};
+    /// use serde::Deserialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Debug, PartialEq, Deserialize)]
+    /// struct Address {
+    ///     city: String,
+    ///     zip: String,
+    /// }
+    ///
+    /// let doc = FlattenedDocument::from(&json!({
+    ///     "name": "Jo",
+    ///     "address": { "city": "Springfield", "zip": "00000" }
+    /// }));
+    ///
+    /// let address: Address = doc.get_as("/address").unwrap();
+    ///
+    /// assert_eq!(address.city, "Springfield");
+    /// assert_eq!(doc.get_as::<String>("/name"), Ok("Jo".to_owned()));
+    /// assert!(matches!(doc.get_as::<u8>("/age"), Err(AccessError::NotFound { .. })));
+    /// ```
+    // `impl Trait` rather than a second parameter keeps `get_as::<T>(..)` working
+    pub fn get_as<T: DeserializeOwned>(
+        &self,
+        pointer: &(impl AsPointer + ?Sized),
+    ) -> Result<T, AccessError> {
+        let pointer = pointer.as_pointer();
+        let deserialized = match self.entries.get(pointer) {
+            Some(value) if !is_container(value) => T::deserialize(value),
+            _ => serde_json::from_value(self.unflatten_under(pointer)?),
+        };
+        deserialized.map_err(|error| AccessError::Deserialize {
+            pointer: pointer.to_owned(),
+            message: error.to_string(),
+        })
+    }
+
+    /// Replace the entries under `prefix` by a single entry holding their nested value
+    ///
+    /// The rest of the document stays flat, and the new entry takes the place
+    /// of the first replaced one. [`FlattenedDocument::unflatten`] keeps such
+    /// nested entries as they are, so the rebuilt document doesn't change.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let value = json!({ "id": 1, "settings": { "theme": "dark" } });
+    /// let mut doc = FlattenedDocument::from(&value);
+    ///
+    /// doc.replace_subtree_with_nested("/settings").unwrap();
+    ///
+    /// assert_eq!(doc.get("/settings"), Some(&json!({ "theme": "dark" })));
+    /// assert_eq!(doc.get("/settings/theme"), None);
+    /// assert_eq!(doc.unflatten(), Ok(value));
+    /// ```
+    pub fn replace_subtree_with_nested<P: AsPointer + ?Sized>(
+        &mut self,
+        prefix: &P,
+    ) -> Result<(), UnflattenError> {
+        let prefix = prefix.as_pointer();
+        let mut nested = Some(self.unflatten_under(prefix)?);
+        pointer::rebuild_entries(&mut self.entries, |(key, value)| {
+            if !pointer::starts_with(&key, prefix) {
+                Some((key, value))
+            } else {
+                nested.take().map(|nested| (prefix.to_owned(), nested))
+            }
+        });
+        Ok(())
+    }
+}
+
+impl From<&Value> for FlattenedDocument {
+    /// Flatten a nested JSON value
+    fn from(value: &Value) -> Self {
+        FlattenedDocument {
+            entries: Flattener::new().flatten_map(value),
+        }
+    }
+}
+
+impl TryFrom<Map<String, Value>> for FlattenedDocument {
+    type Error = ConversionError;
+
+    /// Take already flattened entries, checking that every key is a pointer
+    fn try_from(entries: Map<String, Value>) -> Result<Self, Self::Error> {
+        let errors: Vec<_> = entries
+            .keys()
+            .filter_map(|key| pointer::validate(key).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(FlattenedDocument { entries })
+        } else {
+            Err(ConversionError::InvalidKeys(errors))
+        }
+    }
+}
+
+impl TryFrom<Value> for FlattenedDocument {
+    type Error = ConversionError;
+
+    /// Take an object of already flattened entries, see [`FlattenedDocument::from_nested`] to flatten
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Object(entries) => Self::try_from(entries),
+            _ => Err(ConversionError::NotAnObject),
+        }
+    }
+}
+
+impl From<FlattenedDocument> for Value {
+    /// The entries as an object of pointer keys, like [`from_json`](crate::from_json) output
+    fn from(document: FlattenedDocument) -> Self {
+        Value::Object(document.entries)
+    }
+}
+
+impl<'de> Deserialize<'de> for FlattenedDocument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Map::<String, Value>::deserialize(deserializer)?;
+        for key in entries.keys() {
+            pointer::validate(key).map_err(D::Error::custom)?;
+        }
+        Ok(FlattenedDocument { entries })
+    }
+}
+
+/// A [`FlattenedDocument`] that must [`unflatten`](crate::unflatten) when deserialized
+///
+/// Besides invalid pointers, this rejects entries nested below scalars,
+/// array elements with invalid or missing indices, and pointers of more than
+/// 128 segments, the nesting serde_json parses nested documents to.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{FlattenedDocument, StrictDocument};
+///
+/// let body = r#"{ "/a": 1, "/a/b": 2 }"#;
+///
+/// assert!(serde_json::from_str::<FlattenedDocument>(body).is_ok());
+/// assert_eq!(
+///     serde_json::from_str::<StrictDocument>(body).unwrap_err().to_string(),
+///     "\"/a/b\" is nested below a value that is not a container"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct StrictDocument(pub FlattenedDocument);
+
+/// Deepest pointer a [`StrictDocument`] accepts
+const STRICT_MAX_DEPTH: usize = 128;
+
+impl<'de> Deserialize<'de> for StrictDocument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let document = FlattenedDocument::deserialize(deserializer)?;
+        document
+            .unflatten_with(&UnflattenOptions::new().max_depth(STRICT_MAX_DEPTH))
+            .map_err(D::Error::custom)?;
+        Ok(StrictDocument(document))
+    }
+}
+
+impl<'a> IntoIterator for &'a FlattenedDocument {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = Entries<'a>;
+
+    fn into_iter(self) -> Entries<'a> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for FlattenedDocument {
+    type Item = (String, Value);
+    type IntoIter = IntoEntries;
+
+    fn into_iter(self) -> IntoEntries {
+        IntoEntries {
+            iter: self.entries.into_iter(),
+        }
+    }
+}
+
+/// Iterator over the entries of a [`FlattenedDocument`], see [`FlattenedDocument::iter`]
+pub struct Entries<'a> {
+    map: &'a Map<String, Value>,
+    iter: map::Iter<'a>,
+    /// Entries taken from the front, so that clones can catch up
+    front: usize,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (&'a str, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        self.front += 1;
+        Some((key.as_str(), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Entries<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter
+            .next_back()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+}
+
+impl ExactSizeIterator for Entries<'_> {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl FusedIterator for Entries<'_> {}
+
+/// serde_json's map iterators aren't `Clone`, so a clone starts over and
+/// skips what was already taken from both ends
+impl Clone for Entries<'_> {
+    fn clone(&self) -> Self {
+        let back = self.map.len() - self.front - self.iter.len();
+        let mut iter = self.map.iter();
+        if self.front > 0 {
+            iter.nth(self.front - 1);
+        }
+        if back > 0 {
+            iter.nth_back(back - 1);
+        }
+        Entries {
+            map: self.map,
+            iter,
+            front: self.front,
+        }
+    }
+}
+
+impl fmt::Debug for Entries<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Owning iterator over the entries of a [`FlattenedDocument`]
+pub struct IntoEntries {
+    iter: map::IntoIter,
+}
+
+impl Iterator for IntoEntries {
+    type Item = (String, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IntoEntries {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl ExactSizeIterator for IntoEntries {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
+
+impl FusedIterator for IntoEntries {}
+
+fn collect<'a>(entries: impl Iterator<Item = (&'a str, &'a Value)>) -> Map<String, Value> {
+    entries
+        .map(|(key, value)| (key.to_owned(), value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PointerError;
+    use serde_json::json;
+
+    fn keys<'a>(entries: impl Iterator<Item = (&'a str, &'a Value)>) -> Vec<&'a str> {
+        entries.map(|(k, _)| k).collect()
+    }
+
+    #[test]
+    fn try_from_reports_every_invalid_key() {
+        let value = json!({ "": {}, "/a~2": 1, "/b": 2, "c": 3 });
+
+        assert_eq!(
+            FlattenedDocument::try_from(value),
+            Err(ConversionError::InvalidKeys(vec![
+                PointerError::InvalidEscape("/a~2".to_owned()),
+                PointerError::MissingLeadingSlash("c".to_owned()),
+            ]))
+        );
+        assert_eq!(
+            FlattenedDocument::try_from(json!([])),
+            Err(ConversionError::NotAnObject)
+        );
+    }
+
+    #[test]
+    fn conversions_round_trip_through_values() {
+        let flat = crate::from_json(&json!({ "a": [1, { "b": null }] }));
+
+        let doc = FlattenedDocument::try_from(flat.clone()).unwrap();
+
+        assert_eq!(
+            doc,
+            FlattenedDocument::from_nested(&doc.unflatten().unwrap())
+        );
+        assert_eq!(Value::from(doc), flat);
+    }
+
+    #[cfg(feature = "jsonptr")]
+    #[test]
+    fn get_accepts_jsonptr_pointers() {
+        let doc = FlattenedDocument::from(&json!({ "a/b": ["x"] }));
+        let pointer = jsonptr::PointerBuf::from_tokens(["a/b", "0"]);
+
+        assert_eq!(doc.get(&pointer), Some(&json!("x")));
+        assert_eq!(doc.get(pointer.as_ptr()), Some(&json!("x")));
+        assert_eq!(doc.get(&String::from("/a~1b")), Some(&json!([])));
+    }
+
+    #[cfg(feature = "jsonptr")]
+    #[test]
+    fn subtree_methods_accept_jsonptr_pointers() {
+        let value = json!({ "a/b": { "c": [1, 2] }, "a": 0 });
+        let mut doc = FlattenedDocument::from(&value);
+        let prefix = jsonptr::PointerBuf::from_tokens(["a/b"]);
+        let keys = |entries: Vec<(&str, &Value)>| -> Vec<String> {
+            entries.into_iter().map(|(key, _)| key.to_owned()).collect()
+        };
+
+        assert_eq!(
+            keys(doc.entries_under(&prefix).collect()),
+            vec!["/a~1b", "/a~1b/c", "/a~1b/c/0", "/a~1b/c/1"]
+        );
+        assert_eq!(keys(doc.entries_below(prefix.as_ptr()).collect()).len(), 3);
+        assert_eq!(doc.collect_under(&prefix), doc.collect_under("/a~1b"));
+        assert_eq!(doc.collect_below(&prefix).len(), 3);
+        assert_eq!(doc.unflatten_under(&prefix), Ok(json!({ "c": [1, 2] })));
+        assert_eq!(
+            doc.get_as::<Vec<u8>>(&jsonptr::PointerBuf::from_tokens(["a/b", "c"])),
+            Ok(vec![1, 2])
+        );
+
+        doc.replace_subtree_with_nested(&prefix).unwrap();
+        assert_eq!(doc.get(&prefix), Some(&json!({ "c": [1, 2] })));
+        assert_eq!(doc.unflatten(), Ok(value));
+    }
+
+    #[test]
+    fn entries_are_exact_and_reversible() {
+        let doc = FlattenedDocument::from(&json!({ "a": [1, 2], "b": null }));
+        let forward = keys(doc.iter());
+
+        let mut entries = doc.iter();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries.size_hint(), (5, Some(5)));
+        entries.next();
+        entries.next_back();
+        assert_eq!(entries.len(), 3);
+
+        let clone = entries.clone();
+        assert_eq!(keys(clone), forward[1..4]);
+        assert_eq!(
+            keys(entries.rev()),
+            vec![forward[3], forward[2], forward[1]]
+        );
+        assert_eq!(keys((&doc).into_iter()), forward);
+    }
+
+    #[test]
+    fn into_entries_owns_the_entries() {
+        let doc = FlattenedDocument::from(&json!({ "a": 1 }));
+        let map = doc.as_map().clone();
+
+        let mut entries = doc.into_iter();
+        assert_eq!(entries.len(), 2);
+        let last = entries.next_back();
+
+        assert_eq!(last, map.into_iter().next_back());
+        assert_eq!(entries.count(), 1);
+    }
+
+    #[test]
+    fn serializes_as_the_flattened_map() {
+        #[derive(Serialize)]
+        struct Response {
+            id: u32,
+            document: FlattenedDocument,
+        }
+        // keys in sorted order, so the text is the same with `preserve_order`
+        let value = json!({ "a": [true], "b": { "c~d": null } });
+
+        let response = Response {
+            id: 7,
+            document: FlattenedDocument::from(&value),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            format!(
+                r#"{{"id":7,"document":{}}}"#,
+                serde_json::to_string(&crate::from_json(&value)).unwrap()
+            )
+        );
+        assert_eq!(
+            serde_json::to_string(&response.document).unwrap(),
+            r#"{"":{},"/a":[],"/a/0":true,"/b":{},"/b/c~0d":null}"#
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn serializes_in_insertion_order() {
+        let value: Value = serde_json::from_str(r#"{ "z": 1, "a": [2] }"#).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&FlattenedDocument::from(&value)).unwrap(),
+            r#"{"":{},"/z":1,"/a":[],"/a/0":2}"#
+        );
+    }
+
+    #[test]
+    fn deserialize_validates_pointers() {
+        let doc: FlattenedDocument =
+            serde_json::from_str(r#"{ "": {}, "/a~1b": [], "/a~1b/0": 1 }"#).unwrap();
+        assert_eq!(doc, FlattenedDocument::from(&json!({ "a/b": [1] })));
+
+        let error =
+            serde_json::from_str::<FlattenedDocument>(r#"{ "/ok": 1, "/x~2": 2 }"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid escape sequence in pointer \"/x~2\""
+        );
+        assert!(serde_json::from_str::<FlattenedDocument>(r#"{ "a": 1 }"#).is_err());
+        assert!(serde_json::from_str::<FlattenedDocument>("[]").is_err());
+    }
+
+    #[test]
+    fn strict_deserialize_validates_structure() {
+        let body = r#"{ "/a": [], "/a/0": { "b": 1 } }"#;
+        let strict: StrictDocument = serde_json::from_str(body).unwrap();
+        assert_eq!(strict.0.len(), 2);
+
+        for body in [
+            r#"{ "/a": "x", "/a/0": 1 }"#,
+            r#"{ "/a": [], "/a/1": 1 }"#,
+            r#"{ "/a": [], "/a/01": 1 }"#,
+            r#"{ "a": 1 }"#,
+        ] {
+            assert!(
+                serde_json::from_str::<StrictDocument>(body).is_err(),
+                "{}",
+                body
+            );
+            assert_eq!(
+                serde_json::from_str::<FlattenedDocument>(body).is_ok(),
+                body != r#"{ "a": 1 }"#
+            );
+        }
+    }
+
+    #[test]
+    fn strict_deserialize_rejects_very_deep_keys() {
+        let deep = format!(r#"{{ "{}": 1 }}"#, "/a".repeat(20_000));
+        let error = serde_json::from_str::<StrictDocument>(&deep).unwrap_err();
+
+        assert!(
+            error.to_string().contains("deeper than 128 levels"),
+            "{}",
+            error
+        );
+        assert!(error.to_string().len() < 200);
+        let limit = format!(r#"{{ "{}": 1 }}"#, "/a".repeat(128));
+        assert!(serde_json::from_str::<StrictDocument>(&limit).is_ok());
+    }
+
+    #[test]
+    fn entries_under_matches_whole_segments() {
+        let doc = FlattenedDocument::from(&json!({
+            "users": [{ "name": "a" }, { "name": "b" }],
+            "user": 1,
+            "usersCount": 2
+        }));
+
+        assert_eq!(
+            keys(doc.entries_under("/users/1")),
+            vec!["/users/1", "/users/1/name"]
+        );
+        assert_eq!(keys(doc.entries_under("/user")), vec!["/user"]);
+        assert_eq!(keys(doc.entries_below("/users/1")), vec!["/users/1/name"]);
+    }
+
+    #[test]
+    fn entries_under_escaped_key() {
+        let doc = FlattenedDocument::from(&json!({ "a/b": { "c": 1 }, "a": { "b": 2 } }));
+
+        let actual = doc.collect_under("/a~1b");
+
+        assert_eq!(actual.len(), 2);
+        assert!(actual.get("/a~1b/c").unwrap().eq(&json!(1)));
+    }
+
+    #[test]
+    fn find_with_patterns() {
+        let doc = FlattenedDocument::from(&json!({
+            "password": "y",
+            "users": [
+                { "auth": { "password": "x" }, "email": "a" },
+                { "email": "b" }
+            ]
+        }));
+
+        let emails = doc.find(&PointerPattern::new("/users/*/email").unwrap());
+        let passwords = doc.find(&PointerPattern::new("/**/password").unwrap());
+
+        assert_eq!(
+            keys(emails.into_iter()),
+            vec!["/users/0/email", "/users/1/email"]
+        );
+        assert_eq!(
+            keys(passwords.into_iter()),
+            vec!["/password", "/users/0/auth/password"]
+        );
+    }
+
+    #[test]
+    fn group_by_parent_with_escaped_keys() {
+        let doc = FlattenedDocument::from(&json!({
+            "a": { "b": 3 },
+            "a/b": [1, 2],
+            "m~n": null
+        }));
+
+        let groups = doc.group_by_parent();
+
+        assert_eq!(
+            groups.keys().copied().collect::<Vec<_>>(),
+            vec!["", "/a", "/a~1b"]
+        );
+        assert_eq!(
+            keys(groups[""].iter().copied()),
+            vec!["/a", "/a~1b", "/m~0n"]
+        );
+        assert_eq!(keys(groups["/a"].iter().copied()), vec!["/a/b"]);
+        assert_eq!(
+            keys(groups["/a~1b"].iter().copied()),
+            vec!["/a~1b/0", "/a~1b/1"]
+        );
+    }
+
+    #[test]
+    fn group_by_depth_counts_segments() {
+        let doc = FlattenedDocument::from(&json!({ "a/b": { "": [true] } }));
+
+        let groups = doc.group_by_depth();
+
+        assert_eq!(groups.len(), 4);
+        assert_eq!(keys(groups[0].iter().copied()), vec![""]);
+        assert_eq!(keys(groups[1].iter().copied()), vec!["/a~1b"]);
+        assert_eq!(keys(groups[2].iter().copied()), vec!["/a~1b/"]);
+        assert_eq!(keys(groups[3].iter().copied()), vec!["/a~1b//0"]);
+    }
+
+    #[test]
+    fn root_prefix_covers_everything() {
+        let doc = FlattenedDocument::from(&json!([1, 2]));
+
+        assert_eq!(doc.collect_under("").len(), doc.len());
+        assert_eq!(doc.collect_below("").len(), doc.len() - 1);
+    }
+
+    #[test]
+    fn unflatten_under_matches_whole_segments() {
+        let doc = FlattenedDocument::from(&json!({
+            "user": { "id": 1 },
+            "username": "x",
+            "a/b": { "~c": [1, 2] },
+            "a": { "b": 3 }
+        }));
+
+        assert_eq!(doc.unflatten_under("/user"), Ok(json!({ "id": 1 })));
+        assert_eq!(doc.unflatten_under("/a~1b"), Ok(json!({ "~c": [1, 2] })));
+        assert_eq!(doc.unflatten_under("/a~1b/~0c"), Ok(json!([1, 2])));
+        assert_eq!(doc.unflatten_under("/a/b"), Ok(json!(3)));
+        assert_eq!(doc.unflatten_under(""), doc.unflatten());
+        assert_eq!(
+            doc.unflatten_under("/use"),
+            Err(UnflattenError::NotFound {
+                pointer: "/use".to_owned()
+            })
+        );
+        assert!(matches!(
+            doc.unflatten_under("user"),
+            Err(UnflattenError::InvalidPointer(_))
+        ));
+    }
+
+    #[test]
+    fn unflatten_under_reports_document_pointers() {
+        let mut entries = Map::new();
+        entries.insert("/s".to_owned(), json!([]));
+        entries.insert("/s/1".to_owned(), json!(true));
+        let doc = FlattenedDocument::from_map(entries);
+
+        assert_eq!(
+            doc.unflatten_under("/s"),
+            Err(UnflattenError::MissingIndex {
+                pointer: "/s/1".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn replace_subtree_keeps_the_rest_flat() {
+        let value = json!({
+            "id": 1,
+            "settings": { "a~b": { "x": [null] }, "theme": "dark" },
+            "settings_v2": { "theme": "light" }
+        });
+        let mut doc = FlattenedDocument::from(&value);
+
+        doc.replace_subtree_with_nested("/settings").unwrap();
+
+        assert_eq!(
+            doc.sorted_keys(),
+            vec!["", "/id", "/settings", "/settings_v2", "/settings_v2/theme"]
+        );
+        assert_eq!(
+            doc.get("/settings"),
+            Some(&json!({ "a~b": { "x": [null] }, "theme": "dark" }))
+        );
+        assert_eq!(doc.unflatten(), Ok(value));
+        assert!(doc.replace_subtree_with_nested("/missing").is_err());
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct User {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn get_as_deserializes_leaves_and_subtrees() {
+        let mut doc = FlattenedDocument::from(&json!({
+            "users": [{ "name": "Jo", "tags": ["a"] }, { "name": "Al", "tags": [] }],
+            "count": 2
+        }));
+
+        assert_eq!(doc.get_as::<u32>("/count"), Ok(2));
+        assert_eq!(
+            doc.get_as::<User>("/users/1"),
+            Ok(User {
+                name: "Al".to_owned(),
+                tags: Vec::new()
+            })
+        );
+        assert_eq!(
+            doc.get_as::<Vec<User>>("/users").map(|users| users.len()),
+            Ok(2)
+        );
+
+        doc.replace_subtree_with_nested("/users/0").unwrap();
+        assert_eq!(
+            doc.get_as::<User>("/users/0").map(|user| user.name),
+            Ok("Jo".to_owned())
+        );
+    }
+
+    #[test]
+    fn get_as_errors_name_the_pointer() {
+        let mut entries = Map::new();
+        entries.insert("/s".to_owned(), json!([]));
+        entries.insert("/s/1".to_owned(), json!(true));
+        entries.insert("/n".to_owned(), json!("x"));
+        let doc = FlattenedDocument::from_map(entries);
+
+        assert_eq!(
+            doc.get_as::<u8>("/missing"),
+            Err(AccessError::NotFound {
+                pointer: "/missing".to_owned()
+            })
+        );
+        assert!(matches!(
+            doc.get_as::<u8>("n"),
+            Err(AccessError::InvalidPointer(_))
+        ));
+        assert_eq!(
+            doc.get_as::<Vec<bool>>("/s"),
+            Err(AccessError::Unflatten(UnflattenError::MissingIndex {
+                pointer: "/s/1".to_owned()
+            }))
+        );
+        match doc.get_as::<u8>("/n") {
+            Err(AccessError::Deserialize { pointer, .. }) => assert_eq!(pointer, "/n"),
+            other => panic!("expected a deserialization error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::flattener::is_container;
+use crate::{pointer, Flattener};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Options for [`find_duplicates`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DuplicateOptions {
+    /// Only consider string leaves
+    pub strings_only: bool,
+    /// Ignore values shorter than this, in characters for strings and in
+    /// serialized JSON bytes for other leaves
+    pub min_len: usize,
+    /// Ignore leaves at or below any of these pointers
+    pub skip_prefixes: Vec<String>,
+}
+
+impl DuplicateOptions {
+    /// Create options that consider every leaf
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only consider string leaves
+    pub fn strings_only(mut self, enabled: bool) -> Self {
+        self.strings_only = enabled;
+        self
+    }
+
+    /// Ignore values shorter than `len`
+    pub fn min_len(mut self, len: usize) -> Self {
+        self.min_len = len;
+        self
+    }
+
+    /// Ignore leaves at or below `prefix`
+    pub fn skip_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.skip_prefixes.push(prefix.into());
+        self
+    }
+}
+
+/// A leaf value found at more than one pointer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+    /// The repeated value
+    pub value: Value,
+    /// Every pointer holding the value, in flattening order
+    pub pointers: Vec<String>,
+}
+
+/// Find leaf values that occur at more than one pointer
+///
+/// Values are bucketed by their compact JSON encoding, so `1` and `1.0` are
+/// different values. Groups are returned in order of first occurrence.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::DuplicateOptions;
+/// use serde_json::json;
+///
+/// let value = json!({ "a": { "secret": "hunter2" }, "b": { "token": "hunter2" }, "n": 1 });
+///
+/// let groups = jsonpointer_flatten::find_duplicates(&value, &DuplicateOptions::new());
+///
+/// assert_eq!(groups.len(), 1);
+/// assert_eq!(groups[0].pointers, vec!["/a/secret", "/b/token"]);
+/// ```
+pub fn find_duplicates(value: &Value, options: &DuplicateOptions) -> Vec<DuplicateGroup> {
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+
+    Flattener::new().visit(value, |ptr, _, _, node| {
+        match node {
+            _ if is_container(node) => return,
+            Value::String(_) => {}
+            _ if options.strings_only => return,
+            _ => {}
+        }
+        if options
+            .skip_prefixes
+            .iter()
+            .any(|prefix| pointer::starts_with(ptr, prefix))
+        {
+            return;
+        }
+
+        let canonical = node.to_string();
+        let len = match node {
+            Value::String(s) => s.chars().count(),
+            _ => canonical.len(),
+        };
+        if len < options.min_len {
+            return;
+        }
+
+        match index.get(&canonical) {
+            Some(&idx) => groups[idx].pointers.push(ptr.to_owned()),
+            None => {
+                index.insert(canonical, groups.len());
+                groups.push(DuplicateGroup {
+                    value: node.clone(),
+                    pointers: vec![ptr.to_owned()],
+                });
+            }
+        }
+    });
+
+    groups.retain(|group| group.pointers.len() > 1);
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "cache": { "password": "s3cr3t-value", "port": 5432 },
+            "db": { "password": "s3cr3t-value", "port": 5432 },
+            "debug": { "copy": "s3cr3t-value" },
+            "ids": ["a", "b"],
+            "other_ids": ["a"]
+        })
+    }
+
+    #[test]
+    fn groups_in_order_of_first_occurrence() {
+        let groups = find_duplicates(&sample(), &DuplicateOptions::new());
+
+        assert_eq!(
+            groups,
+            vec![
+                DuplicateGroup {
+                    value: json!("s3cr3t-value"),
+                    pointers: vec![
+                        "/cache/password".to_owned(),
+                        "/db/password".to_owned(),
+                        "/debug/copy".to_owned()
+                    ]
+                },
+                DuplicateGroup {
+                    value: json!(5432),
+                    pointers: vec!["/cache/port".to_owned(), "/db/port".to_owned()]
+                },
+                DuplicateGroup {
+                    value: json!("a"),
+                    pointers: vec!["/ids/0".to_owned(), "/other_ids/0".to_owned()]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn options_narrow_the_search() {
+        let options = DuplicateOptions::new()
+            .strings_only(true)
+            .min_len(3)
+            .skip_prefix("/debug");
+
+        let groups = find_duplicates(&sample(), &options);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].pointers, vec!["/cache/password", "/db/password"]);
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        let groups = find_duplicates(&json!([true, true]), &DuplicateOptions::new());
+
+        assert_eq!(
+            serde_json::to_value(&groups).unwrap(),
+            json!([{ "value": true, "pointers": ["/0", "/1"] }])
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use crate::flattener::is_container;
+use crate::pointer;
+use crate::Flattener;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A flattened entry together with its place in the tree, see [`flatten_entries`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    /// Pointer of the entry
+    pub pointer: String,
+    /// Pointer of the containing entry, `None` for the root
+    pub parent: Option<String>,
+    /// Unescaped last segment of the pointer, `None` for the root
+    pub key_or_index: Option<Segment>,
+    /// Number of segments in the pointer
+    pub depth: usize,
+    /// Whether the node is a scalar rather than an array or object
+    pub is_leaf: bool,
+    /// Value stored for the entry, as in [`Flattener::flatten`]
+    pub value: Value,
+}
+
+/// Last segment of an [`Entry`], serialized as a plain string or number
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Segment {
+    /// Member name within an object
+    Key(String),
+    /// Element index within an array
+    Index(usize),
+}
+
+/// Flatten a JSON value into entries describing their place in the tree
+///
+/// Entries come in flattening order, so parents precede their children.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Entry, Segment};
+/// use serde_json::json;
+///
+/// let entries = jsonpointer_flatten::flatten_entries(&json!({ "a/b": ["x"] }));
+///
+/// assert_eq!(entries[2], Entry {
+///     pointer: "/a~1b/0".to_owned(),
+///     parent: Some("/a~1b".to_owned()),
+///     key_or_index: Some(Segment::Index(0)),
+///     depth: 2,
+///     is_leaf: true,
+///     value: json!("x"),
+/// });
+/// assert_eq!(entries[1].key_or_index, Some(Segment::Key("a/b".to_owned())));
+/// ```
+pub fn flatten_entries(value: &Value) -> Vec<Entry> {
+    Flattener::new().flatten_entries(value)
+}
+
+impl Flattener {
+    /// Like [`flatten_entries`], with the options of this flattener
+    ///
+    /// Container entries follow [`Flattener::container_value`] and values
+    /// [`Flattener::entry_format`]. [`Limits`](crate::Limits) are not enforced here.
+    pub fn flatten_entries(&self, value: &Value) -> Vec<Entry> {
+        let mut entries = Vec::new();
+
+        self.visit(value, |key, depth, index, val| {
+            let value = match self.entry(val) {
+                Some(value) => value,
+                None => return,
+            };
+            let (parent, key_or_index) = match pointer::parent(key) {
+                Some(parent) if depth > 0 => {
+                    let segment = match index {
+                        Some(idx) => Segment::Index(idx),
+                        None => Segment::Key(pointer::unescape(&key[parent.len() + 1..])),
+                    };
+                    let parent = if depth == 1 { self.root() } else { parent };
+                    (Some(parent.to_owned()), Some(segment))
+                }
+                _ => (None, None),
+            };
+            entries.push(Entry {
+                pointer: key.to_owned(),
+                parent,
+                key_or_index,
+                depth,
+                is_leaf: !is_container(val),
+                value,
+            });
+        });
+
+        entries
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ContainerValue, TraversalOrder};
+    use serde_json::json;
+
+    #[test]
+    fn every_entry_knows_its_place() {
+        let value = json!({ "": null, "a": [{ "m~n": 1 }] });
+
+        let entries = flatten_entries(&value);
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.pointer.as_str(), e.parent.as_deref(), e.depth, e.is_leaf))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("", None, 0, false),
+                ("/", Some(""), 1, true),
+                ("/a", Some(""), 1, false),
+                ("/a/0", Some("/a"), 2, false),
+                ("/a/0/m~0n", Some("/a/0"), 3, true),
+            ]
+        );
+        assert_eq!(entries[1].key_or_index, Some(Segment::Key(String::new())));
+        assert_eq!(entries[3].key_or_index, Some(Segment::Index(0)));
+        assert_eq!(
+            entries[4].key_or_index,
+            Some(Segment::Key("m~n".to_owned()))
+        );
+    }
+
+    #[test]
+    fn follows_flattener_options() {
+        let value = json!({ "a": { "b": [1, 2] } });
+        let flattener = Flattener::new()
+            .container_value(ContainerValue::Skip)
+            .traversal_order(TraversalOrder::BreadthFirst)
+            .root_key("$root");
+
+        let leaves = flattener.flatten_entries(&value);
+        assert_eq!(leaves.len(), 2);
+        assert_eq!(leaves[1].pointer, "/a/b/1");
+        assert_eq!(leaves[1].key_or_index, Some(Segment::Index(1)));
+
+        let entries = Flattener::new().root_key("$root").flatten_entries(&value);
+        assert_eq!(entries[0].pointer, "$root");
+        assert_eq!(entries[1].parent.as_deref(), Some("$root"));
+    }
+
+    #[test]
+    fn serializes_segments_as_strings_or_numbers() {
+        let entries = flatten_entries(&json!([true]));
+
+        assert_eq!(
+            serde_json::to_value(&entries).unwrap(),
+            json!([
+                { "pointer": "", "parent": null, "key_or_index": null, "depth": 0, "is_leaf": false, "value": [] },
+                { "pointer": "/0", "parent": "", "key_or_index": 0, "depth": 1, "is_leaf": true, "value": true }
+            ])
+        );
+    }
+}
@@ -0,0 +1,755 @@
+#[cfg(feature = "unicode-normalization")]
+use crate::KeyCollision;
+use crate::{EscapedKey, ResolverFailure};
+use std::fmt;
+
+/// Errors produced by the fallible flattening entry points
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlattenError {
+    /// A key produced while flattening already existed in the target map and
+    /// the flattener was configured with [`OnConflict::Error`](crate::OnConflict::Error)
+    KeyConflict(String),
+    /// The progress callback returned [`ControlFlow::Break`](std::ops::ControlFlow::Break)
+    Cancelled,
+    /// An entry would have been nested deeper than [`Limits::max_depth`](crate::Limits::max_depth)
+    DepthLimitExceeded { limit: usize, pointer: String },
+    /// More entries would have been emitted than [`Limits::max_entries`](crate::Limits::max_entries)
+    EntryLimitExceeded { limit: usize, pointer: String },
+    /// A pointer of `len` bytes at `depth` would have been longer than
+    /// [`Limits::max_pointer_len`](crate::Limits::max_pointer_len)
+    ///
+    /// The pointer is never built, so only the `parent` it would have extended
+    /// is kept, which is empty if the root pointer itself is too long.
+    PointerLengthExceeded {
+        limit: usize,
+        depth: usize,
+        parent: String,
+        len: usize,
+    },
+    /// The output would have grown past [`Limits::max_total_output_bytes`](crate::Limits::max_total_output_bytes),
+    /// `bytes` being the estimated size of the entries emitted before `pointer`
+    OutputSizeExceeded {
+        limit: usize,
+        pointer: String,
+        bytes: usize,
+    },
+    /// Keys containing `/` or `~` were found with
+    /// [`Flattener::forbid_escapable_keys`](crate::Flattener::forbid_escapable_keys), all of them listed
+    EscapableKeys(Vec<EscapedKey>),
+    /// Distinct keys of an object normalize to the same key with
+    /// [`Flattener::normalize_keys`](crate::Flattener::normalize_keys), all collisions listed
+    #[cfg(feature = "unicode-normalization")]
+    NormalizedKeyCollisions(Vec<KeyCollision>),
+}
+
+impl fmt::Display for FlattenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlattenError::KeyConflict(key) => {
+                write!(f, "key \"{}\" already exists in the target map", key)
+            }
+            FlattenError::Cancelled => write!(f, "flattening was cancelled"),
+            FlattenError::DepthLimitExceeded { limit, pointer } => {
+                write!(f, "max_depth of {} exceeded at \"{}\"", limit, pointer)
+            }
+            FlattenError::EntryLimitExceeded { limit, pointer } => {
+                write!(f, "max_entries of {} exceeded at \"{}\"", limit, pointer)
+            }
+            FlattenError::PointerLengthExceeded {
+                limit,
+                depth,
+                parent,
+                len,
+            } => {
+                write!(
+                    f,
+                    "max_pointer_len of {} exceeded by a pointer of {} bytes at depth {} below \"{}\"",
+                    limit,
+                    len,
+                    depth,
+                    elided(parent, 60)
+                )
+            }
+            FlattenError::OutputSizeExceeded {
+                limit,
+                pointer,
+                bytes,
+            } => {
+                write!(
+                    f,
+                    "max_total_output_bytes of {} exceeded at \"{}\" after {} bytes",
+                    limit, pointer, bytes
+                )
+            }
+            FlattenError::EscapableKeys(keys) => {
+                write!(f, "keys would need escaping:")?;
+                for key in keys {
+                    write!(
+                        f,
+                        " \"{}\" in \"{}\"",
+                        key.original_key, key.pointer_of_parent
+                    )?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "unicode-normalization")]
+            FlattenError::NormalizedKeyCollisions(collisions) => {
+                write!(f, "keys collide after normalization:")?;
+                for collision in collisions {
+                    write!(
+                        f,
+                        " {:?} in \"{}\"",
+                        collision.original_keys, collision.pointer_of_parent
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for FlattenError {}
+
+/// `text` cut after `chars` characters, marked with `…` if anything was cut
+fn elided(text: &str, chars: usize) -> std::borrow::Cow<'_, str> {
+    match text.char_indices().nth(chars) {
+        Some((cut, _)) => format!("{}…", &text[..cut]).into(),
+        None => text.into(),
+    }
+}
+
+/// Errors produced when compiling a [`PointerPattern`](crate::PointerPattern)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// The pattern is not empty and does not start with `/`
+    MissingLeadingSlash(String),
+    /// A segment contains `~` not followed by `0`, `1` or `2`
+    InvalidEscape(String),
+    /// A segment mixes `*` with other characters, or is longer than `**`
+    InvalidWildcard(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::MissingLeadingSlash(pattern) => {
+                write!(f, "pattern \"{}\" must be empty or start with '/'", pattern)
+            }
+            PatternError::InvalidEscape(segment) => {
+                write!(f, "invalid escape sequence in segment \"{}\"", segment)
+            }
+            PatternError::InvalidWildcard(segment) => write!(
+                f,
+                "segment \"{}\" must be exactly '*' or '**' to be a wildcard",
+                segment
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Errors produced when compiling or rendering a [`PointerTemplate`](crate::PointerTemplate)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The template is not empty and does not start with `/`
+    MissingLeadingSlash(String),
+    /// A segment contains `~` not followed by `0` or `1`
+    InvalidEscape(String),
+    /// A segment has a `{` or `}` that is neither doubled nor part of a whole-segment placeholder
+    InvalidPlaceholder(String),
+    /// Two placeholders have the same name
+    DuplicatePlaceholder(String),
+    /// No value was given for a placeholder when rendering
+    MissingValue(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::MissingLeadingSlash(template) => {
+                write!(
+                    f,
+                    "template \"{}\" must be empty or start with '/'",
+                    template
+                )
+            }
+            TemplateError::InvalidEscape(segment) => {
+                write!(f, "invalid escape sequence in segment \"{}\"", segment)
+            }
+            TemplateError::InvalidPlaceholder(segment) => write!(
+                f,
+                "segment \"{}\" must be exactly '{{name}}' to be a placeholder",
+                segment
+            ),
+            TemplateError::DuplicatePlaceholder(name) => {
+                write!(f, "placeholder \"{}\" is used more than once", name)
+            }
+            TemplateError::MissingValue(name) => {
+                write!(f, "no value for placeholder \"{}\"", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Errors produced when a string is not a valid JSON Pointer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// The pointer is not empty and does not start with `/`
+    MissingLeadingSlash(String),
+    /// The pointer contains `~` not followed by `0` or `1`
+    InvalidEscape(String),
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerError::MissingLeadingSlash(pointer) => {
+                write!(f, "pointer \"{}\" must be empty or start with '/'", pointer)
+            }
+            PointerError::InvalidEscape(pointer) => {
+                write!(f, "invalid escape sequence in pointer \"{}\"", pointer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Errors produced when rebuilding a nested document from flattened entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnflattenError {
+    /// The input is not an object of pointer keys
+    NotAnObject,
+    /// A key is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// An entry is nested below a value that is neither an object nor an array
+    NotAContainer { pointer: String },
+    /// An entry is nested below an array, but its segment is not an array index
+    InvalidIndex { pointer: String, segment: String },
+    /// An array element is missing, so later elements can't keep their index
+    MissingIndex { pointer: String },
+    /// An entry is not a valid [`EntryFormat::Typed`](crate::EntryFormat::Typed) value
+    InvalidEntry { pointer: String },
+    /// No entry is at or below the pointer
+    NotFound { pointer: String },
+    /// A pointer has more segments than [`UnflattenOptions::max_depth`](crate::UnflattenOptions::max_depth) allows
+    DepthLimitExceeded { limit: usize, pointer: String },
+}
+
+impl fmt::Display for UnflattenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnflattenError::NotAnObject => write!(f, "flattened input must be an object"),
+            UnflattenError::InvalidPointer(error) => error.fmt(f),
+            UnflattenError::NotAContainer { pointer } => {
+                write!(
+                    f,
+                    "\"{}\" is nested below a value that is not a container",
+                    pointer
+                )
+            }
+            UnflattenError::InvalidIndex { pointer, segment } => write!(
+                f,
+                "segment \"{}\" of \"{}\" is not a valid array index",
+                segment, pointer
+            ),
+            UnflattenError::MissingIndex { pointer } => {
+                write!(f, "\"{}\" follows a missing array element", pointer)
+            }
+            UnflattenError::InvalidEntry { pointer } => {
+                write!(f, "\"{}\" is not a typed entry", pointer)
+            }
+            UnflattenError::NotFound { pointer } => {
+                write!(f, "no entry at or below \"{}\"", pointer)
+            }
+            UnflattenError::DepthLimitExceeded { limit, pointer } => write!(
+                f,
+                "\"{}\" is nested deeper than {} levels",
+                elided(pointer, 60),
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnflattenError {}
+
+impl UnflattenError {
+    /// The same error for entries that were rebased from below `prefix`
+    pub(crate) fn under(self, prefix: &str) -> Self {
+        let rebase = |pointer: String| format!("{}{}", prefix, pointer);
+        match self {
+            UnflattenError::NotAContainer { pointer } => UnflattenError::NotAContainer {
+                pointer: rebase(pointer),
+            },
+            UnflattenError::InvalidIndex { pointer, segment } => UnflattenError::InvalidIndex {
+                pointer: rebase(pointer),
+                segment,
+            },
+            UnflattenError::MissingIndex { pointer } => UnflattenError::MissingIndex {
+                pointer: rebase(pointer),
+            },
+            UnflattenError::InvalidEntry { pointer } => UnflattenError::InvalidEntry {
+                pointer: rebase(pointer),
+            },
+            UnflattenError::NotFound { pointer } => UnflattenError::NotFound {
+                pointer: rebase(pointer),
+            },
+            UnflattenError::DepthLimitExceeded { limit, pointer } => {
+                UnflattenError::DepthLimitExceeded {
+                    limit,
+                    pointer: rebase(pointer),
+                }
+            }
+            error => error,
+        }
+    }
+}
+
+impl From<PointerError> for UnflattenError {
+    fn from(error: PointerError) -> Self {
+        UnflattenError::InvalidPointer(error)
+    }
+}
+
+/// Errors produced when applying a JSON Patch to a flattened document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch is not an array of operations, or an operation is malformed
+    InvalidOperation(String),
+    /// A `path` or `from` member is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// The target of an operation, or the parent of an added value, doesn't exist
+    NotFound { pointer: String },
+    /// A value would be added below a value that is neither an object nor an array
+    NotAContainer { pointer: String },
+    /// An array segment is not an index, or is out of bounds
+    InvalidIndex { pointer: String },
+    /// A value would be moved into one of its own children
+    MoveIntoDescendant { from: String, path: String },
+    /// A `test` operation found a different value
+    TestFailed { pointer: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::InvalidOperation(reason) => write!(f, "invalid patch: {}", reason),
+            PatchError::InvalidPointer(error) => error.fmt(f),
+            PatchError::NotFound { pointer } => write!(f, "\"{}\" does not exist", pointer),
+            PatchError::NotAContainer { pointer } => {
+                write!(
+                    f,
+                    "\"{}\" is nested below a value that is not a container",
+                    pointer
+                )
+            }
+            PatchError::InvalidIndex { pointer } => {
+                write!(f, "\"{}\" does not address a valid array index", pointer)
+            }
+            PatchError::MoveIntoDescendant { from, path } => {
+                write!(f, "can't move \"{}\" into its child \"{}\"", from, path)
+            }
+            PatchError::TestFailed { pointer } => {
+                write!(f, "test failed, \"{}\" holds a different value", pointer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl From<PointerError> for PatchError {
+    fn from(error: PointerError) -> Self {
+        PatchError::InvalidPointer(error)
+    }
+}
+
+/// Errors produced when building a JSON Merge Patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergePatchError {
+    /// The target has an object member set to `null`, which a merge patch can
+    /// only express as removing it
+    NullValue { pointer: String },
+}
+
+impl fmt::Display for MergePatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergePatchError::NullValue { pointer } => write!(
+                f,
+                "\"{}\" is null, which a merge patch can't express",
+                pointer
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MergePatchError {}
+
+/// Errors produced by [`merge3_with_resolver`](crate::merge3_with_resolver)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// The resolver answered [`Resolution::Fail`](crate::Resolution::Fail),
+    /// all failures listed, or only the first with [`Merge3Options::fail_fast`](crate::Merge3Options::fail_fast)
+    Unresolved(Vec<ResolverFailure>),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Unresolved(failures) => {
+                write!(f, "conflicts could not be resolved:")?;
+                for failure in failures {
+                    write!(f, " \"{}\" ({})", failure.pointer, failure.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Errors yielded by [`flatten_stream`](crate::flatten_stream)
+#[derive(Debug)]
+pub enum StreamError {
+    /// A document is not valid JSON, or reading failed. `offset` is the byte
+    /// offset where the failed document starts.
+    Parse {
+        offset: usize,
+        error: serde_json::Error,
+    },
+    /// A document exceeded the flattener's limits. `offset` is the byte offset
+    /// just past the document, where the stream continues.
+    Flatten { offset: usize, error: FlattenError },
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Parse { offset, error } => {
+                write!(f, "invalid document at byte {}: {}", offset, error)
+            }
+            StreamError::Flatten { offset, error } => {
+                write!(f, "document ending at byte {}: {}", offset, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Parse { error, .. } => Some(error),
+            StreamError::Flatten { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Errors produced when reshaping an array of objects into a [`Table`](crate::Table)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableError {
+    /// The array pointer is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// Nothing exists at the array pointer
+    NotFound { pointer: String },
+    /// The value at the array pointer is not an array
+    NotAnArray { pointer: String },
+    /// An element contains an array, which [`NestedArrays::Error`](crate::NestedArrays::Error) rejects
+    NestedArray { pointer: String },
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::InvalidPointer(error) => error.fmt(f),
+            TableError::NotFound { pointer } => write!(f, "\"{}\" does not exist", pointer),
+            TableError::NotAnArray { pointer } => write!(f, "\"{}\" is not an array", pointer),
+            TableError::NestedArray { pointer } => {
+                write!(f, "\"{}\" is an array nested inside a row", pointer)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<PointerError> for TableError {
+    fn from(error: PointerError) -> Self {
+        TableError::InvalidPointer(error)
+    }
+}
+
+/// Errors produced when renumbering an array of a [`FlattenedDocument`](crate::FlattenedDocument)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReindexError {
+    /// The array pointer is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// No entry is at or below the array pointer
+    NotFound { pointer: String },
+    /// The entry at the pointer is not an array, nor are its children indexed
+    NotAnArray { pointer: String },
+    /// The array has a child whose segment is not an array index
+    MixedChildren { pointer: String, segment: String },
+}
+
+impl fmt::Display for ReindexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReindexError::InvalidPointer(error) => error.fmt(f),
+            ReindexError::NotFound { pointer } => {
+                write!(f, "no entry at or below \"{}\"", pointer)
+            }
+            ReindexError::NotAnArray { pointer } => write!(f, "\"{}\" is not an array", pointer),
+            ReindexError::MixedChildren { pointer, segment } => write!(
+                f,
+                "array \"{}\" has child \"{}\", which is not an array index",
+                pointer, segment
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReindexError {}
+
+impl From<PointerError> for ReindexError {
+    fn from(error: PointerError) -> Self {
+        ReindexError::InvalidPointer(error)
+    }
+}
+
+/// Errors produced when converting a JSON value into a [`FlattenedDocument`](crate::FlattenedDocument)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// The value is not an object of pointer keys
+    NotAnObject,
+    /// Keys are not valid JSON Pointers, one error for each of them
+    InvalidKeys(Vec<PointerError>),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::NotAnObject => write!(f, "flattened input must be an object"),
+            ConversionError::InvalidKeys(errors) => {
+                write!(f, "{} invalid key", errors.len())?;
+                if errors.len() != 1 {
+                    write!(f, "s")?;
+                }
+                for (idx, error) in errors.iter().enumerate() {
+                    write!(f, "{} {}", if idx == 0 { ":" } else { ";" }, error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Errors produced when expanding the references of a deduplicated document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceError {
+    /// The input is not an object of pointer keys
+    NotAnObject,
+    /// A reference target is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// No entry is at or below the target of the reference at `pointer`
+    MissingTarget { pointer: String, target: String },
+    /// Expanding the reference at `pointer` would end up expanding it again
+    Cycle { pointer: String, target: String },
+}
+
+impl fmt::Display for ReferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceError::NotAnObject => write!(f, "flattened input must be an object"),
+            ReferenceError::InvalidPointer(error) => error.fmt(f),
+            ReferenceError::MissingTarget { pointer, target } => write!(
+                f,
+                "\"{}\" refers to \"{}\", which has no entries",
+                pointer, target
+            ),
+            ReferenceError::Cycle { pointer, target } => write!(
+                f,
+                "\"{}\" refers to \"{}\", which contains the reference itself",
+                pointer, target
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReferenceError {}
+
+impl From<PointerError> for ReferenceError {
+    fn from(error: PointerError) -> Self {
+        ReferenceError::InvalidPointer(error)
+    }
+}
+
+/// Errors produced when reading a [`to_delta_encoded`](crate::to_delta_encoded) document
+#[derive(Debug)]
+pub enum DeltaError {
+    /// Reading failed, or the input ended early
+    Io(std::io::Error),
+    /// The version byte is not one this crate writes
+    UnsupportedVersion(u8),
+    /// A length or count doesn't fit in a `usize`
+    Overflow,
+    /// Entry `index` has an invalid key or value
+    InvalidEntry { index: usize, reason: String },
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeltaError::Io(error) => error.fmt(f),
+            DeltaError::UnsupportedVersion(version) => {
+                write!(f, "unsupported delta encoding version {}", version)
+            }
+            DeltaError::Overflow => write!(f, "length does not fit in memory"),
+            DeltaError::InvalidEntry { index, reason } => {
+                write!(f, "invalid entry {}: {}", index, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeltaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeltaError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DeltaError {
+    fn from(error: std::io::Error) -> Self {
+        DeltaError::Io(error)
+    }
+}
+
+/// Errors produced when serializing canonical JSON, see [`to_canonical_string`](crate::to_canonical_string)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CanonError {
+    /// A number is not a finite IEEE 754 double
+    InvalidNumber(String),
+}
+
+impl fmt::Display for CanonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanonError::InvalidNumber(number) => {
+                write!(f, "number {} is not a finite double", number)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CanonError {}
+
+/// Errors produced when reading a typed value out of a flattened document, see
+/// [`FlattenedDocument::get_as`](crate::FlattenedDocument::get_as)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    /// The pointer is not a valid JSON Pointer
+    InvalidPointer(PointerError),
+    /// No entry is at or below the pointer
+    NotFound { pointer: String },
+    /// The entries below the pointer don't rebuild into a nested value
+    Unflatten(UnflattenError),
+    /// The value at the pointer doesn't deserialize into the requested type
+    Deserialize { pointer: String, message: String },
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::InvalidPointer(error) => error.fmt(f),
+            AccessError::NotFound { pointer } => {
+                write!(f, "no entry at or below \"{}\"", pointer)
+            }
+            AccessError::Unflatten(error) => error.fmt(f),
+            AccessError::Deserialize { pointer, message } => {
+                write!(
+                    f,
+                    "value at \"{}\" does not deserialize: {}",
+                    pointer, message
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AccessError::InvalidPointer(error) => Some(error),
+            AccessError::Unflatten(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<PointerError> for AccessError {
+    fn from(error: PointerError) -> Self {
+        AccessError::InvalidPointer(error)
+    }
+}
+
+impl From<UnflattenError> for AccessError {
+    fn from(error: UnflattenError) -> Self {
+        match error {
+            UnflattenError::InvalidPointer(error) => AccessError::InvalidPointer(error),
+            UnflattenError::NotFound { pointer } => AccessError::NotFound { pointer },
+            error => AccessError::Unflatten(error),
+        }
+    }
+}
+
+/// Errors produced by [`from_str_strict`](crate::from_str_strict)
+#[derive(Debug)]
+pub enum StrictParseError {
+    /// The input is not valid JSON
+    Parse(serde_json::Error),
+    /// An object has two members named `key`, the second one at `pointer`,
+    /// found at `line` and `column` of the input
+    DuplicateKey {
+        key: String,
+        pointer: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl fmt::Display for StrictParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrictParseError::Parse(error) => error.fmt(f),
+            StrictParseError::DuplicateKey {
+                key,
+                pointer,
+                line,
+                column,
+            } => write!(
+                f,
+                "duplicate key \"{}\" at \"{}\", line {} column {}",
+                key, pointer, line, column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StrictParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StrictParseError::Parse(error) => Some(error),
+            StrictParseError::DuplicateKey { .. } => None,
+        }
+    }
+}
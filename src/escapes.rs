@@ -0,0 +1,122 @@
+use crate::flattener::is_container;
+use crate::pointer::escape_into;
+use serde_json::Value;
+use std::fmt::Write;
+
+/// An object key that had to be escaped to become a pointer segment, see [`escaped_keys_report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapedKey {
+    /// Pointer of the object holding the key
+    pub pointer_of_parent: String,
+    /// The key as it appears in the document
+    pub original_key: String,
+    /// The key as it appears in pointers, with `~0` and `~1`
+    pub escaped_segment: String,
+}
+
+/// Find every object key containing `/` or `~`, in flattening order
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::EscapedKey;
+/// use serde_json::json;
+///
+/// let value = json!({ "paths": { "/users/{id}": { "get": {} } } });
+///
+/// assert_eq!(jsonpointer_flatten::escaped_keys_report(&value), vec![EscapedKey {
+///     pointer_of_parent: "/paths".to_owned(),
+///     original_key: "/users/{id}".to_owned(),
+///     escaped_segment: "~1users~1{id}".to_owned(),
+/// }]);
+/// ```
+pub fn escaped_keys_report(value: &Value) -> Vec<EscapedKey> {
+    let mut report = Vec::new();
+    let mut pointer = String::new();
+    collect(value, &mut pointer, &mut report);
+    report
+}
+
+/// Whether any object key contains `/` or `~`, stopping at the first one
+pub fn has_keys_requiring_escape(value: &Value) -> bool {
+    match value {
+        Value::Array(arr) => arr.iter().any(has_keys_requiring_escape),
+        Value::Object(obj) if is_container(value) => obj
+            .iter()
+            .any(|(key, val)| requires_escape(key) || has_keys_requiring_escape(val)),
+        _ => false,
+    }
+}
+
+fn collect(value: &Value, pointer: &mut String, report: &mut Vec<EscapedKey>) {
+    let len = pointer.len();
+    match value {
+        Value::Array(arr) => {
+            for (idx, val) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                collect(val, pointer, report);
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(obj) if is_container(value) => {
+            for (key, val) in obj {
+                pointer.push('/');
+                escape_into(key, pointer);
+                if requires_escape(key) {
+                    report.push(EscapedKey {
+                        pointer_of_parent: pointer[..len].to_owned(),
+                        original_key: key.clone(),
+                        escaped_segment: pointer[len + 1..].to_owned(),
+                    });
+                }
+                collect(val, pointer, report);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn requires_escape(key: &str) -> bool {
+    key.contains(&['/', '~'][..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_nested_keys_with_escaped_parents() {
+        let value = json!({ "a~b": [{ "c/d": 1, "e": 2 }], "f": {} });
+
+        let report = escaped_keys_report(&value);
+
+        assert_eq!(
+            report,
+            vec![
+                EscapedKey {
+                    pointer_of_parent: "".to_owned(),
+                    original_key: "a~b".to_owned(),
+                    escaped_segment: "a~0b".to_owned(),
+                },
+                EscapedKey {
+                    pointer_of_parent: "/a~0b/0".to_owned(),
+                    original_key: "c/d".to_owned(),
+                    escaped_segment: "c~1d".to_owned(),
+                },
+            ]
+        );
+        assert!(has_keys_requiring_escape(&value));
+    }
+
+    #[test]
+    fn plain_keys_need_no_escape() {
+        let value = json!({ "a": [{ "b": "/~ in values is fine" }], "": { "~": null } });
+
+        assert!(has_keys_requiring_escape(&value));
+        assert!(!has_keys_requiring_escape(&value["a"]));
+        assert!(escaped_keys_report(&value["a"]).is_empty());
+        assert!(!has_keys_requiring_escape(&json!("a/b")));
+    }
+}
@@ -0,0 +1,100 @@
+//! Serde helpers storing a nested field in flattened form
+//!
+//! With `#[serde(with = "jsonpointer_flatten::flat")]`, a field holding a
+//! nested value, like a [`Value`] or a [`Map`](serde_json::Map), is serialized
+//! as the object of pointer keys [`from_json`](crate::from_json) produces and
+//! rebuilt with [`unflatten`](crate::unflatten) when deserialized. See
+//! [`nested`](crate::nested) for the other way round.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::{json, Value};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Record {
+//!     id: u32,
+//!     #[serde(with = "jsonpointer_flatten::flat")]
+//!     extra: Value,
+//! }
+//!
+//! let record = Record { id: 1, extra: json!({ "tags": ["a"] }) };
+//! let stored = serde_json::to_value(&record).unwrap();
+//!
+//! assert_eq!(stored, json!({ "id": 1, "extra": { "": {}, "/tags": [], "/tags/0": "a" } }));
+//! assert_eq!(serde_json::from_value::<Record>(stored).unwrap(), record);
+//! ```
+use crate::{from_json, unflatten};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::Value;
+
+/// Serialize `value` flattened
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let nested = serde_json::to_value(value).map_err(ser::Error::custom)?;
+    from_json(&nested).serialize(serializer)
+}
+
+/// Deserialize flattened entries and unflatten them into a `T`
+///
+/// Entries that don't form a document fail with the message of the
+/// [`UnflattenError`](crate::UnflattenError), which names the pointer.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    let flattened = Value::deserialize(deserializer)?;
+    let nested = unflatten(&flattened)
+        .map_err(|error| de::Error::custom(format!("invalid flattened value: {}", error)))?;
+    serde_json::from_value(nested).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Map, Value};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        #[serde(with = "crate::flat")]
+        extra: Value,
+        #[serde(with = "crate::flat")]
+        labels: Map<String, Value>,
+    }
+
+    #[test]
+    fn round_trips_through_json_text() {
+        let record = Record {
+            id: 7,
+            extra: json!({ "a/b": [1, { "c": null }] }),
+            labels: serde_json::from_value(json!({ "env": "prod" })).unwrap(),
+        };
+
+        let text = serde_json::to_string(&record).unwrap();
+        let stored: Value = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(stored["extra"], crate::from_json(&record.extra));
+        assert_eq!(stored["labels"], json!({ "": {}, "/env": "prod" }));
+        assert_eq!(serde_json::from_str::<Record>(&text).unwrap(), record);
+    }
+
+    #[test]
+    fn unflatten_errors_become_serde_errors() {
+        let stored = json!({
+            "id": 7,
+            "extra": { "/a": 1, "/a/b": 2 },
+            "labels": { "": {} }
+        });
+
+        let error = serde_json::from_value::<Record>(stored).unwrap_err();
+
+        assert!(error.to_string().contains("\"/a/b\""), "{}", error);
+    }
+}
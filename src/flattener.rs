@@ -0,0 +1,1730 @@
+use crate::canonical;
+use crate::error::FlattenError;
+use crate::escapes::{escaped_keys_report, has_keys_requiring_escape};
+use crate::limits::{estimated_len, Limits};
+#[cfg(feature = "unicode-normalization")]
+use crate::normalize::KeyNormalization;
+use crate::numbers::normalized_leaf;
+use crate::pointer::escape_into;
+use crate::PointerPattern;
+use serde_json::{json, Map, Value};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ops::ControlFlow;
+
+const DEFAULT_PROGRESS_INTERVAL: usize = 1024;
+
+/// What to do when a flattened key already exists in the target map
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Replace the existing value (the default)
+    #[default]
+    Overwrite,
+    /// Stop and return [`FlattenError::KeyConflict`]
+    Error,
+}
+
+/// What is stored for container entries
+///
+/// Only [`ContainerValue::Empty`] round-trips through [`unflatten`](crate::unflatten):
+/// counts are scalars, so [`unflatten`](crate::unflatten) rejects counted
+/// containers that have children with [`UnflattenError::NotAContainer`](crate::UnflattenError::NotAContainer)
+/// and rebuilds empty ones as `0`. Without placeholders, skipped containers
+/// are inferred from their children's segments and empty ones are lost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContainerValue {
+    /// An empty `[]` or `{}` (the default)
+    #[default]
+    Empty,
+    /// Number of elements or keys
+    Count,
+    /// No entry at all, only leaves are stored. Skipped containers still count
+    /// towards [`Limits::max_entries`].
+    Skip,
+}
+
+impl ContainerValue {
+    /// The entry stored for `value`, if any
+    pub(crate) fn entry(self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (ContainerValue::Empty, _) => Some(entry_value(value)),
+            _ if !is_container(value) => Some(entry_value(value)),
+            (ContainerValue::Count, Value::Array(arr)) => Some(arr.len().into()),
+            (ContainerValue::Count, Value::Object(obj)) => Some(obj.len().into()),
+            _ => None,
+        }
+    }
+}
+
+/// How the values of entries are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryFormat {
+    /// The value itself, or what [`ContainerValue`] says for containers (the default)
+    #[default]
+    Plain,
+    /// An object tagging the value with its JSON type, like
+    /// `{ "type": "number", "value": 42 }`
+    ///
+    /// Types are `null`, `boolean`, `number`, `string`, `object` and `array`.
+    /// Containers carry their number of children instead of a value, as in
+    /// `{ "type": "array", "count": 2 }`, unless [`ContainerValue::Skip`] leaves them out.
+    Typed,
+}
+
+/// The order in which nodes are visited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// Each container is followed by its whole subtree (the default)
+    #[default]
+    DepthFirst,
+    /// All nodes of one depth come before any node of the next. The children
+    /// of a container still follow each other directly, in index or key order.
+    BreadthFirst,
+}
+
+/// Summary of a [`flatten_into`] call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InsertStats {
+    /// Number of entries written into the target map
+    pub inserted: usize,
+    /// Whether any of the written entries replaced an existing key
+    pub overwritten: bool,
+}
+
+/// Result of [`Flattener::flatten_truncated`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruncatedOutput {
+    /// The flattened object, holding every entry emitted before the cut-off
+    pub value: Value,
+    /// Estimated size of the emitted entries, see [`Limits::max_total_output_bytes`]
+    pub bytes: usize,
+    /// Pointer of the first entry that didn't fit, if any
+    pub cut_off: Option<String>,
+}
+
+/// Snapshot passed to the callback of [`Flattener::flatten_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of entries emitted so far
+    pub entries: usize,
+    /// Number of segments in the pointer of the most recently visited node
+    pub depth: usize,
+}
+
+/// Reusable flattener
+///
+/// A flattener only holds its options, so it is `Send + Sync` and every method
+/// takes `&self`: configure one at startup and share it, e.g. in an
+/// [`Arc`](std::sync::Arc), between threads. Each call allocates its own
+/// pointer buffer, use a [`FlattenerScratch`] per thread to reuse one instead.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Flattener, OnConflict};
+/// use serde_json::{json, Map};
+///
+/// let flattener = Flattener::new().on_conflict(OnConflict::Error);
+/// let mut target = Map::new();
+///
+/// flattener.flatten_into(&json!({ "id": 1 }), &mut target, "/0").unwrap();
+/// flattener.flatten_into(&json!({ "id": 2 }), &mut target, "/1").unwrap();
+///
+/// assert_eq!(target.get("/1/id"), Some(&json!(2)));
+/// ```
+///
+/// Sharing one flattener between threads:
+///
+/// ```
+/// use jsonpointer_flatten::{Flattener, Limits};
+/// use serde_json::json;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let flattener = Arc::new(Flattener::new().limits(Limits::new().max_depth(8)));
+///
+/// let workers: Vec<_> = (0..2)
+///     .map(|id| {
+///         let flattener = Arc::clone(&flattener);
+///         thread::spawn(move || flattener.try_flatten(&json!({ "id": id })))
+///     })
+///     .collect();
+///
+/// for (id, worker) in workers.into_iter().enumerate() {
+///     assert_eq!(worker.join().unwrap(), Ok(json!({ "": {}, "/id": id })));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Flattener {
+    on_conflict: OnConflict,
+    progress_interval: usize,
+    limits: Limits,
+    order: TraversalOrder,
+    containers: ContainerValue,
+    format: EntryFormat,
+    root: String,
+    forbid_escapable_keys: bool,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Option<KeyNormalization>,
+    sort_arrays: Vec<PointerPattern>,
+    normalize_numbers: bool,
+}
+
+impl Default for Flattener {
+    fn default() -> Self {
+        Flattener {
+            on_conflict: OnConflict::default(),
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            limits: Limits::default(),
+            order: TraversalOrder::default(),
+            containers: ContainerValue::default(),
+            format: EntryFormat::default(),
+            root: String::new(),
+            forbid_escapable_keys: false,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: None,
+            sort_arrays: Vec::new(),
+            normalize_numbers: false,
+        }
+    }
+}
+
+impl Flattener {
+    /// Create a flattener with default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many nodes are visited between calls to the progress callback
+    /// of [`Flattener::flatten_with_progress`] (default 1024, minimum 1)
+    pub fn progress_interval(mut self, entries: usize) -> Self {
+        self.progress_interval = entries.max(1);
+        self
+    }
+
+    /// Set the behavior of [`Flattener::flatten_into`] for keys already present in the target
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Set the limits enforced by the fallible flatten methods
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the order in which entries are produced
+    ///
+    /// Only observable in the key order of the output with serde_json's
+    /// `preserve_order` feature, and in which entry trips a limit first.
+    pub fn traversal_order(mut self, order: TraversalOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Set what is stored for arrays and objects
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{ContainerValue, Flattener};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().container_value(ContainerValue::Count);
+    /// let flattened = flattener.flatten(&json!({ "phones": ["123", "456"] }));
+    ///
+    /// assert_eq!(flattened["/phones"], json!(2));
+    /// ```
+    pub fn container_value(mut self, containers: ContainerValue) -> Self {
+        self.containers = containers;
+        self
+    }
+
+    /// Set how entry values are written
+    ///
+    /// Unflatten [`EntryFormat::Typed`] output with
+    /// [`UnflattenOptions::entry_format`](crate::UnflattenOptions::entry_format).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{EntryFormat, Flattener};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().entry_format(EntryFormat::Typed);
+    ///
+    /// assert_eq!(
+    ///     flattener.flatten(&json!(["42", 42])),
+    ///     json!({
+    ///         "": { "type": "array", "count": 2 },
+    ///         "/0": { "type": "string", "value": "42" },
+    ///         "/1": { "type": "number", "value": 42 }
+    ///     })
+    /// );
+    /// ```
+    pub fn entry_format(mut self, format: EntryFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the key of the root entry, `""` by default
+    ///
+    /// Use the same key with [`UnflattenOptions::root_key`](crate::UnflattenOptions::root_key)
+    /// to unflatten again. A non-empty prefix passed to [`Flattener::flatten_into`]
+    /// replaces the root key entirely. Note that `/` is also the pointer of an
+    /// empty top-level key, whose entry would then replace the root entry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::Flattener;
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().root_key("$root");
+    ///
+    /// assert_eq!(flattener.flatten(&json!([1])), json!({ "$root": [], "/0": 1 }));
+    /// ```
+    pub fn root_key(mut self, key: impl Into<String>) -> Self {
+        self.root = key.into();
+        self
+    }
+
+    /// Fail with [`FlattenError::EscapableKeys`] on keys containing `/` or `~`
+    ///
+    /// Applies to the fallible methods, [`Flattener::try_flatten`] and those
+    /// returning a [`Result`]. Every offending key is listed, with its parent
+    /// pointer including any prefix, see [`escaped_keys_report`](crate::escaped_keys_report).
+    /// Keys are only checked once the value is within the depth, entry and
+    /// pointer length [`Limits`], whose errors come first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{FlattenError, Flattener};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().forbid_escapable_keys(true);
+    ///
+    /// match flattener.try_flatten(&json!({ "a/b": 1, "c": { "d~": 2 } })) {
+    ///     Err(FlattenError::EscapableKeys(keys)) => assert_eq!(keys.len(), 2),
+    ///     other => panic!("unexpected {:?}", other),
+    /// }
+    /// ```
+    pub fn forbid_escapable_keys(mut self, forbid: bool) -> Self {
+        self.forbid_escapable_keys = forbid;
+        self
+    }
+
+    /// Normalize object keys before escaping them
+    ///
+    /// Keys that only differ in their Unicode representation then map to the
+    /// same pointer, so objects below them are merged and the last leaf wins.
+    /// The fallible methods fail with [`FlattenError::NormalizedKeyCollisions`]
+    /// instead, listing every set of distinct keys of an object that normalize
+    /// to the same key. Use [`UnflattenOptions::normalize_keys`](crate::UnflattenOptions::normalize_keys)
+    /// with the same normalization when unflattening.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{Flattener, KeyNormalization};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().normalize_keys(KeyNormalization::Nfc);
+    /// let flattened = flattener.flatten(&json!({ "cafe\u{301}": { "open": true } }));
+    ///
+    /// assert_eq!(flattened["/caf\u{e9}/open"], json!(true));
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalize_keys(mut self, normalization: KeyNormalization) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    /// Sort the elements of arrays at pointers matching any of `patterns`, and of every array below them
+    ///
+    /// This gives arrays that are really sets the same entries whatever their
+    /// order, for hashing or diffing, at the cost of the original order. Pass
+    /// a pattern `""` to sort every array. Patterns match the output pointers,
+    /// so indices below a sorted array are the sorted ones. Calling this again
+    /// replaces the patterns, and an empty `Vec` sorts nothing.
+    ///
+    /// The order is total: by type first, `null < boolean < number < string <
+    /// array < object`, then by value. Numbers compare numerically, with ties
+    /// like `1` and `1.0` broken by their text, and strings by code points.
+    /// Arrays compare element by element in their own sorted order, then by
+    /// length. Objects do the same over their members sorted by key, members
+    /// comparing by key, then by value. Equal elements keep their order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{Flattener, PointerPattern};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().sort_arrays(vec![PointerPattern::new("/tags").unwrap()]);
+    ///
+    /// assert_eq!(
+    ///     flattener.flatten(&json!({ "tags": ["b", "a"] })),
+    ///     flattener.flatten(&json!({ "tags": ["a", "b"] }))
+    /// );
+    /// ```
+    pub fn sort_arrays(mut self, patterns: Vec<PointerPattern>) -> Self {
+        self.sort_arrays = patterns;
+        self
+    }
+
+    /// Store numbers in their canonical form, see [`normalize_number`](crate::normalize_number)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::Flattener;
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().normalize_numbers(true);
+    ///
+    /// assert_eq!(flattener.flatten(&json!([1.0, -0.0])), json!({ "": [], "/0": 1, "/1": 0 }));
+    /// ```
+    pub fn normalize_numbers(mut self, normalize: bool) -> Self {
+        self.normalize_numbers = normalize;
+        self
+    }
+
+    /// Flatten a JSON value into a new object
+    ///
+    /// [`Limits`] are not enforced here, use [`Flattener::try_flatten`] for untrusted input.
+    pub fn flatten(&self, value: &Value) -> Value {
+        Value::Object(self.flatten_map(value))
+    }
+
+    pub(crate) fn flatten_map(&self, value: &Value) -> Map<String, Value> {
+        self.flatten_map_in(&mut String::new(), value)
+    }
+
+    fn flatten_map_in(&self, pointer: &mut String, value: &Value) -> Map<String, Value> {
+        let mut target = Map::new();
+
+        self.visit_in(pointer, value, |key, _, _, val| {
+            if let Some(entry) = self.entry(val) {
+                target.insert(key.to_owned(), entry);
+            }
+        });
+
+        target
+    }
+
+    /// Flatten a JSON value into a new object, enforcing the configured [`Limits`]
+    pub fn try_flatten(&self, value: &Value) -> Result<Value, FlattenError> {
+        self.try_flatten_in(&mut String::new(), value)
+    }
+
+    fn try_flatten_in(&self, pointer: &mut String, value: &Value) -> Result<Value, FlattenError> {
+        self.check_keys(pointer, value, "")?;
+        let mut target = Map::new();
+
+        self.walk_in(pointer, value, "", &self.limits, |key, _, val| {
+            if let Some(entry) = self.entry(val) {
+                target.insert(key.to_owned(), entry);
+            }
+            Ok(())
+        })?;
+
+        Ok(Value::Object(target))
+    }
+
+    /// Flatten a JSON value, stopping once [`Limits::max_total_output_bytes`] is reached
+    ///
+    /// Instead of failing, the output is cut off before the first entry that
+    /// would cross the budget and the pointer of that entry is recorded. Entries
+    /// come in traversal order, so with [`TraversalOrder::BreadthFirst`] the
+    /// shallow parts of the document are kept first. Other [`Limits`] still fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{Flattener, Limits};
+    /// use serde_json::json;
+    ///
+    /// let flattener = Flattener::new().limits(Limits::new().max_total_output_bytes(10));
+    ///
+    /// let output = flattener.flatten_truncated(&json!([1, 2, 3])).unwrap();
+    ///
+    /// assert_eq!(output.value, json!({ "": [], "/0": 1, "/1": 2 }));
+    /// assert_eq!(output.bytes, 8);
+    /// assert_eq!(output.cut_off, Some("/2".to_owned()));
+    /// ```
+    pub fn flatten_truncated(&self, value: &Value) -> Result<TruncatedOutput, FlattenError> {
+        self.flatten_truncated_in(&mut String::new(), value)
+    }
+
+    fn flatten_truncated_in(
+        &self,
+        pointer: &mut String,
+        value: &Value,
+    ) -> Result<TruncatedOutput, FlattenError> {
+        self.check_keys(pointer, value, "")?;
+        let mut target = Map::new();
+
+        let result = self.walk_sized_in(pointer, value, "", &self.limits, |key, _, _, val| {
+            if let Some(entry) = self.entry(val) {
+                target.insert(key.to_owned(), entry);
+            }
+            Ok(())
+        });
+
+        let (bytes, cut_off) = match result {
+            Ok(bytes) => (bytes, None),
+            Err(FlattenError::OutputSizeExceeded { pointer, bytes, .. }) => (bytes, Some(pointer)),
+            Err(error) => return Err(error),
+        };
+        Ok(TruncatedOutput {
+            value: Value::Object(target),
+            bytes,
+            cut_off,
+        })
+    }
+
+    /// Flatten a JSON value, reporting progress and allowing cancellation
+    ///
+    /// `progress` is called every [`Flattener::progress_interval`] visited
+    /// nodes, containers included whether or not they get an entry.
+    /// Returning [`ControlFlow::Break`] stops the traversal right away and the
+    /// partial output is discarded in favor of [`FlattenError::Cancelled`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{FlattenError, Flattener};
+    /// use serde_json::json;
+    /// use std::ops::ControlFlow;
+    ///
+    /// let flattener = Flattener::new().progress_interval(2);
+    ///
+    /// let result = flattener.flatten_with_progress(&json!([1, 2, 3, 4]), |progress| {
+    ///     if progress.entries >= 4 {
+    ///         ControlFlow::Break(())
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(result, Err(FlattenError::Cancelled));
+    /// ```
+    pub fn flatten_with_progress<F>(
+        &self,
+        value: &Value,
+        progress: F,
+    ) -> Result<Value, FlattenError>
+    where
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        self.flatten_with_progress_in(&mut String::new(), value, progress)
+    }
+
+    fn flatten_with_progress_in<F>(
+        &self,
+        pointer: &mut String,
+        value: &Value,
+        mut progress: F,
+    ) -> Result<Value, FlattenError>
+    where
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        self.check_keys(pointer, value, "")?;
+        let interval = self.progress_interval;
+        let mut target = Map::new();
+        let mut visited = 0;
+
+        self.walk_in(pointer, value, "", &self.limits, |key, depth, val| {
+            // counted per node, as skipped containers and overwritten keys don't grow `target`
+            visited += 1;
+            if let Some(entry) = self.entry(val) {
+                target.insert(key.to_owned(), entry);
+            }
+            if visited % interval == 0 {
+                let entries = target.len();
+                if let ControlFlow::Break(()) = progress(Progress { entries, depth }) {
+                    return Err(FlattenError::Cancelled);
+                }
+            }
+            Ok(())
+        })?;
+
+        Ok(Value::Object(target))
+    }
+
+    /// Flatten a JSON value directly into an existing map
+    ///
+    /// Every key is prepended with `prefix`, which is used verbatim and should
+    /// already be a valid (escaped) pointer, or empty. With [`OnConflict::Error`]
+    /// entries written before the conflicting key remain in `target`.
+    pub fn flatten_into(
+        &self,
+        value: &Value,
+        target: &mut Map<String, Value>,
+        prefix: &str,
+    ) -> Result<InsertStats, FlattenError> {
+        self.flatten_into_in(&mut String::new(), value, target, prefix)
+    }
+
+    fn flatten_into_in(
+        &self,
+        pointer: &mut String,
+        value: &Value,
+        target: &mut Map<String, Value>,
+        prefix: &str,
+    ) -> Result<InsertStats, FlattenError> {
+        self.check_keys(pointer, value, prefix)?;
+        let on_conflict = self.on_conflict;
+        let mut stats = InsertStats::default();
+
+        self.walk_in(pointer, value, prefix, &self.limits, |key, _, val| {
+            let entry = match self.entry(val) {
+                Some(entry) => entry,
+                None => return Ok(()),
+            };
+            if target.contains_key(key) {
+                if on_conflict == OnConflict::Error {
+                    return Err(FlattenError::KeyConflict(key.to_owned()));
+                }
+                stats.overwritten = true;
+            }
+            target.insert(key.to_owned(), entry);
+            stats.inserted += 1;
+            Ok(())
+        })?;
+
+        Ok(stats)
+    }
+
+    pub(crate) fn root(&self) -> &str {
+        &self.root
+    }
+
+    fn check_keys(
+        &self,
+        pointer: &mut String,
+        value: &Value,
+        prefix: &str,
+    ) -> Result<(), FlattenError> {
+        #[cfg(feature = "unicode-normalization")]
+        let normalizes = self.normalization.is_some();
+        #[cfg(not(feature = "unicode-normalization"))]
+        let normalizes = false;
+        if !normalizes && !self.forbid_escapable_keys {
+            return Ok(());
+        }
+
+        // The checks recurse over the whole value, so first walk it with the
+        // limits that bound its depth. Output bytes are left to the real walk,
+        // which truncates instead of failing in `flatten_truncated`.
+        let limits = Limits {
+            max_total_output_bytes: None,
+            ..self.limits
+        };
+        if limits != Limits::default() {
+            self.walk_in(pointer, value, prefix, &limits, |_, _, _| Ok(()))?;
+        }
+
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(normalization) = self.normalization {
+            let mut collisions = crate::normalize::collisions(value, normalization);
+            if !collisions.is_empty() {
+                for collision in &mut collisions {
+                    collision.pointer_of_parent.insert_str(0, prefix);
+                }
+                return Err(FlattenError::NormalizedKeyCollisions(collisions));
+            }
+        }
+        if !self.forbid_escapable_keys || !has_keys_requiring_escape(value) {
+            return Ok(());
+        }
+        let mut keys = escaped_keys_report(value);
+        for key in &mut keys {
+            key.pointer_of_parent.insert_str(0, prefix);
+        }
+        Err(FlattenError::EscapableKeys(keys))
+    }
+
+    /// The entry stored for `value`, if any
+    pub(crate) fn entry(&self, value: &Value) -> Option<Value> {
+        // objects that are not containers carry `arbitrary_precision` numbers
+        let number = matches!(value, Value::Number(_) | Value::Object(_)) && !is_container(value);
+        if self.normalize_numbers && number {
+            return self.format_entry(&normalized_leaf(value));
+        }
+        self.format_entry(value)
+    }
+
+    fn format_entry(&self, value: &Value) -> Option<Value> {
+        match self.format {
+            EntryFormat::Plain => self.containers.entry(value),
+            EntryFormat::Typed
+                if self.containers == ContainerValue::Skip && is_container(value) =>
+            {
+                None
+            }
+            EntryFormat::Typed => Some(typed_entry(value)),
+        }
+    }
+
+    /// Visit every node in flattening order with its pointer, depth and array index
+    ///
+    /// Containers are passed as themselves, see [`entry_value`] for what gets
+    /// stored. Nothing is limited, so unlike [`Flattener::walk_in`] this can't fail.
+    pub(crate) fn visit<'v, F>(&self, value: &'v Value, visit: F)
+    where
+        F: FnMut(&str, usize, Option<usize>, &'v Value),
+    {
+        self.visit_in(&mut String::new(), value, visit)
+    }
+
+    /// Like [`Flattener::visit`], building pointers in `pointer`
+    fn visit_in<'v, F>(&self, pointer: &mut String, value: &'v Value, mut visit: F)
+    where
+        F: FnMut(&str, usize, Option<usize>, &'v Value),
+    {
+        let limits = Limits::default();
+        let walked = self.walk_nodes_in(pointer, value, "", &limits, |key, depth, index, val| {
+            visit(key, depth, index, val);
+            Ok(())
+        });
+        if walked.is_err() {
+            unreachable!("only limits and the callback fail a walk");
+        }
+    }
+
+    /// Visit every node in flattening order with its pointer and depth,
+    /// building pointers in `pointer` and enforcing `limits`
+    ///
+    /// Containers are passed as themselves, see [`entry_value`] for what gets stored.
+    fn walk_in<'v, F>(
+        &self,
+        pointer: &mut String,
+        value: &'v Value,
+        prefix: &str,
+        limits: &Limits,
+        mut emit: F,
+    ) -> Result<(), FlattenError>
+    where
+        F: FnMut(&str, usize, &'v Value) -> Result<(), FlattenError>,
+    {
+        self.walk_nodes_in(pointer, value, prefix, limits, |key, depth, _, val| {
+            emit(key, depth, val)
+        })
+    }
+
+    /// Like [`Flattener::walk_in`], also passing the index of array elements
+    fn walk_nodes_in<'v, F>(
+        &self,
+        pointer: &mut String,
+        value: &'v Value,
+        prefix: &str,
+        limits: &Limits,
+        emit: F,
+    ) -> Result<(), FlattenError>
+    where
+        F: FnMut(&str, usize, Option<usize>, &'v Value) -> Result<(), FlattenError>,
+    {
+        self.walk_sized_in(pointer, value, prefix, limits, emit)
+            .map(|_| ())
+    }
+
+    /// Like [`Flattener::walk_nodes_in`], returning the bytes counted towards
+    /// [`Limits::max_total_output_bytes`], which are only counted if it is set
+    fn walk_sized_in<'v, F>(
+        &self,
+        pointer: &mut String,
+        value: &'v Value,
+        prefix: &str,
+        limits: &Limits,
+        mut emit: F,
+    ) -> Result<usize, FlattenError>
+    where
+        F: FnMut(&str, usize, Option<usize>, &'v Value) -> Result<(), FlattenError>,
+    {
+        pointer.clear();
+        pointer.push_str(prefix);
+
+        let root = self.root.as_str();
+        let emit = move |key: &str, depth, index, val| {
+            // only the root of an unprefixed walk has an empty pointer
+            emit(if key.is_empty() { root } else { key }, depth, index, val)
+        };
+        let mut walk = Walk {
+            limits,
+            pointer,
+            #[cfg(feature = "unicode-normalization")]
+            normalization: self.normalization,
+            sort_arrays: &self.sort_arrays,
+            index: None,
+            entries: 0,
+            output_bytes: 0,
+            emit,
+        };
+        match self.order {
+            TraversalOrder::DepthFirst => walk.process(value, 0, false)?,
+            TraversalOrder::BreadthFirst => walk.process_breadth_first(value)?,
+        }
+        Ok(walk.output_bytes)
+    }
+}
+
+/// A [`Flattener`] with a pointer buffer that is kept between calls
+///
+/// Flattening many documents through the same scratch avoids allocating a
+/// fresh buffer per document. Create one per thread, as its methods take `&mut self`.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Flattener, FlattenerScratch};
+/// use serde_json::json;
+///
+/// let flattener = Flattener::new();
+/// let mut scratch = FlattenerScratch::new(&flattener);
+///
+/// for id in 0..3 {
+///     assert_eq!(scratch.flatten(&json!([id])), json!({ "": [], "/0": id }));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlattenerScratch<'a> {
+    flattener: &'a Flattener,
+    pointer: String,
+}
+
+impl<'a> FlattenerScratch<'a> {
+    /// Create an empty scratch for `flattener`
+    pub fn new(flattener: &'a Flattener) -> Self {
+        FlattenerScratch {
+            flattener,
+            pointer: String::new(),
+        }
+    }
+
+    /// See [`Flattener::flatten`]
+    pub fn flatten(&mut self, value: &Value) -> Value {
+        Value::Object(self.flattener.flatten_map_in(&mut self.pointer, value))
+    }
+
+    /// See [`Flattener::try_flatten`]
+    pub fn try_flatten(&mut self, value: &Value) -> Result<Value, FlattenError> {
+        self.flattener.try_flatten_in(&mut self.pointer, value)
+    }
+
+    /// See [`Flattener::flatten_truncated`]
+    pub fn flatten_truncated(&mut self, value: &Value) -> Result<TruncatedOutput, FlattenError> {
+        self.flattener
+            .flatten_truncated_in(&mut self.pointer, value)
+    }
+
+    /// See [`Flattener::flatten_with_progress`]
+    pub fn flatten_with_progress<F>(
+        &mut self,
+        value: &Value,
+        progress: F,
+    ) -> Result<Value, FlattenError>
+    where
+        F: FnMut(Progress) -> ControlFlow<()>,
+    {
+        self.flattener
+            .flatten_with_progress_in(&mut self.pointer, value, progress)
+    }
+
+    /// See [`Flattener::flatten_into`]
+    pub fn flatten_into(
+        &mut self,
+        value: &Value,
+        target: &mut Map<String, Value>,
+        prefix: &str,
+    ) -> Result<InsertStats, FlattenError> {
+        self.flattener
+            .flatten_into_in(&mut self.pointer, value, target, prefix)
+    }
+}
+
+/// Flatten a JSON value directly into an existing map, prepending `prefix` to every key
+///
+/// Existing keys are overwritten; use [`Flattener::on_conflict`] to error instead.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{json, Map};
+///
+/// let mut target = Map::new();
+/// let stats = jsonpointer_flatten::flatten_into(&json!({ "a": 1 }), &mut target, "/doc");
+///
+/// assert_eq!(stats.inserted, 2);
+/// assert_eq!(target.get("/doc/a"), Some(&json!(1)));
+/// ```
+pub fn flatten_into(value: &Value, target: &mut Map<String, Value>, prefix: &str) -> InsertStats {
+    Flattener::new()
+        .flatten_into(value, target, prefix)
+        .expect("overwrite mode never fails")
+}
+
+/// Flatten a JSON value, enforcing `limits`
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{FlattenError, Limits};
+/// use serde_json::json;
+///
+/// let limits = Limits::new().max_entries(2);
+///
+/// let result = jsonpointer_flatten::try_flatten(&json!(["a", "b"]), &limits);
+///
+/// assert_eq!(
+///     result,
+///     Err(FlattenError::EntryLimitExceeded { limit: 2, pointer: "/1".to_owned() })
+/// );
+/// ```
+pub fn try_flatten(value: &Value, limits: &Limits) -> Result<Value, FlattenError> {
+    Flattener::new().limits(*limits).try_flatten(value)
+}
+
+/// The value stored for a node: an empty placeholder for containers, a copy otherwise
+pub(crate) fn entry_value(value: &Value) -> Value {
+    match value {
+        Value::Array(_) => json!([]),
+        #[cfg(feature = "arbitrary_precision")]
+        Value::Object(obj) if private_number(obj).is_some() => {
+            private_number(obj).unwrap_or(Value::Null)
+        }
+        Value::Object(_) => json!({}),
+        _ => value.clone(),
+    }
+}
+
+/// Length of `key` once escaped, without escaping it
+fn escaped_len(key: &str) -> usize {
+    key.len() + key.bytes().filter(|b| *b == b'~' || *b == b'/').count()
+}
+
+/// Element `idx` of `arr`, taken from the canonical `order` if sorted
+fn element<'v>(arr: &'v [Value], order: &Option<Vec<&'v Value>>, idx: usize) -> &'v Value {
+    match order {
+        Some(order) => order[idx],
+        None => &arr[idx],
+    }
+}
+
+/// The [`EntryFormat::Typed`] entry of a node
+fn typed_entry(value: &Value) -> Value {
+    let (kind, member, inner) = match value {
+        Value::Null => ("null", "value", Value::Null),
+        Value::Bool(_) => ("boolean", "value", value.clone()),
+        Value::Number(_) => ("number", "value", value.clone()),
+        Value::String(_) => ("string", "value", value.clone()),
+        Value::Array(arr) => ("array", "count", arr.len().into()),
+        Value::Object(obj) if is_container(value) => ("object", "count", obj.len().into()),
+        Value::Object(_) => ("number", "value", entry_value(value)),
+    };
+    let mut entry = Map::new();
+    entry.insert("type".to_owned(), kind.into());
+    entry.insert(member.to_owned(), inner);
+    Value::Object(entry)
+}
+
+/// Whether a node is flattened as a placeholder followed by its children
+pub(crate) fn is_container(value: &Value) -> bool {
+    match value {
+        Value::Array(_) => true,
+        #[cfg(feature = "arbitrary_precision")]
+        Value::Object(obj) => private_number(obj).is_none(),
+        #[cfg(not(feature = "arbitrary_precision"))]
+        Value::Object(_) => true,
+        _ => false,
+    }
+}
+
+/// Recognize the object serde_json uses to carry an `arbitrary_precision` number
+///
+/// serde_json itself never leaves this shape inside a [`Value`], but a value
+/// assembled by hand or by another serializer can, and it must stay a single
+/// number leaf rather than being descended into.
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) fn private_number(obj: &Map<String, Value>) -> Option<Value> {
+    const TOKEN: &str = "$serde_json::private::Number";
+
+    if obj.len() != 1 {
+        return None;
+    }
+    match obj.get(TOKEN) {
+        Some(Value::String(s)) => serde_json::from_str(s).ok().map(Value::Number),
+        _ => None,
+    }
+}
+
+struct Walk<'a, F> {
+    limits: &'a Limits,
+    pointer: &'a mut String,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Option<KeyNormalization>,
+    sort_arrays: &'a [PointerPattern],
+    /// Array index of the node about to be emitted, if it's an array element
+    index: Option<usize>,
+    entries: usize,
+    output_bytes: usize,
+    emit: F,
+}
+
+impl<'v, F> Walk<'_, F>
+where
+    F: FnMut(&str, usize, Option<usize>, &'v Value) -> Result<(), FlattenError>,
+{
+    /// `sorted` tells whether `value` is below a match of [`Flattener::sort_arrays`]
+    fn process(
+        &mut self,
+        value: &'v Value,
+        depth: usize,
+        sorted: bool,
+    ) -> Result<(), FlattenError> {
+        let len = self.pointer.len();
+        let sorted = sorted || self.sorts(value);
+        match value {
+            Value::Array(arr) => {
+                self.emit(depth, value)?;
+                let order = sorted.then(|| canonical::sorted(arr));
+                for idx in 0..arr.len() {
+                    self.push_index(idx, depth + 1)?;
+                    self.index = Some(idx);
+                    self.process(element(arr, &order, idx), depth + 1, sorted)?;
+                    self.pointer.truncate(len);
+                }
+            }
+            Value::Object(obj) => {
+                #[cfg(feature = "arbitrary_precision")]
+                if private_number(obj).is_some() {
+                    return self.emit(depth, value);
+                }
+                self.emit(depth, value)?;
+                for (key, val) in obj {
+                    self.push_key(key, depth + 1)?;
+                    self.index = None;
+                    self.process(val, depth + 1, sorted)?;
+                    self.pointer.truncate(len);
+                }
+            }
+            _ => self.emit(depth, value)?,
+        }
+        Ok(())
+    }
+
+    fn process_breadth_first(&mut self, root: &'v Value) -> Result<(), FlattenError> {
+        let mut queue = VecDeque::new();
+        self.enqueue(&mut queue, 0, root, false)?;
+
+        while let Some((pointer, depth, container, sorted)) = queue.pop_front() {
+            match container {
+                Value::Array(arr) => {
+                    let order = sorted.then(|| canonical::sorted(arr));
+                    for idx in 0..arr.len() {
+                        self.pointer.clone_from(&pointer);
+                        self.push_index(idx, depth + 1)?;
+                        self.index = Some(idx);
+                        let val = element(arr, &order, idx);
+                        self.enqueue(&mut queue, depth + 1, val, sorted)?;
+                    }
+                }
+                Value::Object(obj) => {
+                    for (key, val) in obj {
+                        self.pointer.clone_from(&pointer);
+                        self.push_key(key, depth + 1)?;
+                        self.index = None;
+                        self.enqueue(&mut queue, depth + 1, val, sorted)?;
+                    }
+                }
+                _ => unreachable!("only containers are queued"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the children of the container at the current pointer are sorted
+    fn sorts(&self, value: &Value) -> bool {
+        is_container(value)
+            && self
+                .sort_arrays
+                .iter()
+                .any(|pattern| pattern.matches(self.pointer))
+    }
+
+    /// Append the segment of `key` for a node at `depth`
+    ///
+    /// [`Limits::max_pointer_len`] is checked first, so an oversized key is
+    /// never copied into the pointer.
+    fn push_key(&mut self, key: &str, depth: usize) -> Result<(), FlattenError> {
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(normalization) = self.normalization {
+            self.check_pointer_len(depth, 1 + normalization.escaped_len(key))?;
+            let key = normalization.apply(key);
+            self.pointer.push('/');
+            escape_into(&key, self.pointer);
+            return Ok(());
+        }
+        self.check_pointer_len(depth, 1 + escaped_len(key))?;
+        self.pointer.push('/');
+        escape_into(key, self.pointer);
+        Ok(())
+    }
+
+    /// Append the segment of array index `idx` for a node at `depth`
+    fn push_index(&mut self, idx: usize, depth: usize) -> Result<(), FlattenError> {
+        let digits = std::iter::successors(Some(idx), |n| (*n >= 10).then(|| n / 10)).count();
+        self.check_pointer_len(depth, 1 + digits)?;
+        let _ = write!(self.pointer, "/{}", idx);
+        Ok(())
+    }
+
+    /// Fail if appending `added` bytes to the current pointer would cross [`Limits::max_pointer_len`]
+    fn check_pointer_len(&self, depth: usize, added: usize) -> Result<(), FlattenError> {
+        match self.limits.max_pointer_len {
+            Some(limit) if self.pointer.len() + added > limit => {
+                Err(FlattenError::PointerLengthExceeded {
+                    limit,
+                    depth,
+                    parent: self.pointer.to_string(),
+                    len: self.pointer.len() + added,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Emit a node, queueing it for expansion if it's a container
+    fn enqueue(
+        &mut self,
+        queue: &mut VecDeque<(String, usize, &'v Value, bool)>,
+        depth: usize,
+        value: &'v Value,
+        sorted: bool,
+    ) -> Result<(), FlattenError> {
+        self.emit(depth, value)?;
+        if is_container(value) {
+            let sorted = sorted || self.sorts(value);
+            queue.push_back((self.pointer.clone(), depth, value, sorted));
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, depth: usize, value: &'v Value) -> Result<(), FlattenError> {
+        self.entries += 1;
+        let entry_bytes = match self.limits.max_total_output_bytes {
+            Some(_) => self.pointer.len() + estimated_len(value),
+            None => 0,
+        };
+        self.output_bytes += entry_bytes;
+
+        if let Some(error) = self.exceeded_limit(depth, entry_bytes) {
+            return Err(error);
+        }
+
+        (self.emit)(self.pointer, depth, self.index, value)
+    }
+
+    fn exceeded_limit(&self, depth: usize, entry_bytes: usize) -> Option<FlattenError> {
+        let limits = self.limits;
+        let pointer = || self.pointer.to_string();
+
+        if let Some(limit) = limits.max_depth.filter(|limit| depth > *limit) {
+            return Some(FlattenError::DepthLimitExceeded {
+                limit,
+                pointer: pointer(),
+            });
+        }
+        // deeper pointers are checked before they are built, see `Walk::push_key`
+        if let Some(limit) = limits
+            .max_pointer_len
+            .filter(|limit| depth == 0 && self.pointer.len() > *limit)
+        {
+            return Some(FlattenError::PointerLengthExceeded {
+                limit,
+                depth,
+                parent: String::new(),
+                len: self.pointer.len(),
+            });
+        }
+        if let Some(limit) = limits.max_entries.filter(|limit| self.entries > *limit) {
+            return Some(FlattenError::EntryLimitExceeded {
+                limit,
+                pointer: pointer(),
+            });
+        }
+        if let Some(limit) = limits
+            .max_total_output_bytes
+            .filter(|limit| self.output_bytes > *limit)
+        {
+            return Some(FlattenError::OutputSizeExceeded {
+                limit,
+                pointer: pointer(),
+                bytes: self.output_bytes - entry_bytes,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flatten_into_with_prefix() {
+        let mut target = Map::new();
+
+        let stats = flatten_into(&json!({ "a": [1] }), &mut target, "/x");
+
+        assert_eq!(stats.inserted, 3);
+        assert!(!stats.overwritten);
+        assert!(target.get("/x").unwrap().eq(&json!({})));
+        assert!(target.get("/x/a").unwrap().eq(&json!([])));
+        assert!(target.get("/x/a/0").unwrap().eq(&json!(1)));
+    }
+
+    #[test]
+    fn flatten_into_reports_overwrite() {
+        let mut target = Map::new();
+        target.insert("/a".to_owned(), json!(0));
+
+        let stats = flatten_into(&json!({ "a": 1 }), &mut target, "");
+
+        assert!(stats.overwritten);
+        assert!(target.get("/a").unwrap().eq(&json!(1)));
+    }
+
+    #[test]
+    fn flatten_into_errors_on_conflict() {
+        let flattener = Flattener::new().on_conflict(OnConflict::Error);
+        let mut target = Map::new();
+        target.insert("/doc/a".to_owned(), json!(0));
+
+        let result = flattener.flatten_into(&json!({ "a": 1 }), &mut target, "/doc");
+
+        assert_eq!(result, Err(FlattenError::KeyConflict("/doc/a".to_owned())));
+        assert!(target.get("/doc/a").unwrap().eq(&json!(0)));
+    }
+
+    #[test]
+    fn flattener_is_reusable() {
+        let flattener = Flattener::new();
+
+        let first = flattener.flatten(&json!({ "a": { "b": 1 } }));
+        let second = flattener.flatten(&json!([true]));
+
+        assert!(first.get("/a/b").unwrap().eq(&json!(1)));
+        assert!(second.get("/0").unwrap().eq(&json!(true)));
+        assert!(second.get("/a/b").is_none());
+    }
+
+    #[test]
+    fn scratch_is_reused_across_prefixes() {
+        let flattener = Flattener::new().limits(Limits::new().max_depth(1));
+        let mut scratch = FlattenerScratch::new(&flattener);
+        let mut target = Map::new();
+
+        scratch
+            .flatten_into(&json!({ "a": 1 }), &mut target, "/long/prefix")
+            .unwrap();
+        scratch
+            .flatten_into(&json!([2]), &mut target, "/p")
+            .unwrap();
+
+        assert_eq!(
+            scratch.flatten(&json!({ "b": 3 })),
+            flattener.flatten(&json!({ "b": 3 }))
+        );
+        assert!(scratch.try_flatten(&json!([[1]])).is_err());
+        assert_eq!(
+            Value::Object(target),
+            json!({ "/long/prefix": {}, "/long/prefix/a": 1, "/p": [], "/p/0": 2 })
+        );
+    }
+
+    #[test]
+    fn forbidden_escapable_keys_are_all_listed() {
+        let value = json!({ "a/b": { "c~d": 1 }, "ok": [{ "e/": 2 }] });
+        let flattener = Flattener::new().forbid_escapable_keys(true);
+        let mut target = Map::new();
+
+        let error = flattener
+            .flatten_into(&value, &mut target, "/doc")
+            .unwrap_err();
+
+        let keys = match error {
+            FlattenError::EscapableKeys(keys) => keys,
+            other => panic!("unexpected {:?}", other),
+        };
+        let parents: Vec<(&str, &str)> = keys
+            .iter()
+            .map(|key| (key.pointer_of_parent.as_str(), key.original_key.as_str()))
+            .collect();
+        assert_eq!(
+            parents,
+            vec![("/doc", "a/b"), ("/doc/a~1b", "c~d"), ("/doc/ok/0", "e/")]
+        );
+        assert!(target.is_empty());
+        assert!(flattener.try_flatten(&json!({ "plain": 1 })).is_ok());
+        assert!(Flattener::new().try_flatten(&value).is_ok());
+    }
+
+    #[test]
+    fn key_checks_stay_within_the_limits() {
+        let mut value = json!({ "a/b": 1 });
+        for _ in 0..50 {
+            value = json!({ "x": value });
+        }
+        let flattener = Flattener::new().forbid_escapable_keys(true);
+
+        let error = flattener
+            .clone()
+            .limits(Limits::new().max_depth(3))
+            .try_flatten(&value);
+
+        assert_eq!(
+            error,
+            Err(FlattenError::DepthLimitExceeded {
+                limit: 3,
+                pointer: "/x/x/x/x".to_owned()
+            })
+        );
+        assert!(matches!(
+            flattener.try_flatten(&value),
+            Err(FlattenError::EscapableKeys(_))
+        ));
+        let truncated = flattener
+            .limits(Limits::new().max_total_output_bytes(1))
+            .flatten_truncated(&value);
+        assert!(matches!(truncated, Err(FlattenError::EscapableKeys(_))));
+    }
+
+    #[test]
+    fn sorted_arrays_below_patterns() {
+        let sets = Flattener::new().sort_arrays(vec![PointerPattern::new("/sets").unwrap()]);
+        let value = json!({ "list": [2, 1], "sets": { "a": [3, null, [2, 1]] } });
+
+        assert_eq!(
+            sets.flatten(&value),
+            json!({
+                "": {},
+                "/list": [],
+                "/list/0": 2,
+                "/list/1": 1,
+                "/sets": {},
+                "/sets/a": [],
+                "/sets/a/0": null,
+                "/sets/a/1": 3,
+                "/sets/a/2": [],
+                "/sets/a/2/0": 1,
+                "/sets/a/2/1": 2
+            })
+        );
+        let breadth_first = sets.clone().traversal_order(TraversalOrder::BreadthFirst);
+        assert_eq!(breadth_first.flatten(&value), sets.flatten(&value));
+        let list = sets.sort_arrays(vec![PointerPattern::new("/list").unwrap()]);
+        assert_eq!(list.flatten(&value)["/list/0"], json!(1));
+        assert_eq!(list.flatten(&value)["/sets/a/0"], json!(3));
+    }
+
+    #[test]
+    fn sorting_everything_ignores_element_order() {
+        let all = Flattener::new().sort_arrays(vec![PointerPattern::new("").unwrap()]);
+        let a = json!([{ "id": 2, "tags": ["y", "x"] }, { "id": 1 }, "s"]);
+        let b = json!(["s", { "id": 1 }, { "id": 2, "tags": ["x", "y"] }]);
+
+        assert_eq!(all.flatten(&a), all.flatten(&b));
+        assert_eq!(all.flatten(&a)["/0"], json!("s"));
+        assert_ne!(Flattener::new().flatten(&a), Flattener::new().flatten(&b));
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn shareable_between_threads() {
+        assert_send_sync::<Flattener>();
+        assert_send_sync::<FlattenerScratch<'_>>();
+        assert_send_sync::<crate::FlattenedDocument>();
+    }
+
+    #[test]
+    fn limits_default_to_unlimited() {
+        let value = json!({ "a": { "b": { "c": [1, 2, 3] } } });
+
+        assert_eq!(
+            try_flatten(&value, &Limits::new()),
+            Ok(crate::from_json(&value))
+        );
+    }
+
+    #[test]
+    fn depth_limit_trips_before_descending() {
+        let value = json!({ "a": { "b": { "c": 1 } } });
+
+        let result = try_flatten(&value, &Limits::new().max_depth(2));
+
+        assert_eq!(
+            result,
+            Err(FlattenError::DepthLimitExceeded {
+                limit: 2,
+                pointer: "/a/b/c".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn pointer_length_limit() {
+        let value = json!({ "short": 1, "a_rather_long_key": 2 });
+
+        let result = try_flatten(&value, &Limits::new().max_pointer_len(8));
+
+        assert_eq!(
+            result,
+            Err(FlattenError::PointerLengthExceeded {
+                limit: 8,
+                depth: 1,
+                parent: String::new(),
+                len: 18
+            })
+        );
+        assert!(try_flatten(&json!([[0]]), &Limits::new().max_pointer_len(4)).is_ok());
+        assert!(matches!(
+            try_flatten(&json!({ "a~b": [0] }), &Limits::new().max_pointer_len(6)),
+            Err(FlattenError::PointerLengthExceeded { len: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn long_keys_fail_before_their_pointer_is_built() {
+        let key = "k".repeat(2048);
+        let mut value = json!(null);
+        for _ in 0..60 {
+            let mut obj = Map::new();
+            obj.insert(key.clone(), value);
+            value = Value::Object(obj);
+        }
+        let limits = Limits::new().max_pointer_len(16 * 1024);
+
+        let error = try_flatten(&value, &limits).unwrap_err();
+
+        // 60 segments of 2049 bytes would make pointers of up to 120 KB
+        match &error {
+            FlattenError::PointerLengthExceeded {
+                depth, parent, len, ..
+            } => {
+                assert_eq!((*depth, parent.len(), *len), (8, 7 * 2049, 8 * 2049));
+            }
+            other => panic!("expected a pointer length error, got {:?}", other),
+        }
+        assert!(error.to_string().len() < 200, "{}", error);
+        assert!(Flattener::new()
+            .traversal_order(TraversalOrder::BreadthFirst)
+            .limits(limits)
+            .try_flatten(&value)
+            .is_err());
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn pointer_length_counts_normalized_keys() {
+        use crate::KeyNormalization;
+
+        // U+FDFA is 3 bytes and expands to 33 under NFKC, "~" escapes to 2 bytes
+        let value = json!({ "\u{fdfa}": { "~": 1 } });
+        let limits = Limits::new().max_pointer_len(36);
+
+        assert!(try_flatten(&value, &limits).is_ok());
+        let flattener = Flattener::new().limits(limits);
+        assert!(matches!(
+            flattener
+                .clone()
+                .normalize_keys(KeyNormalization::Nfkc)
+                .try_flatten(&value),
+            Err(FlattenError::PointerLengthExceeded { len: 37, .. })
+        ));
+        assert!(flattener
+            .normalize_keys(KeyNormalization::Nfkc)
+            .limits(Limits::new().max_pointer_len(37))
+            .try_flatten(&value)
+            .is_ok());
+    }
+
+    #[test]
+    fn output_size_limit() {
+        let value = json!({ "a": "xxxx", "b": "yyyy" });
+
+        // "" {} = 2, "/a" "xxxx" = 8, "/b" "yyyy" = 8
+        assert!(try_flatten(&value, &Limits::new().max_total_output_bytes(18)).is_ok());
+        assert_eq!(
+            try_flatten(&value, &Limits::new().max_total_output_bytes(17)),
+            Err(FlattenError::OutputSizeExceeded {
+                limit: 17,
+                pointer: "/b".to_owned(),
+                bytes: 10
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_output_stops_at_budget() {
+        let value = json!({ "a": "xxxx", "b": "yyyy" });
+        let flattener = Flattener::new().limits(Limits::new().max_total_output_bytes(17));
+
+        assert_eq!(
+            flattener.flatten_truncated(&value),
+            Ok(TruncatedOutput {
+                value: json!({ "": {}, "/a": "xxxx" }),
+                bytes: 10,
+                cut_off: Some("/b".to_owned()),
+            })
+        );
+        // counted like the limit does, so the root key doesn't change the size
+        let rooted = flattener
+            .root_key("$root")
+            .flatten_truncated(&value)
+            .unwrap();
+        assert_eq!((rooted.bytes, rooted.cut_off), (10, Some("/b".to_owned())));
+        let unlimited = Flattener::new().flatten_truncated(&value).unwrap();
+        assert_eq!(unlimited.value, Flattener::new().flatten(&value));
+        assert_eq!(unlimited.cut_off, None);
+    }
+
+    #[test]
+    fn truncated_output_keeps_other_limits() {
+        let flattener =
+            Flattener::new().limits(Limits::new().max_depth(0).max_total_output_bytes(100));
+
+        assert!(matches!(
+            flattener.flatten_truncated(&json!([1])),
+            Err(FlattenError::DepthLimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn limits_apply_to_flatten_into() {
+        let flattener = Flattener::new().limits(Limits::new().max_entries(1));
+        let mut target = Map::new();
+
+        let result = flattener.flatten_into(&json!([1]), &mut target, "/x");
+
+        assert_eq!(
+            result,
+            Err(FlattenError::EntryLimitExceeded {
+                limit: 1,
+                pointer: "/x/0".to_owned()
+            })
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn arbitrary_precision_number_is_a_single_leaf() {
+        let digits = "1234567890123456789012345678901234567890";
+        let value: Value = serde_json::from_str(&format!("{{ \"n\": {} }}", digits)).unwrap();
+
+        let actual = crate::from_json(&value);
+
+        assert_eq!(actual.as_object().unwrap().len(), 2);
+        assert!(actual.get("/n").unwrap().is_number());
+        assert_eq!(actual.get("/n").unwrap().to_string(), digits);
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn private_number_object_is_not_descended() {
+        let digits = "1234567890123456789012345678901234567890";
+        let value = json!({ "n": { "$serde_json::private::Number": digits } });
+
+        let actual = crate::from_json(&value);
+
+        assert_eq!(actual.as_object().unwrap().len(), 2);
+        assert_eq!(actual.get("/n").unwrap().to_string(), digits);
+    }
+
+    #[test]
+    fn progress_is_reported_every_interval() {
+        let flattener = Flattener::new().progress_interval(2);
+        let mut seen = Vec::new();
+
+        let result = flattener.flatten_with_progress(&json!({ "a": { "b": [1] } }), |p| {
+            seen.push(p);
+            ControlFlow::Continue(())
+        });
+
+        assert!(result.unwrap().get("/a/b/0").is_some());
+        assert_eq!(
+            seen,
+            vec![
+                Progress {
+                    entries: 2,
+                    depth: 1
+                },
+                Progress {
+                    entries: 4,
+                    depth: 3
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn progress_counts_nodes_without_entries() {
+        let flattener = Flattener::new()
+            .progress_interval(2)
+            .container_value(ContainerValue::Skip);
+        let mut seen = Vec::new();
+
+        let result = flattener.flatten_with_progress(&json!([{}, {}, [], { "a": 1 }]), |p| {
+            seen.push(p.entries);
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(result, Ok(json!({ "/3/a": 1 })));
+        assert_eq!(seen, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn cancel_after_fixed_entry_count() {
+        let value = Value::Array((0..10_000).map(|i| json!(i)).collect());
+        let flattener = Flattener::new().progress_interval(100);
+        let mut calls = 0;
+
+        let result = flattener.flatten_with_progress(&value, |p| {
+            calls += 1;
+            if p.entries >= 500 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        assert_eq!(result, Err(FlattenError::Cancelled));
+        assert_eq!(calls, 5);
+    }
+
+    #[test]
+    fn breadth_first_produces_same_entries() {
+        let value = json!({ "a": [1, [2]], "b": { "c": true }, "d": null });
+        let flattener = Flattener::new().traversal_order(TraversalOrder::BreadthFirst);
+
+        assert_eq!(flattener.flatten(&value), crate::from_json(&value));
+    }
+
+    #[test]
+    fn limits_trip_in_traversal_order() {
+        let value = json!({ "a": [1, 2], "b": { "c": true }, "d": null });
+        let limits = Limits::new().max_entries(4);
+        let trip = |order| {
+            Flattener::new()
+                .limits(limits)
+                .traversal_order(order)
+                .try_flatten(&value)
+        };
+
+        assert_eq!(
+            trip(TraversalOrder::DepthFirst),
+            Err(FlattenError::EntryLimitExceeded {
+                limit: 4,
+                pointer: "/b".to_owned()
+            })
+        );
+        assert_eq!(
+            trip(TraversalOrder::BreadthFirst),
+            Err(FlattenError::EntryLimitExceeded {
+                limit: 4,
+                pointer: "/a/0".to_owned()
+            })
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn breadth_first_output_order() {
+        let value = json!({ "a": [1, 2], "b": { "c": true }, "d": null });
+
+        let flattened = Flattener::new()
+            .traversal_order(TraversalOrder::BreadthFirst)
+            .flatten(&value);
+
+        assert_eq!(
+            flattened.as_object().unwrap().keys().collect::<Vec<_>>(),
+            vec!["", "/a", "/b", "/d", "/a/0", "/a/1", "/b/c"]
+        );
+    }
+
+    #[test]
+    fn container_values() {
+        let value = json!({ "phones": ["1", "2"], "address": { "zip": "0" }, "tags": [] });
+        let flatten = |containers| Flattener::new().container_value(containers).flatten(&value);
+
+        assert_eq!(flatten(ContainerValue::Empty), crate::from_json(&value));
+        assert_eq!(
+            flatten(ContainerValue::Count),
+            json!({
+                "": 3,
+                "/address": 1,
+                "/address/zip": "0",
+                "/phones": 2,
+                "/phones/0": "1",
+                "/phones/1": "2",
+                "/tags": 0
+            })
+        );
+        assert_eq!(
+            flatten(ContainerValue::Skip),
+            json!({ "/address/zip": "0", "/phones/0": "1", "/phones/1": "2" })
+        );
+    }
+
+    #[test]
+    fn only_empty_containers_round_trip() {
+        let value = json!({ "phones": ["1", "2"], "tags": [] });
+        let unflatten = |containers| {
+            crate::unflatten(&Flattener::new().container_value(containers).flatten(&value))
+        };
+
+        assert_eq!(unflatten(ContainerValue::Empty), Ok(value.clone()));
+        assert_eq!(
+            unflatten(ContainerValue::Count),
+            Err(crate::UnflattenError::NotAContainer {
+                pointer: "/phones".to_owned()
+            })
+        );
+        assert_eq!(
+            unflatten(ContainerValue::Skip),
+            Ok(json!({ "phones": ["1", "2"] }))
+        );
+    }
+
+    #[test]
+    fn skipped_containers_are_not_inserted() {
+        let mut target = Map::new();
+
+        let stats = Flattener::new()
+            .container_value(ContainerValue::Skip)
+            .flatten_into(&json!([[1], {}]), &mut target, "")
+            .unwrap();
+
+        assert_eq!(stats.inserted, 1);
+        assert_eq!(Value::Object(target), json!({ "/0/0": 1 }));
+    }
+
+    #[test]
+    fn normalized_numbers_in_every_format() {
+        let value = json!({ "a": [2.0, -0.0, 0.25], "b": "2.0" });
+        let flattener = Flattener::new().normalize_numbers(true);
+
+        assert_eq!(
+            flattener.flatten(&value),
+            json!({ "": {}, "/a": [], "/a/0": 2, "/a/1": 0, "/a/2": 0.25, "/b": "2.0" })
+        );
+        assert_eq!(
+            flattener.entry_format(EntryFormat::Typed).flatten(&value)["/a/0"],
+            json!({ "type": "number", "value": 2 })
+        );
+    }
+}
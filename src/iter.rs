@@ -0,0 +1,381 @@
+use crate::flattener::is_container;
+use crate::pointer::escape_into;
+use crate::TraversalOrder;
+use serde_json::{map, Value};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ops::BitOr;
+use std::slice;
+
+/// Lazy iterator over the entries of a nested document, see [`iter`]
+pub struct Iter<'v> {
+    root: Option<&'v Value>,
+    pointer: String,
+    stack: Vec<Frame<'v>>,
+    order: TraversalOrder,
+    /// Containers waiting to be expanded when breadth-first, with their pointers
+    queue: VecDeque<(String, &'v Value)>,
+    /// Whether the last yielded entry was a container pushed on the stack or queue
+    entered: bool,
+}
+
+struct Frame<'v> {
+    children: Children<'v>,
+    /// Length of the container's own pointer
+    len: usize,
+}
+
+enum Children<'v> {
+    Array(std::iter::Enumerate<slice::Iter<'v, Value>>),
+    Object(map::Iter<'v>),
+}
+
+impl<'v> Frame<'v> {
+    fn new(container: &'v Value, len: usize) -> Self {
+        let children = match container {
+            Value::Array(arr) => Children::Array(arr.iter().enumerate()),
+            Value::Object(obj) => Children::Object(obj.iter()),
+            _ => unreachable!("only arrays and objects are containers"),
+        };
+        Frame { children, len }
+    }
+}
+
+/// Lazily iterate over `(pointer, value)` pairs in the same order as [`from_json`](crate::from_json)
+///
+/// Nothing is copied: containers are yielded as references to themselves
+/// rather than as empty placeholders, followed by their descendants.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "a": [1] });
+/// let pointers: Vec<String> = jsonpointer_flatten::iter(&value).map(|(p, _)| p).collect();
+///
+/// assert_eq!(pointers, vec!["", "/a", "/a/0"]);
+/// ```
+pub fn iter(value: &Value) -> Iter<'_> {
+    iter_ordered(value, TraversalOrder::DepthFirst)
+}
+
+/// Like [`iter`], visiting nodes in the given order
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::TraversalOrder;
+/// use serde_json::json;
+///
+/// let value = json!({ "a": { "b": 1 }, "c": 2 });
+/// let pointers: Vec<String> = jsonpointer_flatten::iter_ordered(&value, TraversalOrder::BreadthFirst)
+///     .map(|(p, _)| p)
+///     .collect();
+///
+/// assert_eq!(pointers, vec!["", "/a", "/c", "/a/b"]);
+/// ```
+pub fn iter_ordered(value: &Value, order: TraversalOrder) -> Iter<'_> {
+    Iter {
+        root: Some(value),
+        pointer: String::new(),
+        stack: Vec::new(),
+        order,
+        queue: VecDeque::new(),
+        entered: false,
+    }
+}
+
+impl<'v> Iter<'v> {
+    /// Don't descend into the container that was just yielded
+    ///
+    /// Does nothing if the last entry was a leaf.
+    ///
+    /// ```
+    /// use serde_json::json;
+    ///
+    /// let value = json!({ "a": { "b": 1 }, "c": 2 });
+    /// let mut iter = jsonpointer_flatten::iter(&value);
+    /// let mut pointers = Vec::new();
+    ///
+    /// while let Some((pointer, _)) = iter.next() {
+    ///     if pointer == "/a" {
+    ///         iter.skip_children();
+    ///     }
+    ///     pointers.push(pointer);
+    /// }
+    ///
+    /// assert_eq!(pointers, vec!["", "/a", "/c"]);
+    /// ```
+    pub fn skip_children(&mut self) {
+        if self.entered {
+            self.entered = false;
+            match self.order {
+                TraversalOrder::DepthFirst => {
+                    self.stack.pop();
+                }
+                TraversalOrder::BreadthFirst => {
+                    self.queue.pop_back();
+                }
+            }
+        }
+    }
+
+    /// Pointer of the node last returned by [`Iter::next_node`]
+    pub(crate) fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    /// Advance like [`Iterator::next`], without copying the pointer
+    pub(crate) fn next_node(&mut self) -> Option<&'v Value> {
+        if let Some(root) = self.root.take() {
+            return Some(self.visit(root));
+        }
+
+        loop {
+            self.entered = false;
+            if self.stack.is_empty() {
+                // only breadth-first traversal queues containers
+                let (pointer, container) = self.queue.pop_front()?;
+                self.pointer = pointer;
+                self.stack.push(Frame::new(container, self.pointer.len()));
+            }
+            let frame = self.stack.last_mut()?;
+            self.pointer.truncate(frame.len);
+
+            let child = match &mut frame.children {
+                Children::Array(children) => children.next().map(|(idx, child)| {
+                    let _ = write!(self.pointer, "/{}", idx);
+                    child
+                }),
+                Children::Object(children) => children.next().map(|(key, child)| {
+                    self.pointer.push('/');
+                    escape_into(key, &mut self.pointer);
+                    child
+                }),
+            };
+
+            match child {
+                Some(child) => return Some(self.visit(child)),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+
+    fn visit(&mut self, node: &'v Value) -> &'v Value {
+        self.entered = is_container(node);
+        if self.entered {
+            match self.order {
+                TraversalOrder::DepthFirst => {
+                    self.stack.push(Frame::new(node, self.pointer.len()));
+                }
+                TraversalOrder::BreadthFirst => {
+                    self.queue.push_back((self.pointer.clone(), node));
+                }
+            }
+        }
+        node
+    }
+}
+
+impl<'v> Iterator for Iter<'v> {
+    type Item = (String, &'v Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next_node()?;
+        Some((self.pointer.clone(), node))
+    }
+}
+
+/// A set of leaf types, combined with `|`
+///
+/// ```
+/// use jsonpointer_flatten::JsonType;
+/// use serde_json::json;
+///
+/// let types = JsonType::STRING | JsonType::NUMBER;
+///
+/// assert!(types.contains(&json!(1)));
+/// assert!(!types.contains(&json!(null)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JsonType(u8);
+
+impl JsonType {
+    /// `null`
+    pub const NULL: JsonType = JsonType(1);
+    /// `true` and `false`
+    pub const BOOL: JsonType = JsonType(1 << 1);
+    /// Any number
+    pub const NUMBER: JsonType = JsonType(1 << 2);
+    /// Any string
+    pub const STRING: JsonType = JsonType(1 << 3);
+    /// Every leaf type
+    pub const LEAF: JsonType = JsonType(0b1111);
+
+    /// Whether `value` is a leaf of one of these types
+    ///
+    /// Containers are never contained.
+    pub fn contains(self, value: &Value) -> bool {
+        let ty = match value {
+            Value::Null => JsonType::NULL,
+            Value::Bool(_) => JsonType::BOOL,
+            Value::String(_) => JsonType::STRING,
+            _ if !is_container(value) => JsonType::NUMBER,
+            _ => return false,
+        };
+        self.0 & ty.0 != 0
+    }
+}
+
+impl BitOr for JsonType {
+    type Output = JsonType;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        JsonType(self.0 | rhs.0)
+    }
+}
+
+/// Lazily iterate over the leaves of `value` whose type is in `ty`
+///
+/// Container entries are always skipped.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::JsonType;
+/// use serde_json::json;
+///
+/// let value = json!({ "name": "John", "age": 24, "tags": ["a"], "spouse": null });
+/// let pointers: Vec<String> = jsonpointer_flatten::leaves_of_type(&value, JsonType::STRING)
+///     .map(|(p, _)| p)
+///     .collect();
+///
+/// assert_eq!(pointers, vec!["/name", "/tags/0"]);
+/// ```
+pub fn leaves_of_type(value: &Value, ty: JsonType) -> impl Iterator<Item = (String, &Value)> {
+    iter(value).filter(move |(_, node)| ty.contains(node))
+}
+
+/// Lazily iterate over the string leaves of `value`
+pub fn string_leaves(value: &Value) -> impl Iterator<Item = (String, &str)> {
+    iter(value).filter_map(|(pointer, node)| node.as_str().map(|s| (pointer, s)))
+}
+
+/// Lazily iterate over the number leaves of `value`, converted to `f64`
+pub fn number_leaves(value: &Value) -> impl Iterator<Item = (String, f64)> + '_ {
+    leaves_of_type(value, JsonType::NUMBER)
+        .filter_map(|(pointer, node)| node.as_f64().map(|n| (pointer, n)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn iter_matches_from_json() {
+        let value = json!({
+            "a/b": [1, { "c": null }],
+            "m~n": {},
+            "": [[]],
+            "z": "end"
+        });
+
+        let collected: serde_json::Map<String, Value> = iter(&value)
+            .map(|(p, v)| (p, crate::flattener::entry_value(v)))
+            .collect();
+
+        assert_eq!(Value::Object(collected), crate::from_json(&value));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn iter_follows_input_order() {
+        let value: Value = serde_json::from_str(r#"{ "b": 1, "a": { "d": 2, "c": 3 } }"#).unwrap();
+
+        let pointers: Vec<String> = iter(&value).map(|(p, _)| p).collect();
+        let flattened = crate::from_json(&value);
+        let keys: Vec<&str> = flattened
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+
+        assert_eq!(pointers, vec!["", "/b", "/a", "/a/d", "/a/c"]);
+        assert_eq!(keys, pointers);
+    }
+
+    #[cfg(not(feature = "preserve_order"))]
+    #[test]
+    fn iter_follows_sorted_order() {
+        let value: Value = serde_json::from_str(r#"{ "b": 1, "a": { "d": 2, "c": 3 } }"#).unwrap();
+
+        let pointers: Vec<String> = iter(&value).map(|(p, _)| p).collect();
+
+        assert_eq!(pointers, vec!["", "/a", "/a/c", "/a/d", "/b"]);
+    }
+
+    #[test]
+    fn iter_in_both_orders() {
+        let value = json!({ "a": [1, 2], "b": { "c": true }, "d": null });
+
+        let dfs: Vec<String> = iter_ordered(&value, TraversalOrder::DepthFirst)
+            .map(|(p, _)| p)
+            .collect();
+        let bfs: Vec<String> = iter_ordered(&value, TraversalOrder::BreadthFirst)
+            .map(|(p, _)| p)
+            .collect();
+
+        assert_eq!(dfs, vec!["", "/a", "/a/0", "/a/1", "/b", "/b/c", "/d"]);
+        assert_eq!(bfs, vec!["", "/a", "/b", "/d", "/a/0", "/a/1", "/b/c"]);
+    }
+
+    #[test]
+    fn skip_children_breadth_first() {
+        let value = json!({ "a": [[1]], "b": { "c": [2] } });
+        let mut iter = iter_ordered(&value, TraversalOrder::BreadthFirst);
+        let mut pointers = Vec::new();
+
+        while let Some((pointer, _)) = iter.next() {
+            if pointer == "/a" || pointer == "/b/c" {
+                iter.skip_children();
+            }
+            pointers.push(pointer);
+        }
+
+        assert_eq!(pointers, vec!["", "/a", "/b", "/b/c"]);
+    }
+
+    #[test]
+    fn iter_over_scalar() {
+        let value = json!(42);
+
+        assert_eq!(
+            iter(&value).collect::<Vec<_>>(),
+            vec![(String::new(), &value)]
+        );
+    }
+
+    #[test]
+    fn typed_leaves() {
+        let value = json!({ "a": 1, "b": [2.5, "x"], "c": true, "d": {} });
+
+        let numbers: Vec<_> = number_leaves(&value).collect();
+        let strings: Vec<_> = string_leaves(&value).collect();
+        let mixed: Vec<String> = leaves_of_type(&value, JsonType::BOOL | JsonType::STRING)
+            .map(|(p, _)| p)
+            .collect();
+
+        assert_eq!(
+            numbers,
+            vec![("/a".to_owned(), 1.0), ("/b/0".to_owned(), 2.5)]
+        );
+        assert_eq!(strings, vec![("/b/1".to_owned(), "x")]);
+        assert_eq!(mixed, vec!["/b/1", "/c"]);
+        assert_eq!(leaves_of_type(&value, JsonType::LEAF).count(), 4);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::error::CanonError;
+#[cfg(feature = "arbitrary_precision")]
+use crate::flattener::private_number;
+use crate::from_json;
+use serde_json::{Number, Value};
+
+/// Serialize `value` as [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) canonical JSON
+///
+/// The JSON Canonicalization Scheme gives equal values the same bytes, for
+/// hashing or signing: no whitespace, object members sorted by the UTF-16
+/// code units of their keys, strings with only the escapes JSON requires,
+/// and numbers formatted like ECMAScript's `Number.prototype.toString`.
+/// Numbers are IEEE 754 doubles in JCS, so integers beyond 2^53 are rounded
+/// to the nearest one, and a number too large for a double, which only the
+/// `arbitrary_precision` feature lets into a [`Value`], fails with
+/// [`CanonError::InvalidNumber`]. Strings are not Unicode normalized. Enable
+/// serde_json's `float_roundtrip` feature for input numbers to be parsed into
+/// the nearest double, as canonical output from other implementations assumes.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "b": [1.50, 1e21, "\u{20ac}"], "a": { "\u{e9}": null, "z": true } });
+///
+/// assert_eq!(
+///     jsonpointer_flatten::to_canonical_string(&value).unwrap(),
+///     r#"{"a":{"z":true,"é":null},"b":[1.5,1e+21,"€"]}"#
+/// );
+/// ```
+pub fn to_canonical_string(value: &Value) -> Result<String, CanonError> {
+    let mut out = String::new();
+    write_canonical(value, &mut out)?;
+    Ok(out)
+}
+
+/// Flatten `value` and serialize the flattened object with [`to_canonical_string`]
+///
+/// Entries are sorted by their pointer keys, so documents that differ in key
+/// order only, or in the order serde_json's `preserve_order` feature keeps,
+/// give the same string.
+///
+/// ```
+/// use serde_json::json;
+///
+/// assert_eq!(
+///     jsonpointer_flatten::canonical_flat_string(&json!({ "a": [1.0] })).unwrap(),
+///     r#"{"":{},"/a":[],"/a/0":1}"#
+/// );
+/// ```
+pub fn canonical_flat_string(value: &Value) -> Result<String, CanonError> {
+    to_canonical_string(&from_json(value))
+}
+
+fn write_canonical(value: &Value, out: &mut String) -> Result<(), CanonError> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&number(n)?),
+        // serde_json escapes exactly what JCS does: quotes, backslashes and
+        // control characters, with short escapes where JSON has them
+        Value::String(s) => out.push_str(&Value::from(s.as_str()).to_string()),
+        Value::Array(arr) => {
+            out.push('[');
+            for (idx, element) in arr.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_canonical(element, out)?;
+            }
+            out.push(']');
+        }
+        #[cfg(feature = "arbitrary_precision")]
+        Value::Object(obj) if private_number(obj).is_some() => {
+            if let Some(Value::Number(n)) = private_number(obj) {
+                out.push_str(&number(&n)?);
+            }
+        }
+        Value::Object(obj) => {
+            let mut members: Vec<_> = obj.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (idx, (key, member)) in members.into_iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_canonical(&Value::from(key.as_str()), out)?;
+                out.push(':');
+                write_canonical(member, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn number(n: &Number) -> Result<String, CanonError> {
+    match n.as_f64() {
+        Some(x) if x.is_finite() => Ok(es6_number(x)),
+        _ => Err(CanonError::InvalidNumber(n.to_string())),
+    }
+}
+
+/// Format a finite double like ECMAScript's `Number.prototype.toString`
+fn es6_number(x: f64) -> String {
+    if x == 0.0 {
+        // negative zero too
+        return "0".to_owned();
+    }
+
+    // Rust's shortest round-trip digits are the ones ECMAScript picks, except
+    // that a tie between two of them goes up, where exact formatting at the
+    // same precision breaks it towards the even digit, like ECMAScript
+    let shortest = format!("{:e}", x.abs());
+    let precision = shortest.find('e').unwrap_or(0).saturating_sub(2);
+    let exact = format!("{:.*e}", precision, x.abs());
+    let scientific = if exact.parse::<f64>() == Ok(x.abs()) {
+        exact
+    } else {
+        shortest
+    };
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap_or(0));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // x = 0.digits × 10^n
+    let n = exponent[1..].parse::<i32>().unwrap_or(0) + 1;
+
+    let mut out = String::new();
+    if x < 0.0 {
+        out.push('-');
+    }
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n > 0 { '+' } else { '-' });
+        out.push_str(&(n - 1).abs().to_string());
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    macro_rules! vector {
+        ($name:literal) => {
+            (
+                $name,
+                include_str!(concat!("../testdata/jcs/input/", $name, ".json")),
+                include_str!(concat!("../testdata/jcs/output/", $name, ".json")),
+            )
+        };
+    }
+
+    #[test]
+    fn reference_test_vectors() {
+        for (name, input, output) in [
+            vector!("arrays"),
+            vector!("french"),
+            vector!("structures"),
+            vector!("unicode"),
+            vector!("values"),
+            vector!("weird"),
+        ] {
+            let value: Value = serde_json::from_str(input).unwrap();
+
+            assert_eq!(
+                to_canonical_string(&value).unwrap(),
+                output.trim_end_matches('\n'),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn rfc_number_examples() {
+        // Appendix B of RFC 8785, as the bits of the double and its canonical text
+        for (bits, expected) in [
+            (0x0000000000000000u64, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ] {
+            assert_eq!(es6_number(f64::from_bits(bits)), expected, "{:#x}", bits);
+        }
+    }
+
+    #[test]
+    fn formatted_numbers_parse_back() {
+        let mut bits = 0x2545f4914f6cdd1du64;
+        for _ in 0..100_000 {
+            // xorshift over all bit patterns, skipping NaN and infinities
+            bits ^= bits << 13;
+            bits ^= bits >> 7;
+            bits ^= bits << 17;
+            let x = f64::from_bits(bits);
+            if x.is_finite() {
+                assert_eq!(es6_number(x).parse::<f64>(), Ok(x), "{:#x}", bits);
+            }
+        }
+    }
+
+    #[test]
+    fn integers_are_doubles() {
+        assert_eq!(
+            to_canonical_string(&json!([u64::MAX, i64::MIN, 100, -0.0])).unwrap(),
+            "[18446744073709552000,-9223372036854776000,100,0]"
+        );
+    }
+
+    #[test]
+    fn flat_strings_ignore_key_order() {
+        let a: Value = serde_json::from_str(r#"{ "b": 1, "a": { "y": [], "x": 2 } }"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{ "a": { "x": 2, "y": [] }, "b": 1 }"#).unwrap();
+
+        assert_eq!(
+            canonical_flat_string(&a).unwrap(),
+            r#"{"":{},"/a":{},"/a/x":2,"/a/y":[],"/b":1}"#
+        );
+        assert_eq!(canonical_flat_string(&a), canonical_flat_string(&b));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn numbers_beyond_doubles_fail() {
+        let value: Value =
+            serde_json::from_str(r#"{ "big": 1e400, "exact": 0.10000000000000000001 }"#).unwrap();
+
+        assert_eq!(
+            to_canonical_string(&value),
+            Err(CanonError::InvalidNumber("1e400".to_owned()))
+        );
+        assert_eq!(to_canonical_string(&value["exact"]).unwrap(), "0.1");
+    }
+}
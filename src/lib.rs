@@ -1,9 +1,130 @@
 //! Rust library to flatten a JSON object using JSON Pointer field addressing as defined in [IETF RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901).
+//!
+//! # Features
+//!
+//! - `arbitrary_precision`: enables the serde_json feature of the same name. Numbers
+//!   of any size are kept exactly and flattened as single number leaves. It can be
+//!   combined with any other feature of this crate.
+//! - `preserve_order`: enables the serde_json feature of the same name. Object keys
+//!   are then traversed in input order, so the entries of flattened maps and of
+//!   [`iter`] follow the original document, and [`unflatten`] rebuilds objects in
+//!   the order their pointers first appear. Without it, keys are visited sorted.
+//! - `raw_value`: enables the serde_json feature of the same name and adds
+//!   [`flatten_raw`], which pairs pointers with slices of the input text.
+//! - `json-patch`: converts [`FlatDiff`] and [`GeneratedPatch`] into the `Patch`
+//!   type of the json-patch crate, and adds [`FlattenedDocument::apply_json_patch`]
+//!   to apply such patches to flattened documents.
+//! - `jsonptr`: implements [`pointer::AsPointer`] for the pointer types of the
+//!   jsonptr crate and adds [`pointer::to_jsonptr`] to convert the other way.
+//! - `proptest`: adds the [`strategies`] module generating documents, pointers and
+//!   flattened documents for property tests.
+//! - `rayon`: adds [`flatten_batch`], which flattens many documents in parallel.
+//! - `regex`: adds [`SearchQuery::regex`] for regular expression [`search`].
+//! - `unicode-normalization`: adds [`Flattener::normalize_keys`], which merges
+//!   keys that only differ in their Unicode normalization form.
 use serde::Serialize;
-use serde_json::{json, Map, Result, Value};
-
-type PointerMap = Vec<String>;
-type ValueMap = Map<String, Value>;
+use serde_json::{Result, Value};
+
+mod assert;
+#[cfg(feature = "rayon")]
+mod batch;
+mod canonical;
+mod dedup;
+mod delta;
+mod diff;
+mod diff_stream;
+mod display;
+mod document;
+mod duplicates;
+mod entry;
+mod error;
+mod escapes;
+pub mod flat;
+mod flattener;
+mod iter;
+mod jcs;
+mod limits;
+mod merge;
+mod merge_patch;
+pub mod nested;
+#[cfg(feature = "unicode-normalization")]
+mod normalize;
+mod numbers;
+mod patch;
+mod patch_gen;
+#[cfg(feature = "json-patch")]
+mod patch_interop;
+mod pattern;
+pub mod pointer;
+mod preview;
+#[cfg(feature = "raw_value")]
+mod raw;
+mod reindex;
+mod search;
+mod select;
+mod size;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+mod stream;
+mod strict;
+mod table;
+mod template;
+#[cfg(test)]
+mod testing;
+mod unflatten;
+mod walk;
+
+pub use assert::{check_flat_eq, FlatEqOptions};
+#[cfg(feature = "rayon")]
+pub use batch::flatten_batch;
+pub use dedup::{expand_references, flatten_deduplicated, DedupOptions, Deduplicated};
+pub use delta::{from_delta_encoded, to_delta_encoded};
+pub use diff::{diff, diff_with, DiffEntry, DiffOptions, DiffTextOptions, FlatDiff, PrefixSummary};
+pub use diff_stream::{diff_streaming, diff_streaming_with, DiffStream};
+pub use display::TableDisplay;
+pub use document::{Entries, FlattenedDocument, IntoEntries, StrictDocument};
+pub use duplicates::{find_duplicates, DuplicateGroup, DuplicateOptions};
+pub use entry::{flatten_entries, Entry, Segment};
+pub use error::{
+    AccessError, CanonError, ConversionError, DeltaError, FlattenError, MergeError,
+    MergePatchError, PatchError, PatternError, PointerError, ReindexError, StreamError,
+    StrictParseError, TableError, TemplateError, UnflattenError,
+};
+pub use escapes::{escaped_keys_report, has_keys_requiring_escape, EscapedKey};
+pub use flattener::{
+    flatten_into, try_flatten, ContainerValue, EntryFormat, Flattener, FlattenerScratch,
+    InsertStats, OnConflict, Progress, TraversalOrder, TruncatedOutput,
+};
+pub use iter::{iter, iter_ordered, leaves_of_type, number_leaves, string_leaves, Iter, JsonType};
+pub use jcs::{canonical_flat_string, to_canonical_string};
+pub use limits::Limits;
+pub use merge::{
+    merge3, merge3_with_resolver, merge_with_resolver, Conflict, ConflictKind, Merge3Options,
+    Merge3Result, MergeStrategy, Resolution, ResolverFailure,
+};
+pub use merge_patch::{apply_merge_patch_flat, diff_to_merge_patch};
+#[cfg(feature = "unicode-normalization")]
+pub use normalize::{KeyCollision, KeyNormalization};
+pub use numbers::{normalize_number, normalize_numbers};
+pub use patch::UpdateReport;
+pub use patch_gen::{json_patch_with, GeneratedPatch, PatchOptions};
+pub use pattern::PointerPattern;
+pub use preview::{flatten_preview, Omission, OmissionKind, Preview, PreviewOptions};
+#[cfg(feature = "raw_value")]
+pub use raw::flatten_raw;
+pub use reindex::ReindexReport;
+pub use search::{
+    any, find_first, find_n, find_value, find_value_by, find_value_with, search, FindOptions,
+    SearchQuery,
+};
+pub use select::flatten_selected;
+pub use size::{largest_subtrees, size_map};
+pub use stream::{flatten_stream, FlattenStream};
+pub use strict::from_str_strict;
+pub use table::{to_table, to_table_with, NestedArrays, Table, TableOptions};
+pub use template::{Captures, PointerTemplate};
+pub use unflatten::{unflatten, unflatten_with, UnflattenOptions};
+pub use walk::{visit_mut, visit_nodes_mut, walk, ContainerKind, FlattenVisitor};
 
 /// Flatten a JSON string
 ///
@@ -30,6 +151,10 @@ pub fn from_str(s: &str) -> Result<Value> {
 
 /// Flatten a JSON value
 ///
+/// Entries are produced in pre-order, each container before its children.
+/// Object keys are visited in the value's own order, which is sorted unless
+/// serde_json's `preserve_order` feature is enabled.
+///
 /// # Example
 ///
 /// ```
@@ -50,12 +175,7 @@ pub fn from_str(s: &str) -> Result<Value> {
 /// let result = jsonpointer_flatten::from_json(&value);
 /// ```
 pub fn from_json(value: &Value) -> Value {
-    let mut route = PointerMap::new();
-    let mut target = ValueMap::new();
-
-    process(&value, &mut route, &mut target);
-
-    Value::Object(target)
+    Flattener::new().flatten(value)
 }
 
 /// Flatten a struct value
@@ -85,50 +205,10 @@ where
     from_str(&serde_json::to_string(value)?)
 }
 
-fn process(value: &Value, route: &mut PointerMap, target: &mut ValueMap) {
-    match value {
-        Value::Null => {
-            target.insert(route.concat(), Value::Null);
-        }
-        Value::Bool(b) => {
-            target.insert(route.concat(), Value::Bool(b.clone()));
-        }
-        Value::Number(n) => {
-            target.insert(route.concat(), Value::Number(n.clone()));
-        }
-        Value::String(s) => {
-            target.insert(route.concat(), Value::String(s.clone()));
-        }
-        Value::Array(arr) => {
-            target.insert(route.concat(), json!([]));
-            arr.iter().enumerate().for_each(|(idx, val)| {
-                route.push(format!("/{}", idx));
-                process(val, route, target);
-            });
-        }
-        Value::Object(obj) => {
-            target.insert(route.concat(), json!({}));
-            for (key, val) in obj {
-                route.push(format!("/{}", escape(key.as_str())));
-                process(val, route, target);
-            }
-        }
-    }
-    route.pop();
-}
-
-fn escape<'a>(value: &'a str) -> String {
-    value.replace("~", "~0").replace("/", "~1")
-}
-
-#[allow(dead_code)]
-fn unescape<'a>(value: &'a str) -> String {
-    value.replace("~1", "/").replace("~0", "~")
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_serialize() {
@@ -238,6 +318,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     fn flatten_from_str_throws_invalid_json() {
         let value = "not json";
 
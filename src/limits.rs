@@ -0,0 +1,126 @@
+use serde_json::Value;
+use std::fmt;
+
+/// Resource limits for flattening untrusted input
+///
+/// Every limit is optional and unset by default, which preserves the unlimited
+/// behavior of [`from_json`](crate::from_json). Limits are enforced by
+/// [`try_flatten`](crate::try_flatten) and the fallible [`Flattener`](crate::Flattener)
+/// methods; each violation is reported as its own [`FlattenError`](crate::FlattenError)
+/// variant carrying the limit and the pointer where it tripped.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{FlattenError, Limits};
+/// use serde_json::json;
+///
+/// let limits = Limits::new().max_depth(1);
+///
+/// let result = jsonpointer_flatten::try_flatten(&json!({ "a": { "b": 1 } }), &limits);
+///
+/// assert_eq!(
+///     result,
+///     Err(FlattenError::DepthLimitExceeded { limit: 1, pointer: "/a/b".to_owned() })
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of segments in any emitted pointer
+    pub max_depth: Option<usize>,
+    /// Maximum number of emitted entries, containers included
+    pub max_entries: Option<usize>,
+    /// Maximum length in bytes of any emitted pointer
+    pub max_pointer_len: Option<usize>,
+    /// Maximum accumulated size in bytes of all keys plus their values, see
+    /// [`Flattener::flatten_truncated`](crate::Flattener::flatten_truncated)
+    pub max_total_output_bytes: Option<usize>,
+}
+
+impl Limits {
+    /// Create a set of limits with nothing limited
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the number of segments in any emitted pointer
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Limit the number of emitted entries, containers included
+    pub fn max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = Some(entries);
+        self
+    }
+
+    /// Limit the length in bytes of any emitted pointer
+    ///
+    /// Each segment is measured before it is appended, so a long key fails
+    /// without being copied, and the error names its parent pointer instead.
+    pub fn max_pointer_len(mut self, bytes: usize) -> Self {
+        self.max_pointer_len = Some(bytes);
+        self
+    }
+
+    /// Limit the accumulated size of the output
+    ///
+    /// Each entry counts as the length of its key plus the length of its value
+    /// serialized as compact JSON, ignoring any escaping inside strings.
+    /// Container placeholders count as two bytes. The estimate only looks at
+    /// value types and string lengths, so it is cheap but may undercount
+    /// strings that need escaping. Use [`Flattener::flatten_truncated`](crate::Flattener::flatten_truncated)
+    /// to cut the output off at the budget instead of failing.
+    pub fn max_total_output_bytes(mut self, bytes: usize) -> Self {
+        self.max_total_output_bytes = Some(bytes);
+        self
+    }
+}
+
+/// Approximate compact JSON length of a flattened entry value
+pub(crate) fn estimated_len(value: &Value) -> usize {
+    match value {
+        Value::Null => 4,
+        Value::Bool(true) => 4,
+        Value::Bool(false) => 5,
+        Value::Number(n) => {
+            let mut counter = Counter(0);
+            let _ = fmt::Write::write_fmt(&mut counter, format_args!("{}", n));
+            counter.0
+        }
+        Value::String(s) => s.len() + 2,
+        Value::Array(_) | Value::Object(_) => 2,
+    }
+}
+
+struct Counter(usize);
+
+impl fmt::Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn estimate_matches_compact_serialization() {
+        for value in [
+            json!(null),
+            json!(true),
+            json!(false),
+            json!(-12.5),
+            json!("abc"),
+        ] {
+            assert_eq!(
+                estimated_len(&value),
+                serde_json::to_string(&value).unwrap().len()
+            );
+        }
+    }
+}
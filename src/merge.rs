@@ -0,0 +1,743 @@
+use crate::error::{MergeError, UnflattenError};
+use crate::flattener::is_container;
+use crate::{flatten_into, pointer, FlattenedDocument, Flattener};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// Which side wins when both sides changed the same pointer differently
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep our version
+    #[default]
+    Ours,
+    /// Keep their version
+    Theirs,
+    /// Keep the base version, discarding both changes
+    Base,
+}
+
+/// Options for [`merge3`], [`merge3_with_resolver`] and [`merge_with_resolver`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Merge3Options {
+    strategy: MergeStrategy,
+    fail_fast: bool,
+}
+
+impl Merge3Options {
+    /// Create options that resolve conflicts in favour of our version
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how conflicts are resolved in the merged document
+    ///
+    /// Conflicts are reported regardless of the strategy.
+    pub fn strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Stop at the first [`Resolution::Fail`] of a resolver
+    ///
+    /// By default every conflict is still passed to the resolver and all
+    /// failures are returned together.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+}
+
+/// How a resolver passed to [`merge3_with_resolver`] or [`merge_with_resolver`]
+/// settles a conflict
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// Keep our version, removing the pointer if we deleted it
+    TakeLeft,
+    /// Keep their version, removing the pointer if they deleted it
+    TakeRight,
+    /// Use this value instead. Containers are flattened in place, and the
+    /// entries both sides have below the pointer are still merged on their own.
+    Use(Value),
+    /// Fail the merge with this message
+    Fail(String),
+}
+
+/// A conflict a resolver answered with [`Resolution::Fail`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolverFailure {
+    pub pointer: String,
+    pub message: String,
+}
+
+/// The kind of a [`Conflict`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    /// Both sides added the pointer with different values
+    BothAdded,
+    /// Both sides changed the pointer to different values
+    BothModified,
+    /// One side removed the pointer while the other changed it, or
+    /// changed anything below it if it is a container
+    DeletedAndModified,
+    /// The merged entry lost its parent container, e.g. because one side
+    /// replaced it by a scalar while the other added children to it.
+    /// Orphans are always dropped from the merged document.
+    Orphaned,
+}
+
+/// A pointer the two sides disagree about
+///
+/// Values are flattened entries, so containers show up as `{}` or `[]`, and
+/// `None` means the pointer is absent from that version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub pointer: String,
+    pub kind: ConflictKind,
+    pub base: Option<Value>,
+    pub ours: Option<Value>,
+    pub theirs: Option<Value>,
+}
+
+/// The outcome of [`merge3`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge3Result {
+    document: FlattenedDocument,
+    conflicts: Vec<Conflict>,
+}
+
+impl Merge3Result {
+    /// The merged flattened document, with conflicts resolved by the strategy
+    pub fn document(&self) -> &FlattenedDocument {
+        &self.document
+    }
+
+    /// Take the merged flattened document
+    pub fn into_document(self) -> FlattenedDocument {
+        self.document
+    }
+
+    /// All conflicts, ordered by [`pointer::compare`]
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Whether the merge had no conflicts
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    /// Rebuild the merged nested document
+    ///
+    /// Fails when naive index merging left a hole in an array.
+    pub fn to_value(&self) -> Result<Value, UnflattenError> {
+        self.document.unflatten()
+    }
+}
+
+/// Three-way merge of two versions of `base`, compared entry by entry
+///
+/// A pointer changed (added, modified or removed) on one side only takes that
+/// change, and identical changes on both sides apply once. Different changes
+/// to the same pointer are reported as [`Conflict`]s and resolved according
+/// to [`Merge3Options::strategy`].
+///
+/// Arrays are merged by index like objects are merged by key, so elements
+/// inserted or removed anywhere but the end shift every following index and
+/// are likely to conflict. Appending to the same array on both sides is a
+/// [`ConflictKind::BothAdded`] conflict.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{ConflictKind, Merge3Options};
+/// use serde_json::json;
+///
+/// let base = json!({ "name": "John", "age": 24, "city": "Paris" });
+/// let ours = json!({ "name": "John Smith", "age": 25, "city": "Paris" });
+/// let theirs = json!({ "name": "John", "age": 26, "city": "Rome" });
+///
+/// let merged = jsonpointer_flatten::merge3(&base, &ours, &theirs, &Merge3Options::new());
+///
+/// assert_eq!(
+///     merged.to_value().unwrap(),
+///     json!({ "name": "John Smith", "age": 25, "city": "Rome" })
+/// );
+/// assert_eq!(merged.conflicts()[0].pointer, "/age");
+/// assert_eq!(merged.conflicts()[0].kind, ConflictKind::BothModified);
+/// ```
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value, options: &Merge3Options) -> Merge3Result {
+    let (base, ours, theirs) = (flat(base), flat(ours), flat(theirs));
+    let resolved = merge_resolved(&base, &ours, &theirs, options, |conflict| {
+        Resolved::Keep(match options.strategy {
+            MergeStrategy::Ours => conflict.ours.as_ref(),
+            MergeStrategy::Theirs => conflict.theirs.as_ref(),
+            MergeStrategy::Base => conflict.base.as_ref(),
+        })
+    });
+    match resolved {
+        Ok(merged) => merged,
+        Err(_) => unreachable!("strategies never fail"),
+    }
+}
+
+/// Three-way merge like [`merge3`], settling each conflict with `resolver`
+///
+/// The resolver is called once per [`Conflict`] in merge order, parents before
+/// children, with the pointer, the kind and our and their flattened values,
+/// `None` being a deletion. [`ConflictKind::Orphaned`] entries are always
+/// dropped and not passed to it. The strategy of `options` is unused, and every
+/// conflict is still reported in the result.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Merge3Options, Resolution};
+/// use serde_json::json;
+///
+/// let base = json!({ "name": null, "seen": 1 });
+/// let ours = json!({ "name": "Jo", "seen": 5 });
+/// let theirs = json!({ "name": null, "seen": 9 });
+///
+/// let merged = jsonpointer_flatten::merge3_with_resolver(
+///     &base,
+///     &ours,
+///     &theirs,
+///     &Merge3Options::new(),
+///     |_, _, ours, theirs| match (ours, theirs) {
+///         (Some(o), Some(t)) if o.as_u64() < t.as_u64() => Resolution::TakeRight,
+///         _ => Resolution::TakeLeft,
+///     },
+/// )
+/// .unwrap();
+///
+/// assert_eq!(merged.to_value().unwrap(), json!({ "name": "Jo", "seen": 9 }));
+/// ```
+pub fn merge3_with_resolver<F>(
+    base: &Value,
+    ours: &Value,
+    theirs: &Value,
+    options: &Merge3Options,
+    resolver: F,
+) -> Result<Merge3Result, MergeError>
+where
+    F: FnMut(&str, ConflictKind, Option<&Value>, Option<&Value>) -> Resolution,
+{
+    let (base, ours, theirs) = (flat(base), flat(ours), flat(theirs));
+    merge_resolved(&base, &ours, &theirs, options, resolve_with(resolver))
+}
+
+/// Two-way merge of `ours` and `theirs`, settling each conflict with `resolver`
+///
+/// Without a base every entry only one side has is kept, and two different
+/// values at the same pointer are a [`ConflictKind::BothAdded`] conflict,
+/// settled by the resolver as in [`merge3_with_resolver`].
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Merge3Options, Resolution};
+/// use serde_json::{json, Value};
+///
+/// let ours = json!({ "name": "Jo", "seen": 5 });
+/// let theirs = json!({ "name": null, "seen": 9, "tags": ["new"] });
+///
+/// let merged = jsonpointer_flatten::merge_with_resolver(
+///     &ours,
+///     &theirs,
+///     &Merge3Options::new(),
+///     |_, _, ours, theirs| match (ours, theirs) {
+///         (_, Some(Value::Null)) => Resolution::TakeLeft,
+///         (Some(o), Some(t)) if o.as_u64() < t.as_u64() => Resolution::TakeRight,
+///         _ => Resolution::TakeLeft,
+///     },
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     merged.to_value().unwrap(),
+///     json!({ "name": "Jo", "seen": 9, "tags": ["new"] })
+/// );
+/// ```
+pub fn merge_with_resolver<F>(
+    ours: &Value,
+    theirs: &Value,
+    options: &Merge3Options,
+    resolver: F,
+) -> Result<Merge3Result, MergeError>
+where
+    F: FnMut(&str, ConflictKind, Option<&Value>, Option<&Value>) -> Resolution,
+{
+    let (ours, theirs) = (flat(ours), flat(theirs));
+    merge_resolved(&Map::new(), &ours, &theirs, options, resolve_with(resolver))
+}
+
+fn flat(value: &Value) -> Map<String, Value> {
+    Flattener::new().flatten_map(value)
+}
+
+/// Adapt a public resolver to the one `merge_resolved` calls per conflict
+fn resolve_with<F>(mut resolver: F) -> impl for<'c> FnMut(&'c Conflict) -> Resolved<'c>
+where
+    F: FnMut(&str, ConflictKind, Option<&Value>, Option<&Value>) -> Resolution,
+{
+    move |conflict| {
+        let (o, t) = (conflict.ours.as_ref(), conflict.theirs.as_ref());
+        match resolver(&conflict.pointer, conflict.kind, o, t) {
+            Resolution::TakeLeft => Resolved::Keep(o),
+            Resolution::TakeRight => Resolved::Keep(t),
+            Resolution::Use(value) => Resolved::Use(value),
+            Resolution::Fail(message) => Resolved::Fail(message),
+        }
+    }
+}
+
+/// The outcome of settling one conflict
+enum Resolved<'a> {
+    Keep(Option<&'a Value>),
+    Use(Value),
+    Fail(String),
+}
+
+/// Merge flattened versions, an empty `base` making it a two-way merge
+fn merge_resolved<F>(
+    base: &Map<String, Value>,
+    ours: &Map<String, Value>,
+    theirs: &Map<String, Value>,
+    options: &Merge3Options,
+    mut resolve: F,
+) -> Result<Merge3Result, MergeError>
+where
+    F: for<'c> FnMut(&'c Conflict) -> Resolved<'c>,
+{
+    // parents come before their children in every flattened map, so this
+    // order checks parents first and keeps our key order
+    let mut seen = HashSet::new();
+    let pointers: Vec<&str> = ours
+        .keys()
+        .chain(theirs.keys())
+        .chain(base.keys())
+        .map(String::as_str)
+        .filter(|ptr| seen.insert(*ptr))
+        .collect();
+
+    let mut merged = Map::new();
+    let mut conflicts = Vec::new();
+    let mut failures = Vec::new();
+
+    for ptr in pointers {
+        let (b, o, t) = (base.get(ptr), ours.get(ptr), theirs.get(ptr));
+        // deleting a container is not one-sided when the other side changed something below it
+        let deleted_modified = b.is_some_and(is_container)
+            && match (o, t) {
+                (None, Some(_)) if t == b => modified_below(theirs, base, ptr),
+                (Some(_), None) if o == b => modified_below(ours, base, ptr),
+                _ => false,
+            };
+        let resolved = if !deleted_modified && (o == t || t == b) {
+            o.map(Cow::Borrowed)
+        } else if !deleted_modified && o == b {
+            t.map(Cow::Borrowed)
+        } else {
+            let kind = match (b, o, t) {
+                (None, _, _) => ConflictKind::BothAdded,
+                (Some(_), Some(_), Some(_)) => ConflictKind::BothModified,
+                _ => ConflictKind::DeletedAndModified,
+            };
+            let conflict = Conflict {
+                pointer: ptr.to_owned(),
+                kind,
+                base: b.cloned(),
+                ours: o.cloned(),
+                theirs: t.cloned(),
+            };
+            let resolved = match resolve(&conflict) {
+                Resolved::Keep(value) => Ok(value.cloned().map(Cow::Owned)),
+                Resolved::Use(value) => Ok(Some(Cow::Owned(value))),
+                Resolved::Fail(message) => Err(message),
+            };
+            conflicts.push(conflict);
+            match resolved {
+                Ok(resolved) => resolved,
+                Err(message) => {
+                    failures.push(ResolverFailure {
+                        pointer: ptr.to_owned(),
+                        message,
+                    });
+                    if options.fail_fast {
+                        break;
+                    }
+                    continue;
+                }
+            }
+        };
+
+        let resolved = match resolved {
+            Some(value) => value,
+            None => continue,
+        };
+        let orphaned = match pointer::parent(ptr) {
+            Some(parent) => !merged.get(parent).is_some_and(is_container),
+            None => false,
+        };
+        if orphaned {
+            // the entry is dropped whichever way its own conflict was resolved
+            conflicts.retain(|conflict| conflict.pointer != ptr);
+            conflicts.push(Conflict {
+                pointer: ptr.to_owned(),
+                kind: ConflictKind::Orphaned,
+                base: b.cloned(),
+                ours: o.cloned(),
+                theirs: t.cloned(),
+            });
+            continue;
+        }
+        if is_container(&resolved) {
+            flatten_into(&resolved, &mut merged, ptr);
+        } else {
+            merged.insert(ptr.to_owned(), resolved.into_owned());
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(MergeError::Unresolved(failures));
+    }
+    conflicts.sort_by(|a, b| pointer::compare(&a.pointer, &b.pointer));
+    Ok(Merge3Result {
+        document: FlattenedDocument::from_map(merged),
+        conflicts,
+    })
+}
+
+/// Whether `side` added or changed an entry strictly below `ptr` compared to `base`
+fn modified_below(side: &Map<String, Value>, base: &Map<String, Value>, ptr: &str) -> bool {
+    side.iter().any(|(key, value)| {
+        key != ptr && pointer::starts_with(key, ptr) && base.get(key) != Some(value)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn merge(base: &Value, ours: &Value, theirs: &Value) -> Merge3Result {
+        merge3(base, ours, theirs, &Merge3Options::new())
+    }
+
+    #[test]
+    fn one_sided_and_identical_changes_apply() {
+        let base = json!({ "a": 1, "b": 2, "c": 3, "d": { "x": 1 } });
+        let ours = json!({ "a": 10, "b": 2, "c": 30, "e": true });
+        let theirs = json!({ "a": 1, "b": 20, "c": 30, "d": { "x": 1 }, "e": true });
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert!(merged.is_clean());
+        assert_eq!(
+            merged.to_value(),
+            Ok(json!({ "a": 10, "b": 20, "c": 30, "e": true }))
+        );
+    }
+
+    #[test]
+    fn conflict_kinds_and_strategies() {
+        let base = json!({ "mod": 0, "del": 0 });
+        let ours = json!({ "mod": 1, "add": "o" });
+        let theirs = json!({ "mod": 2, "del": 2, "add": "t" });
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(
+            merged.conflicts(),
+            &[
+                Conflict {
+                    pointer: "/add".to_owned(),
+                    kind: ConflictKind::BothAdded,
+                    base: None,
+                    ours: Some(json!("o")),
+                    theirs: Some(json!("t")),
+                },
+                Conflict {
+                    pointer: "/del".to_owned(),
+                    kind: ConflictKind::DeletedAndModified,
+                    base: Some(json!(0)),
+                    ours: None,
+                    theirs: Some(json!(2)),
+                },
+                Conflict {
+                    pointer: "/mod".to_owned(),
+                    kind: ConflictKind::BothModified,
+                    base: Some(json!(0)),
+                    ours: Some(json!(1)),
+                    theirs: Some(json!(2)),
+                },
+            ]
+        );
+        assert_eq!(merged.to_value(), Ok(json!({ "mod": 1, "add": "o" })));
+
+        let options = Merge3Options::new().strategy(MergeStrategy::Theirs);
+        let merged = merge3(&base, &ours, &theirs, &options);
+        assert_eq!(merged.conflicts().len(), 3);
+        assert_eq!(
+            merged.to_value(),
+            Ok(json!({ "mod": 2, "del": 2, "add": "t" }))
+        );
+
+        let options = Merge3Options::new().strategy(MergeStrategy::Base);
+        let merged = merge3(&base, &ours, &theirs, &options);
+        assert_eq!(merged.to_value(), Ok(json!({ "mod": 0, "del": 0 })));
+    }
+
+    #[test]
+    fn children_of_replaced_container_are_orphaned() {
+        let base = json!({ "a": {} });
+        let ours = json!({ "a": "scalar" });
+        let theirs = json!({ "a": { "b": { "c": 1 } } });
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(merged.to_value(), Ok(json!({ "a": "scalar" })));
+        assert_eq!(
+            merged
+                .conflicts()
+                .iter()
+                .map(|c| (c.pointer.as_str(), c.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/a/b", ConflictKind::Orphaned),
+                ("/a/b/c", ConflictKind::Orphaned)
+            ]
+        );
+    }
+
+    #[test]
+    fn deleted_container_conflicts_with_changes_below_it() {
+        let base = json!({ "a": { "x": 1, "y": 1 }, "b": { "z": 1 } });
+        let ours = json!({});
+        let theirs = json!({ "a": { "x": 2, "y": 1 }, "b": { "z": 1 } });
+        let kinds = |merged: &Merge3Result| {
+            merged
+                .conflicts()
+                .iter()
+                .map(|c| (c.pointer.clone(), c.kind))
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![
+            ("/a".to_owned(), ConflictKind::DeletedAndModified),
+            ("/a/x".to_owned(), ConflictKind::DeletedAndModified),
+        ];
+
+        let options = Merge3Options::new().strategy(MergeStrategy::Theirs);
+        let merged = merge3(&base, &ours, &theirs, &options);
+        assert_eq!(merged.to_value(), Ok(json!({ "a": { "x": 2 } })));
+        assert_eq!(kinds(&merged), expected);
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(merged.to_value(), Ok(json!({})));
+        assert_eq!(kinds(&merged), expected);
+
+        // the same with the sides swapped
+        let merged = merge(&base, &theirs, &ours);
+        assert_eq!(merged.to_value(), Ok(json!({ "a": { "x": 2 } })));
+        assert_eq!(kinds(&merged), expected);
+    }
+
+    #[test]
+    fn arrays_merge_by_index() {
+        let base = json!({ "tags": ["a", "b"] });
+        let ours = json!({ "tags": ["A", "b"] });
+        let theirs = json!({ "tags": ["a", "b", "c"] });
+
+        let merged = merge(&base, &ours, &theirs);
+
+        assert!(merged.is_clean());
+        assert_eq!(merged.to_value(), Ok(json!({ "tags": ["A", "b", "c"] })));
+
+        let theirs = json!({ "tags": ["a", "b", "d"] });
+        let ours = json!({ "tags": ["a", "b", "c"] });
+        let merged = merge(&base, &ours, &theirs);
+
+        assert_eq!(merged.conflicts()[0].kind, ConflictKind::BothAdded);
+        assert_eq!(merged.conflicts()[0].pointer, "/tags/2");
+    }
+
+    /// Pointer of the record under `/customers` that `ptr` belongs to
+    fn record(ptr: &str) -> Option<&str> {
+        let rest = ptr.strip_prefix("/customers/")?;
+        let len = "/customers/".len() + rest.find('/').unwrap_or(rest.len());
+        Some(&ptr[..len])
+    }
+
+    #[test]
+    fn resolver_keeps_latest_timestamp() {
+        let base = json!({
+            "customers": {
+                "c1": { "email": "a@example.com", "updated_at": "2024-01-01T00:00:00Z" },
+                "c2": { "email": "b@example.com", "updated_at": "2024-01-01T00:00:00Z" }
+            }
+        });
+        let ours = json!({
+            "customers": {
+                "c1": { "email": "a@work.example", "updated_at": "2024-03-05T17:30:00Z" },
+                "c2": { "email": "b@work.example", "updated_at": "2024-02-01T09:00:00Z" }
+            }
+        });
+        let theirs = json!({
+            "customers": {
+                "c1": { "email": "a@home.example", "updated_at": "2024-03-02T08:00:00Z" },
+                "c2": { "email": "b@home.example", "updated_at": "2024-02-11T12:15:00Z" },
+                "c3": { "email": "c@example.com", "updated_at": "2024-02-20T10:00:00Z" }
+            }
+        });
+        let mut calls = Vec::new();
+
+        let merged = merge3_with_resolver(
+            &base,
+            &ours,
+            &theirs,
+            &Merge3Options::new(),
+            |ptr, kind, _, _| {
+                calls.push(ptr.to_owned());
+                assert_eq!(kind, ConflictKind::BothModified);
+                let updated_at = |doc: &Value| {
+                    let record = record(ptr)?;
+                    doc.pointer(&format!("{}/updated_at", record))?
+                        .as_str()
+                        .map(str::to_owned)
+                };
+                match (updated_at(&ours), updated_at(&theirs)) {
+                    (Some(o), Some(t)) if o >= t => Resolution::TakeLeft,
+                    (Some(_), Some(_)) => Resolution::TakeRight,
+                    _ => Resolution::Fail("not a customer record".to_owned()),
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            merged.to_value(),
+            Ok(json!({
+                "customers": {
+                    "c1": { "email": "a@work.example", "updated_at": "2024-03-05T17:30:00Z" },
+                    "c2": { "email": "b@home.example", "updated_at": "2024-02-11T12:15:00Z" },
+                    "c3": { "email": "c@example.com", "updated_at": "2024-02-20T10:00:00Z" }
+                }
+            }))
+        );
+        assert_eq!(merged.conflicts().len(), 4);
+        assert_eq!(
+            calls,
+            vec![
+                "/customers/c1/email",
+                "/customers/c1/updated_at",
+                "/customers/c2/email",
+                "/customers/c2/updated_at"
+            ]
+        );
+    }
+
+    #[test]
+    fn two_way_resolver_keeps_latest_timestamp() {
+        let ours = json!({
+            "customers": {
+                "c1": { "email": "a@work.example", "updated_at": "2024-03-05T17:30:00Z" },
+                "c2": { "email": "b@example.com", "updated_at": "2024-01-01T00:00:00Z" }
+            }
+        });
+        let theirs = json!({
+            "customers": {
+                "c1": { "email": "a@home.example", "updated_at": "2024-03-02T08:00:00Z" },
+                "c2": { "email": "b@home.example", "updated_at": "2024-02-11T12:15:00Z" },
+                "c3": { "email": "c@example.com", "updated_at": "2024-02-20T10:00:00Z" }
+            }
+        });
+        let mut calls = Vec::new();
+
+        let merged =
+            merge_with_resolver(&ours, &theirs, &Merge3Options::new(), |ptr, kind, _, _| {
+                calls.push(ptr.to_owned());
+                assert_eq!(kind, ConflictKind::BothAdded);
+                let updated_at = |doc: &Value| {
+                    let record = record(ptr)?;
+                    doc.pointer(&format!("{}/updated_at", record))?
+                        .as_str()
+                        .map(str::to_owned)
+                };
+                match (updated_at(&ours), updated_at(&theirs)) {
+                    (Some(o), Some(t)) if o >= t => Resolution::TakeLeft,
+                    (Some(_), Some(_)) => Resolution::TakeRight,
+                    _ => Resolution::Fail("not a customer record".to_owned()),
+                }
+            })
+            .unwrap();
+
+        assert_eq!(
+            merged.to_value(),
+            Ok(json!({
+                "customers": {
+                    "c1": { "email": "a@work.example", "updated_at": "2024-03-05T17:30:00Z" },
+                    "c2": { "email": "b@home.example", "updated_at": "2024-02-11T12:15:00Z" },
+                    "c3": { "email": "c@example.com", "updated_at": "2024-02-20T10:00:00Z" }
+                }
+            }))
+        );
+        assert_eq!(
+            calls,
+            vec![
+                "/customers/c1/email",
+                "/customers/c1/updated_at",
+                "/customers/c2/email",
+                "/customers/c2/updated_at"
+            ]
+        );
+
+        let failure = |ptr: &str| ResolverFailure {
+            pointer: ptr.to_owned(),
+            message: "no".to_owned(),
+        };
+        let options = Merge3Options::new().fail_fast(true);
+        assert_eq!(
+            merge_with_resolver(
+                &json!({ "a": 1, "b": 1 }),
+                &json!({ "a": 2, "b": 2 }),
+                &options,
+                |_, _, _, _| { Resolution::Fail("no".to_owned()) }
+            ),
+            Err(MergeError::Unresolved(vec![failure("/a")]))
+        );
+    }
+
+    #[test]
+    fn resolver_failures_are_collected_or_fail_fast() {
+        let base = json!({ "a": 0, "b": 0, "c": { "x": 0 } });
+        let ours = json!({ "a": 1, "b": 1, "c": { "x": 1 } });
+        let theirs = json!({ "a": 2, "b": 2, "c": { "x": 2 } });
+        let resolver = |ptr: &str, _, _: Option<&Value>, _: Option<&Value>| match ptr {
+            "/c/x" => Resolution::Use(json!([null])),
+            _ => Resolution::Fail(format!("{} is immutable", ptr)),
+        };
+        let failure = |ptr: &str| ResolverFailure {
+            pointer: ptr.to_owned(),
+            message: format!("{} is immutable", ptr),
+        };
+
+        assert_eq!(
+            merge3_with_resolver(&base, &ours, &theirs, &Merge3Options::new(), resolver),
+            Err(MergeError::Unresolved(vec![failure("/a"), failure("/b")]))
+        );
+        let options = Merge3Options::new().fail_fast(true);
+        assert_eq!(
+            merge3_with_resolver(&base, &ours, &theirs, &options, resolver),
+            Err(MergeError::Unresolved(vec![failure("/a")]))
+        );
+
+        let ours = json!({ "a": 0, "b": 0, "c": { "x": 1 } });
+        let merged =
+            merge3_with_resolver(&base, &ours, &theirs, &Merge3Options::new(), resolver).unwrap();
+        assert_eq!(
+            merged.to_value(),
+            Ok(json!({ "a": 2, "b": 2, "c": { "x": [null] } }))
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::error::MergePatchError;
+use crate::flattener::{flatten_into, is_container};
+use crate::pointer::{escape_into, remove_subtree};
+use crate::FlattenedDocument;
+use serde_json::{Map, Value};
+
+/// Build an [RFC 7386](https://tools.ietf.org/html/rfc7386) JSON Merge Patch turning `a` into `b`
+///
+/// Objects are compared member by member and removed members become `null`.
+/// Everything else, arrays included, is replaced wholesale. Equal documents
+/// produce the empty patch `{}`.
+///
+/// Merge patches use `null` for deletion, so they can't set an object member
+/// to `null`. Fails with [`MergePatchError::NullValue`] when `b` would need
+/// that, be it for a changed member or inside a newly added object. Nulls
+/// within arrays are fine.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let a = json!({ "title": "Hello", "author": { "name": "John", "email": "a@b.c" } });
+/// let b = json!({ "title": "Hi", "author": { "name": "John" }, "tags": ["x"] });
+///
+/// assert_eq!(
+///     jsonpointer_flatten::diff_to_merge_patch(&a, &b),
+///     Ok(json!({ "title": "Hi", "author": { "email": null }, "tags": ["x"] }))
+/// );
+/// ```
+pub fn diff_to_merge_patch(a: &Value, b: &Value) -> Result<Value, MergePatchError> {
+    let mut pointer = String::new();
+    match merge_diff(a, b, &mut pointer)? {
+        Some(patch) => Ok(patch),
+        None => Ok(Value::Object(Map::new())),
+    }
+}
+
+/// Apply an [RFC 7386](https://tools.ietf.org/html/rfc7386) JSON Merge Patch to a flattened document
+///
+/// An object patch merges into the object at the same pointer, replacing a
+/// non-object there, and a `null` member removes the entry together with
+/// everything below it. Any other patch value, arrays included, replaces the
+/// subtree at its pointer.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::FlattenedDocument;
+/// use serde_json::json;
+///
+/// let mut doc = FlattenedDocument::from(&json!({ "a": { "b": 1, "c": [1, 2] } }));
+///
+/// jsonpointer_flatten::apply_merge_patch_flat(&mut doc, &json!({ "a": { "b": null, "c": [3] } }));
+///
+/// assert_eq!(doc, FlattenedDocument::from(&json!({ "a": { "c": [3] } })));
+/// ```
+pub fn apply_merge_patch_flat(doc: &mut FlattenedDocument, patch: &Value) {
+    let mut pointer = String::new();
+    merge_into(doc.entries_mut(), &mut pointer, patch);
+}
+
+fn merge_diff(
+    a: &Value,
+    b: &Value,
+    pointer: &mut String,
+) -> Result<Option<Value>, MergePatchError> {
+    let (a_obj, b_obj) = match (a, b) {
+        (Value::Object(a_obj), Value::Object(b_obj)) if is_container(a) && is_container(b) => {
+            (a_obj, b_obj)
+        }
+        _ if a == b => return Ok(None),
+        _ => {
+            check_representable(b, pointer)?;
+            return Ok(Some(b.clone()));
+        }
+    };
+
+    let mut patch = Map::new();
+    for key in a_obj.keys() {
+        if !b_obj.contains_key(key) {
+            patch.insert(key.clone(), Value::Null);
+        }
+    }
+
+    let len = pointer.len();
+    for (key, b_val) in b_obj {
+        pointer.push('/');
+        escape_into(key, pointer);
+        let member = match a_obj.get(key) {
+            Some(a_val) => merge_diff(a_val, b_val, pointer)?,
+            None => {
+                check_representable(b_val, pointer)?;
+                Some(b_val.clone())
+            }
+        };
+        pointer.truncate(len);
+
+        if let Some(member) = member {
+            patch.insert(key.clone(), member);
+        }
+    }
+
+    Ok(if patch.is_empty() {
+        None
+    } else {
+        Some(Value::Object(patch))
+    })
+}
+
+/// Check that `value`, written as an object member at `pointer`, survives applying
+fn check_representable(value: &Value, pointer: &mut String) -> Result<(), MergePatchError> {
+    match value {
+        Value::Null if !pointer.is_empty() => Err(MergePatchError::NullValue {
+            pointer: pointer.clone(),
+        }),
+        Value::Object(obj) if is_container(value) => {
+            let len = pointer.len();
+            for (key, val) in obj {
+                pointer.push('/');
+                escape_into(key, pointer);
+                check_representable(val, pointer)?;
+                pointer.truncate(len);
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn merge_into(entries: &mut Map<String, Value>, pointer: &mut String, patch: &Value) {
+    let members = match patch {
+        Value::Object(members) if is_container(patch) => members,
+        _ => {
+            remove_subtree(entries, pointer);
+            flatten_into(patch, entries, pointer);
+            return;
+        }
+    };
+
+    let is_object = entries
+        .get(pointer.as_str())
+        .is_some_and(|value| value.is_object() && is_container(value));
+    if !is_object {
+        remove_subtree(entries, pointer);
+        entries.insert(pointer.clone(), Value::Object(Map::new()));
+    }
+
+    let len = pointer.len();
+    for (key, val) in members {
+        pointer.push('/');
+        escape_into(key, pointer);
+        if val.is_null() {
+            remove_subtree(entries, pointer);
+        } else {
+            merge_into(entries, pointer, val);
+        }
+        pointer.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    /// Appendix A of RFC 7386 as `(original, patch, result)`
+    fn rfc_examples() -> Vec<(Value, Value, Value)> {
+        vec![
+            (json!({"a":"b"}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"b"}), json!({"b":"c"}), json!({"a":"b","b":"c"})),
+            (json!({"a":"b"}), json!({"a":null}), json!({})),
+            (
+                json!({"a":"b","b":"c"}),
+                json!({"a":null}),
+                json!({"b":"c"}),
+            ),
+            (json!({"a":["b"]}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"c"}), json!({"a":["b"]}), json!({"a":["b"]})),
+            (
+                json!({"a":{"b":"c"}}),
+                json!({"a":{"b":"d","c":null}}),
+                json!({"a":{"b":"d"}}),
+            ),
+            (json!({"a":[{"b":"c"}]}), json!({"a":[1]}), json!({"a":[1]})),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a":"b"}), json!(["c"]), json!(["c"])),
+            (json!({"a":"foo"}), json!(null), json!(null)),
+            (json!({"a":"foo"}), json!("bar"), json!("bar")),
+            (json!({"e":null}), json!({"a":1}), json!({"e":null,"a":1})),
+            (json!([1, 2]), json!({"a":"b","c":null}), json!({"a":"b"})),
+            (
+                json!({}),
+                json!({"a":{"bb":{"ccc":null}}}),
+                json!({"a":{"bb":{}}}),
+            ),
+        ]
+    }
+
+    #[test]
+    fn apply_rfc_examples() {
+        for (original, patch, result) in rfc_examples() {
+            let mut doc = FlattenedDocument::from(&original);
+
+            apply_merge_patch_flat(&mut doc, &patch);
+
+            assert_eq!(doc, FlattenedDocument::from(&result), "{}", patch);
+        }
+    }
+
+    #[test]
+    fn diff_round_trips_rfc_examples() {
+        for (original, _, result) in rfc_examples() {
+            let patch = diff_to_merge_patch(&original, &result).unwrap();
+            let mut doc = FlattenedDocument::from(&original);
+
+            apply_merge_patch_flat(&mut doc, &patch);
+
+            assert_eq!(doc, FlattenedDocument::from(&result), "{}", patch);
+        }
+    }
+
+    #[test]
+    fn diff_is_minimal() {
+        let a = json!({ "a": { "b": 1, "c": [1, 2] }, "d": 1 });
+
+        assert_eq!(diff_to_merge_patch(&a, &a), Ok(json!({})));
+        assert_eq!(
+            diff_to_merge_patch(&a, &json!({ "a": { "b": 1, "c": [1] }, "d": 1 })),
+            Ok(json!({ "a": { "c": [1] } }))
+        );
+        assert_eq!(
+            diff_to_merge_patch(&a, &json!({ "a": { "b": 1, "c": [null] } })),
+            Ok(json!({ "a": { "c": [null] }, "d": null }))
+        );
+    }
+
+    #[test]
+    fn nulls_in_objects_are_not_representable() {
+        assert_eq!(
+            diff_to_merge_patch(&json!({ "a": 1 }), &json!({ "a": null })),
+            Err(MergePatchError::NullValue {
+                pointer: "/a".to_owned()
+            })
+        );
+        assert_eq!(
+            diff_to_merge_patch(&json!({}), &json!({ "a": { "b~": null } })),
+            Err(MergePatchError::NullValue {
+                pointer: "/a/b~0".to_owned()
+            })
+        );
+        assert_eq!(
+            diff_to_merge_patch(&json!(1), &json!(null)),
+            Ok(json!(null))
+        );
+    }
+}
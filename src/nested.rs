@@ -0,0 +1,96 @@
+//! Serde helpers exposing a flattened field that is stored nested
+//!
+//! The inverse of [`flat`](crate::flat): with
+//! `#[serde(with = "jsonpointer_flatten::nested")]`, a field holding flattened
+//! entries, like a [`FlattenedDocument`](crate::FlattenedDocument) or an object
+//! [`Value`] of pointer keys, is serialized as the nested document
+//! [`unflatten`](crate::unflatten) rebuilds, and flattened again when
+//! deserialized.
+//!
+//! # Example
+//!
+//! ```
+//! use jsonpointer_flatten::FlattenedDocument;
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::json;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Record {
+//!     #[serde(with = "jsonpointer_flatten::nested")]
+//!     settings: FlattenedDocument,
+//! }
+//!
+//! let record: Record = serde_json::from_value(json!({ "settings": { "theme": "dark" } })).unwrap();
+//!
+//! assert_eq!(record.settings.get("/theme"), Some(&json!("dark")));
+//! assert_eq!(serde_json::to_value(&record).unwrap(), json!({ "settings": { "theme": "dark" } }));
+//! ```
+use crate::{from_json, unflatten};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde_json::Value;
+
+/// Unflatten the entries of `value` and serialize the nested document
+///
+/// Entries that don't form a document fail with the message of the
+/// [`UnflattenError`](crate::UnflattenError), which names the pointer.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let flattened = serde_json::to_value(value).map_err(ser::Error::custom)?;
+    unflatten(&flattened)
+        .map_err(|error| ser::Error::custom(format!("invalid flattened value: {}", error)))?
+        .serialize(serializer)
+}
+
+/// Deserialize a nested document and flatten it into a `T`
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    let nested = Value::deserialize(deserializer)?;
+    serde_json::from_value(from_json(&nested)).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::FlattenedDocument;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "crate::nested")]
+        document: FlattenedDocument,
+        #[serde(with = "crate::nested")]
+        entries: Value,
+    }
+
+    #[test]
+    fn round_trips_through_json_text() {
+        let nested = json!({ "a": [1, { "b~c": true }] });
+        let text =
+            serde_json::to_string(&json!({ "document": nested, "entries": [null] })).unwrap();
+
+        let record: Record = serde_json::from_str(&text).unwrap();
+
+        assert_eq!(record.document, FlattenedDocument::from(&nested));
+        assert_eq!(record.entries, json!({ "": [], "/0": null }));
+        assert_eq!(serde_json::to_string(&record).unwrap(), text);
+    }
+
+    #[test]
+    fn unflatten_errors_become_serde_errors() {
+        let record = Record {
+            document: FlattenedDocument::new(),
+            entries: json!({ "/a": 1, "/a/b": 2 }),
+        };
+
+        let error = serde_json::to_string(&record).unwrap_err();
+
+        assert!(error.to_string().contains("\"/a/b\""), "{}", error);
+    }
+}
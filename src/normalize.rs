@@ -0,0 +1,212 @@
+use crate::flattener::is_container;
+use crate::pointer::{self, escape_into};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write;
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// Unicode normalization applied to object keys, see [`Flattener::normalize_keys`](crate::Flattener::normalize_keys)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyNormalization {
+    /// Canonical composition, merging precomposed and decomposed spellings
+    Nfc,
+    /// Compatibility composition, also merging variants like full-width letters
+    Nfkc,
+}
+
+impl KeyNormalization {
+    /// Normalize a key, borrowing it if it's normalized already
+    pub(crate) fn apply(self, key: &str) -> Cow<'_, str> {
+        match self {
+            _ if self.is_normalized(key) => Cow::Borrowed(key),
+            KeyNormalization::Nfc => Cow::Owned(key.nfc().collect()),
+            KeyNormalization::Nfkc => Cow::Owned(key.nfkc().collect()),
+        }
+    }
+
+    /// Length of `key` once normalized and escaped, measured without building it
+    pub(crate) fn escaped_len(self, key: &str) -> usize {
+        let len = |c: char| match c {
+            '~' | '/' => 2,
+            c => c.len_utf8(),
+        };
+        match self {
+            _ if self.is_normalized(key) => key.chars().map(len).sum(),
+            KeyNormalization::Nfc => key.nfc().map(len).sum(),
+            KeyNormalization::Nfkc => key.nfkc().map(len).sum(),
+        }
+    }
+
+    fn is_normalized(self, key: &str) -> bool {
+        let quick = match self {
+            KeyNormalization::Nfc => is_nfc_quick(key.chars()),
+            KeyNormalization::Nfkc => is_nfkc_quick(key.chars()),
+        };
+        quick == IsNormalized::Yes
+    }
+
+    /// Normalize every segment of a pointer, escaping again what normalization introduced
+    pub(crate) fn apply_pointer(self, ptr: &str) -> String {
+        let mut normalized = String::with_capacity(ptr.len());
+        for segment in ptr.split('/').skip(1) {
+            normalized.push('/');
+            escape_into(&self.apply(&pointer::unescape(segment)), &mut normalized);
+        }
+        normalized
+    }
+
+    /// Normalize the keys of flattened entries, later entries replacing earlier ones
+    pub(crate) fn apply_entries(
+        self,
+        entries: &Map<String, Value>,
+        root: &str,
+    ) -> Map<String, Value> {
+        entries
+            .iter()
+            .map(|(key, value)| {
+                let key = if key == root || pointer::validate(key).is_err() {
+                    key.clone()
+                } else {
+                    self.apply_pointer(key)
+                };
+                (key, value.clone())
+            })
+            .collect()
+    }
+}
+
+/// Distinct keys of one object that normalize to the same key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCollision {
+    /// Pointer of the object holding the keys
+    pub pointer_of_parent: String,
+    /// The colliding keys as they appear in the document
+    pub original_keys: Vec<String>,
+    /// Escaped segment they all normalize to
+    pub normalized_segment: String,
+}
+
+/// Find every set of keys that collide after normalization, in flattening order
+pub(crate) fn collisions(value: &Value, normalization: KeyNormalization) -> Vec<KeyCollision> {
+    let mut found = Vec::new();
+    let mut pointer = String::new();
+    collect(value, normalization, &mut pointer, &mut found);
+    found
+}
+
+fn collect(
+    value: &Value,
+    normalization: KeyNormalization,
+    pointer: &mut String,
+    found: &mut Vec<KeyCollision>,
+) {
+    let len = pointer.len();
+    match value {
+        Value::Array(arr) => {
+            for (idx, val) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                collect(val, normalization, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(obj) if is_container(value) => {
+            let mut index: HashMap<Cow<'_, str>, usize> = HashMap::new();
+            let mut groups: Vec<(Cow<'_, str>, Vec<&String>)> = Vec::new();
+            for key in obj.keys() {
+                let normalized = normalization.apply(key);
+                match index.get(&normalized) {
+                    Some(&group) => groups[group].1.push(key),
+                    None => {
+                        index.insert(normalized.clone(), groups.len());
+                        groups.push((normalized, vec![key]));
+                    }
+                }
+            }
+            for (normalized, keys) in groups {
+                if keys.len() > 1 {
+                    let mut segment = String::new();
+                    escape_into(&normalized, &mut segment);
+                    found.push(KeyCollision {
+                        pointer_of_parent: pointer.clone(),
+                        original_keys: keys.into_iter().cloned().collect(),
+                        normalized_segment: segment,
+                    });
+                }
+            }
+
+            for (key, val) in obj {
+                pointer.push('/');
+                escape_into(&normalization.apply(key), pointer);
+                collect(val, normalization, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FlattenError, Flattener, UnflattenOptions};
+    use serde_json::json;
+
+    const NFD: &str = "cafe\u{301}";
+    const NFC: &str = "caf\u{e9}";
+
+    #[test]
+    fn variants_merge_into_one_pointer() {
+        let value = json!({ NFD: { "a": 1 }, NFC: { "b": 2 } });
+        let flattener = Flattener::new().normalize_keys(KeyNormalization::Nfc);
+
+        let flattened = flattener.flatten(&value);
+
+        assert_eq!(
+            flattened,
+            json!({ "": {}, "/caf\u{e9}": {}, "/caf\u{e9}/a": 1, "/caf\u{e9}/b": 2 })
+        );
+        let options = UnflattenOptions::new().normalize_keys(KeyNormalization::Nfc);
+        assert_eq!(
+            crate::unflatten_with(&flattened, &options),
+            Ok(json!({ NFC: { "a": 1, "b": 2 } }))
+        );
+    }
+
+    #[test]
+    fn collisions_are_reported() {
+        let value = json!({ "x": [{ NFD: 1, NFC: 2, "y": 3 }] });
+        let flattener = Flattener::new().normalize_keys(KeyNormalization::Nfc);
+
+        assert_eq!(
+            flattener.try_flatten(&value),
+            Err(FlattenError::NormalizedKeyCollisions(vec![KeyCollision {
+                pointer_of_parent: "/x/0".to_owned(),
+                original_keys: vec![NFD.to_owned(), NFC.to_owned()],
+                normalized_segment: NFC.to_owned(),
+            }]))
+        );
+        assert!(flattener.try_flatten(&json!({ NFD: 1 })).is_ok());
+    }
+
+    #[test]
+    fn compatibility_forms_are_escaped_after_normalizing() {
+        let value = json!({ "a\u{ff0f}b": 1 });
+        let flattener = Flattener::new().normalize_keys(KeyNormalization::Nfkc);
+
+        let flattened = flattener.flatten(&value);
+        assert_eq!(flattened, json!({ "": {}, "/a~1b": 1 }));
+
+        let options = UnflattenOptions::new().normalize_keys(KeyNormalization::Nfkc);
+        assert_eq!(
+            crate::unflatten_with(&json!({ "/a\u{ff0f}b/c\u{ff0f}": 1 }), &options),
+            Ok(json!({ "a/b": { "c/": 1 } }))
+        );
+        assert_eq!(
+            Flattener::new()
+                .normalize_keys(KeyNormalization::Nfc)
+                .flatten(&value),
+            json!({ "": {}, "/a\u{ff0f}b": 1 })
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::flattener::{entry_value, is_container};
+use serde_json::{Number, Value};
+
+/// 2^63, the first double past `i64::MAX`
+const I64_END: f64 = 9_223_372_036_854_775_808.0;
+/// 2^64, the first double past `u64::MAX`
+const U64_END: f64 = 18_446_744_073_709_551_616.0;
+
+/// Map a number to the canonical form of its value
+///
+/// Numbers that are equal as values then also compare equal as [`Number`]s:
+///
+/// - Integers in the `i64` or `u64` range stay the same integer.
+/// - Doubles with an integral value in the `i64` or `u64` range become that
+///   integer, so `1.0` and `1e2` become `1` and `100`, and `-0.0` becomes `0`.
+/// - Other doubles stay doubles, including integral ones past `u64::MAX`,
+///   like `2e19`.
+///
+/// Integers are kept exactly, so `9007199254740993` is not equal to the
+/// double `9007199254740993.0`, which parses to `9007199254740992`. With
+/// the `arbitrary_precision` feature, numbers that are neither `i64` nor
+/// `u64` are rounded to the nearest double first, and those too large for one
+/// are kept as they are.
+pub fn normalize_number(number: &Number) -> Number {
+    if let Some(int) = number.as_i64() {
+        return Number::from(int);
+    }
+    if let Some(int) = number.as_u64() {
+        return Number::from(int);
+    }
+    match number.as_f64() {
+        Some(float) if float.is_finite() => {
+            if float.trunc() == float && (-I64_END..I64_END).contains(&float) {
+                Number::from(float as i64)
+            } else if float.trunc() == float && (0.0..U64_END).contains(&float) {
+                Number::from(float as u64)
+            } else {
+                Number::from_f64(float).unwrap_or_else(|| number.clone())
+            }
+        }
+        _ => number.clone(),
+    }
+}
+
+/// A copy of `value` with every number replaced by [`normalize_number`]
+///
+/// Works on nested documents and flattened ones alike, so either can be
+/// normalized before hashing or serializing it.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "a": 1.0, "b": [-0.0, 1e2, 0.5] });
+///
+/// assert_eq!(
+///     jsonpointer_flatten::normalize_numbers(&value),
+///     json!({ "a": 1, "b": [0, 100, 0.5] })
+/// );
+/// ```
+pub fn normalize_numbers(value: &Value) -> Value {
+    match value {
+        Value::Array(arr) => Value::Array(arr.iter().map(normalize_numbers).collect()),
+        Value::Object(obj) if is_container(value) => Value::Object(
+            obj.iter()
+                .map(|(key, val)| (key.clone(), normalize_numbers(val)))
+                .collect(),
+        ),
+        _ => normalized_leaf(value),
+    }
+}
+
+/// A leaf with its number normalized, as stored by [`entry_value`]
+pub(crate) fn normalized_leaf(value: &Value) -> Value {
+    match entry_value(value) {
+        Value::Number(number) => Value::Number(normalize_number(&number)),
+        leaf => leaf,
+    }
+}
+
+/// Whether two entries are equal once their numbers are normalized
+pub(crate) fn eq_normalized(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => normalize_number(a) == normalize_number(b),
+        // `arbitrary_precision` numbers carried as objects
+        (Value::Object(_), _) | (_, Value::Object(_)) if !is_container(a) && !is_container(b) => {
+            normalized_leaf(a) == normalized_leaf(b)
+        }
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn normalized(text: &str) -> Value {
+        normalize_numbers(&serde_json::from_str(text).unwrap())
+    }
+
+    #[test]
+    fn integral_doubles_become_integers() {
+        assert_eq!(normalized("1.0"), json!(1));
+        assert_eq!(normalized("1e2"), json!(100));
+        assert_eq!(normalized("-0.0"), json!(0));
+        assert_eq!(normalized("-0"), json!(0));
+        assert_eq!(normalized("-2.50e1"), json!(-25));
+        assert_eq!(normalized("0.5"), json!(0.5));
+        assert!(normalized("1.0").is_u64());
+    }
+
+    #[test]
+    fn integer_range_boundaries() {
+        assert_eq!(normalized("-9223372036854775808"), json!(i64::MIN));
+        assert_eq!(normalized("9223372036854775807"), json!(i64::MAX));
+        assert_eq!(normalized("18446744073709551615"), json!(u64::MAX));
+        assert_eq!(normalized("-9223372036854775808.0"), json!(i64::MIN));
+        // i64::MAX has no double, the nearest one is 2^63, which only fits in a u64
+        assert_eq!(normalized("9223372036854775807.0"), json!(1u64 << 63));
+        // u64::MAX rounds up to 2^64 as a double, past the u64 range
+        assert!(normalized("18446744073709551615.0").is_f64());
+        assert!(normalized("-9223372036854777856.0").is_f64());
+        assert!(normalized("1e300").is_f64());
+        assert_ne!(
+            normalized("9007199254740993"),
+            normalized("9007199254740993.0")
+        );
+        assert_eq!(
+            normalized("9007199254740992"),
+            normalized("9007199254740993.0")
+        );
+    }
+
+    #[test]
+    fn only_numbers_change() {
+        let value = json!({ "a": [1.0, "1.0", null, { "b": -0.0 }], "c": true });
+
+        assert_eq!(
+            normalize_numbers(&value),
+            json!({ "a": [1, "1.0", null, { "b": 0 }], "c": true })
+        );
+        assert!(eq_normalized(&json!(2.0), &json!(2)));
+        assert!(!eq_normalized(&json!("2"), &json!(2)));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn arbitrary_precision_text_is_normalized() {
+        assert_eq!(normalized("1.50"), normalized("1.5"));
+        assert_eq!(normalized("1E2"), json!(100));
+        assert_eq!(
+            normalized("1e400"),
+            serde_json::from_str::<Value>("1e400").unwrap()
+        );
+    }
+}
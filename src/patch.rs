@@ -0,0 +1,908 @@
+use crate::error::PatchError;
+use crate::flattener::is_container;
+use crate::pointer::{self, is_array_index};
+use crate::unflatten::{unflatten_map, UnflattenOptions};
+use crate::{FlattenedDocument, Flattener};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+/// Pointers whose entries a patch touched, see [`FlattenedDocument::update_from_patch`]
+///
+/// Each list is sorted by [`pointer::compare`](crate::pointer::compare). An
+/// entry that an operation changed and a later one changed back is not listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Entries that did not exist before the patch
+    pub added: Vec<String>,
+    /// Entries that no longer exist
+    pub removed: Vec<String>,
+    /// Entries that exist before and after with a different value
+    pub changed: Vec<String>,
+}
+
+impl UpdateReport {
+    /// Every pointer of the report, in no particular order
+    pub fn pointers(&self) -> impl Iterator<Item = &str> {
+        self.added
+            .iter()
+            .chain(&self.removed)
+            .chain(&self.changed)
+            .map(String::as_str)
+    }
+
+    /// Number of pointers in the report
+    pub fn len(&self) -> usize {
+        self.added.len() + self.removed.len() + self.changed.len()
+    }
+
+    /// Whether the patch left every entry as it was
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FlattenedDocument {
+    /// Apply an [RFC 6902](https://tools.ietf.org/html/rfc6902) JSON Patch in place
+    ///
+    /// Operations work on the flattened entries directly: inserting into or
+    /// removing from an array renames the entries of the following elements,
+    /// and added values are flattened below their path, placeholders and all.
+    /// Patches are all-or-nothing, so the document is left unchanged when any
+    /// operation fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let mut doc = FlattenedDocument::from(&json!({ "phones": ["123", "456"] }));
+    /// let patch = json!([
+    ///     { "op": "remove", "path": "/phones/0" },
+    ///     { "op": "add", "path": "/address", "value": { "zip": "00000" } }
+    /// ]);
+    ///
+    /// doc.apply_patch(&patch).unwrap();
+    ///
+    /// assert_eq!(doc.get("/phones/0"), Some(&json!("456")));
+    /// assert_eq!(doc.get("/phones/1"), None);
+    /// assert_eq!(doc.get("/address"), Some(&json!({})));
+    /// assert_eq!(doc.get("/address/zip"), Some(&json!("00000")));
+    /// ```
+    pub fn apply_patch(&mut self, patch: &Value) -> Result<(), PatchError> {
+        self.apply_operations(operations(patch)?)
+    }
+
+    pub(crate) fn apply_operations<'p>(
+        &mut self,
+        operations: impl IntoIterator<Item = ParsedOperation<'p>>,
+    ) -> Result<(), PatchError> {
+        // patching a copy keeps even the order of entries when an operation fails
+        let mut entries = self.as_map().clone();
+        let mut patcher = Patcher::new(&mut entries);
+        for operation in operations {
+            patcher.apply(operation?)?;
+        }
+        *self.entries_mut() = entries;
+        Ok(())
+    }
+
+    /// Apply a JSON Patch in place like [`FlattenedDocument::apply_patch`], reporting the changed entries
+    ///
+    /// Operations on a leaf cost a few lookups each, and so does shifting
+    /// array elements that are all leaves. Operations on a container, and
+    /// shifting elements that are containers, scan every key for the
+    /// descendants, as a map of entries can't be looked up by prefix, so they
+    /// take time proportional to the document. Nothing is copied up front, so
+    /// when an operation fails the touched entries are restored from the
+    /// values saved along the way. With serde_json's `preserve_order` feature,
+    /// removing or renaming entries also rebuilds the whole map to keep them
+    /// in order, and restored entries may end up last.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let mut doc = FlattenedDocument::from(&json!({ "tags": ["a", "b"], "n": 1 }));
+    /// let patch = json!([
+    ///     { "op": "add", "path": "/tags/0", "value": "z" },
+    ///     { "op": "replace", "path": "/n", "value": 2 }
+    /// ]);
+    ///
+    /// let report = doc.update_from_patch(&patch).unwrap();
+    ///
+    /// assert_eq!(report.added, vec!["/tags/2"]);
+    /// assert_eq!(report.changed, vec!["/n", "/tags/0", "/tags/1"]);
+    /// assert_eq!(doc, FlattenedDocument::from(&json!({ "tags": ["z", "a", "b"], "n": 2 })));
+    /// ```
+    pub fn update_from_patch(&mut self, patch: &Value) -> Result<UpdateReport, PatchError> {
+        self.update_from_operations(operations(patch)?)
+    }
+
+    pub(crate) fn update_from_operations<'p>(
+        &mut self,
+        operations: impl IntoIterator<Item = ParsedOperation<'p>>,
+    ) -> Result<UpdateReport, PatchError> {
+        let mut patcher = Patcher::new(self.entries_mut());
+        for operation in operations {
+            if let Err(error) = operation.and_then(|operation| patcher.apply(operation)) {
+                patcher.restore();
+                return Err(error);
+            }
+        }
+        Ok(patcher.report())
+    }
+}
+
+fn operations(patch: &Value) -> Result<impl Iterator<Item = ParsedOperation<'_>>, PatchError> {
+    let operations = patch.as_array().ok_or_else(|| {
+        PatchError::InvalidOperation("patch must be an array of operations".to_owned())
+    })?;
+    Ok(operations
+        .iter()
+        .enumerate()
+        .map(|(idx, operation)| parse(idx, operation)))
+}
+
+/// An operation of a patch with valid pointers, borrowed from the patch
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Operation<'p> {
+    Add { path: &'p str, value: &'p Value },
+    Remove { path: &'p str },
+    Replace { path: &'p str, value: &'p Value },
+    Move { from: &'p str, path: &'p str },
+    Copy { from: &'p str, path: &'p str },
+    Test { path: &'p str, value: &'p Value },
+}
+
+pub(crate) type ParsedOperation<'p> = Result<Operation<'p>, PatchError>;
+
+fn parse(idx: usize, operation: &Value) -> ParsedOperation<'_> {
+    let member = |name: &str| {
+        operation.get(name).ok_or_else(|| {
+            PatchError::InvalidOperation(format!("operation {} is missing \"{}\"", idx, name))
+        })
+    };
+    let pointer_member = |name: &str| {
+        let ptr = member(name)?.as_str().ok_or_else(|| {
+            PatchError::InvalidOperation(format!(
+                "\"{}\" of operation {} must be a string",
+                name, idx
+            ))
+        })?;
+        pointer::validate(ptr)?;
+        Ok::<_, PatchError>(ptr)
+    };
+
+    let path = pointer_member("path")?;
+    Ok(match member("op")?.as_str() {
+        Some("add") => Operation::Add {
+            path,
+            value: member("value")?,
+        },
+        Some("remove") => Operation::Remove { path },
+        Some("replace") => Operation::Replace {
+            path,
+            value: member("value")?,
+        },
+        Some("move") => Operation::Move {
+            from: pointer_member("from")?,
+            path,
+        },
+        Some("copy") => Operation::Copy {
+            from: pointer_member("from")?,
+            path,
+        },
+        Some("test") => Operation::Test {
+            path,
+            value: member("value")?,
+        },
+        _ => {
+            return Err(PatchError::InvalidOperation(format!(
+                "operation {} has an unknown \"op\"",
+                idx
+            )))
+        }
+    })
+}
+
+/// Applies operations to the entries, saving the old value of every key it changes
+///
+/// Leaves are looked up directly, while containers are found by scanning
+/// every key, see [`FlattenedDocument::update_from_patch`] for the costs.
+/// Entries keep their relative order, which matters with serde_json's
+/// `preserve_order` feature: removals and renames rebuild the map instead of
+/// using `Map::remove`, which would move the last entry into the gap.
+struct Patcher<'a> {
+    entries: &'a mut Map<String, Value>,
+    /// The value before the patch of every key touched so far
+    original: HashMap<String, Option<Value>>,
+}
+
+impl<'a> Patcher<'a> {
+    fn new(entries: &'a mut Map<String, Value>) -> Self {
+        Patcher {
+            entries,
+            original: HashMap::new(),
+        }
+    }
+
+    fn apply(&mut self, operation: Operation<'_>) -> Result<(), PatchError> {
+        match operation {
+            Operation::Add { path, value } => self.add(path, Flattener::new().flatten_map(value)),
+            Operation::Remove { path } => self.remove(path),
+            Operation::Replace { path, value } => {
+                let value = Flattener::new().flatten_map(value);
+                self.require(path)?;
+                self.replace_subtree(path, value);
+                Ok(())
+            }
+            Operation::Move { from, path } => {
+                if from == path {
+                    return self.require(from);
+                }
+                if pointer::starts_with(path, from) {
+                    return Err(PatchError::MoveIntoDescendant {
+                        from: from.to_owned(),
+                        path: path.to_owned(),
+                    });
+                }
+                let value = self.subtree(from)?;
+                self.remove(from)?;
+                self.add(path, value)
+            }
+            Operation::Copy { from, path } => {
+                let value = self.subtree(from)?;
+                self.add(path, value)
+            }
+            Operation::Test { path, value } => {
+                // entries taken over through `TryFrom` may not rebuild, e.g. arrays with gaps
+                let actual = unflatten_map(&self.subtree(path)?, &UnflattenOptions::default());
+                if actual.as_ref() == Ok(value) {
+                    Ok(())
+                } else {
+                    Err(PatchError::TestFailed {
+                        pointer: path.to_owned(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// Add the flattened `value` at `path`, shifting array elements as needed
+    fn add(&mut self, path: &str, value: Map<String, Value>) -> Result<(), PatchError> {
+        let parent = match pointer::parent(path) {
+            Some(parent) => parent,
+            None => {
+                self.replace_subtree("", value);
+                return Ok(());
+            }
+        };
+
+        match self.entries.get(parent) {
+            Some(Value::Array(_)) => {
+                let segment = &path[parent.len() + 1..];
+                let len = self.array_len(parent);
+                let idx = match segment {
+                    "-" => len,
+                    _ => match array_index(segment) {
+                        Some(idx) if idx <= len => idx,
+                        _ => return Err(invalid_index(path)),
+                    },
+                };
+                self.shift(parent, idx, len, true);
+                self.insert_subtree(&format!("{}/{}", parent, idx), value);
+            }
+            Some(container) if is_container(container) => self.replace_subtree(path, value),
+            Some(_) => {
+                return Err(PatchError::NotAContainer {
+                    pointer: path.to_owned(),
+                })
+            }
+            None => {
+                return Err(PatchError::NotFound {
+                    pointer: path.to_owned(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), PatchError> {
+        self.require(path)?;
+        match pointer::parent(path) {
+            Some(parent) if matches!(self.entries.get(parent), Some(Value::Array(_))) => {
+                let idx =
+                    array_index(&path[parent.len() + 1..]).ok_or_else(|| invalid_index(path))?;
+                let len = self.array_len(parent);
+                let keys = self.keys_under(path);
+                self.remove_keys(&keys);
+                self.shift(parent, idx + 1, len, false);
+            }
+            _ => {
+                let keys = self.keys_under(path);
+                self.remove_keys(&keys);
+            }
+        }
+        Ok(())
+    }
+
+    fn require(&self, path: &str) -> Result<(), PatchError> {
+        if self.entries.contains_key(path) {
+            Ok(())
+        } else {
+            Err(PatchError::NotFound {
+                pointer: path.to_owned(),
+            })
+        }
+    }
+
+    /// The keys at and below `path`, scanning all keys only below a container
+    fn keys_under(&self, path: &str) -> Vec<String> {
+        match self.entries.get(path) {
+            None => Vec::new(),
+            Some(value) if !is_container(value) => vec![path.to_owned()],
+            Some(_) => self
+                .entries
+                .keys()
+                .filter(|key| pointer::starts_with(key, path))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The entries at and below `path`, with `path` cut off their keys
+    fn subtree(&self, path: &str) -> Result<Map<String, Value>, PatchError> {
+        self.require(path)?;
+        Ok(self
+            .keys_under(path)
+            .into_iter()
+            .map(|key| (key[path.len()..].to_owned(), self.entries[&key].clone()))
+            .collect())
+    }
+
+    /// Put the flattened `value` in place of the entries at and below `path`
+    ///
+    /// Keys present before and after are overwritten rather than removed, so
+    /// they keep their place.
+    fn replace_subtree(&mut self, path: &str, value: Map<String, Value>) {
+        let stale: Vec<String> = self
+            .keys_under(path)
+            .into_iter()
+            .filter(|key| !value.contains_key(&key[path.len()..]))
+            .collect();
+        self.remove_keys(&stale);
+        self.insert_subtree(path, value);
+    }
+
+    fn insert_subtree(&mut self, path: &str, value: Map<String, Value>) {
+        for (suffix, value) in value {
+            let key = format!("{}{}", path, suffix);
+            self.save(&key);
+            self.entries.insert(key, value);
+        }
+    }
+
+    /// Number of elements of `array`, found by probing for indices
+    fn array_len(&self, array: &str) -> usize {
+        let has = |idx: usize| self.entries.contains_key(&format!("{}/{}", array, idx));
+        if !has(0) {
+            return 0;
+        }
+        // `low` is an element and `high` is not
+        let (mut low, mut high) = (0, 1);
+        while has(high) {
+            low = high;
+            high *= 2;
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if has(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        high
+    }
+
+    /// Move elements `from..len` of `array` one index up or down
+    fn shift(&mut self, array: &str, from: usize, len: usize, up: bool) {
+        if from >= len {
+            return;
+        }
+        let renamed = |key: &str| {
+            let rest = key.strip_prefix(array)?.strip_prefix('/')?;
+            let segment = rest.split('/').next()?;
+            let idx = array_index(segment).filter(|idx| *idx >= from)?;
+            let idx = if up { idx + 1 } else { idx - 1 };
+            Some(format!("{}/{}{}", array, idx, &rest[segment.len()..]))
+        };
+
+        let elements: Vec<String> = (from..len)
+            .map(|idx| format!("{}/{}", array, idx))
+            .collect();
+        let leaves = elements.iter().all(|key| {
+            self.entries
+                .get(key)
+                .is_some_and(|value| !is_container(value))
+        });
+        let keys = if leaves {
+            elements
+        } else {
+            self.entries
+                .keys()
+                .filter(|key| renamed(key).is_some())
+                .cloned()
+                .collect()
+        };
+
+        let renames: HashMap<String, String> = keys
+            .into_iter()
+            .filter_map(|key| renamed(&key).map(|new| (key, new)))
+            .collect();
+        for (old, new) in &renames {
+            self.save(old);
+            self.save(new);
+        }
+        if cfg!(feature = "preserve_order") {
+            pointer::rebuild_entries(self.entries, |(key, value)| match renames.get(&key) {
+                Some(new) => Some((new.clone(), value)),
+                None => Some((key, value)),
+            });
+        } else {
+            let moved: Vec<(String, Value)> = renames
+                .into_iter()
+                .filter_map(|(old, new)| self.entries.remove(&old).map(|value| (new, value)))
+                .collect();
+            self.entries.extend(moved);
+        }
+    }
+
+    /// Remember the value of `key` before its first change
+    fn save(&mut self, key: &str) {
+        if !self.original.contains_key(key) {
+            let value = self.entries.get(key).cloned();
+            self.original.insert(key.to_owned(), value);
+        }
+    }
+
+    fn remove_keys(&mut self, keys: &[String]) {
+        for key in keys {
+            self.save(key);
+        }
+        remove_keys(self.entries, keys);
+    }
+
+    /// Put back the value every touched key had before the patch
+    fn restore(&mut self) {
+        let mut removed = Vec::new();
+        for (key, value) in mem::take(&mut self.original) {
+            match value {
+                Some(value) => {
+                    self.entries.insert(key, value);
+                }
+                None => removed.push(key),
+            }
+        }
+        remove_keys(self.entries, &removed);
+    }
+
+    /// The touched keys whose value differs from before the patch
+    fn report(self) -> UpdateReport {
+        let mut report = UpdateReport::default();
+        for (key, old) in self.original {
+            match (old, self.entries.get(&key)) {
+                (None, Some(_)) => report.added.push(key),
+                (Some(_), None) => report.removed.push(key),
+                (Some(old), Some(new)) if old != *new => report.changed.push(key),
+                _ => {}
+            }
+        }
+        let sort = |pointers: &mut Vec<String>| pointers.sort_by(|a, b| pointer::compare(a, b));
+        sort(&mut report.added);
+        sort(&mut report.removed);
+        sort(&mut report.changed);
+        report
+    }
+}
+
+fn remove_keys(entries: &mut Map<String, Value>, keys: &[String]) {
+    if keys.is_empty() {
+        return;
+    }
+    if cfg!(feature = "preserve_order") {
+        let keys: HashSet<&str> = keys.iter().map(String::as_str).collect();
+        pointer::rebuild_entries(entries, |(key, value)| {
+            (!keys.contains(key.as_str())).then_some((key, value))
+        });
+    } else {
+        for key in keys {
+            entries.remove(key);
+        }
+    }
+}
+
+fn array_index(segment: &str) -> Option<usize> {
+    if is_array_index(segment) {
+        segment.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn invalid_index(path: &str) -> PatchError {
+    PatchError::InvalidIndex {
+        pointer: path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Rng;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    /// Apply to the flattened form and compare with the expected nested document
+    fn check(doc: Value, patch: Value, expected: Value) {
+        let mut flat = FlattenedDocument::from(&doc);
+
+        flat.apply_patch(&patch).unwrap();
+
+        assert_eq!(flat, FlattenedDocument::from(&expected));
+    }
+
+    fn check_error(doc: Value, patch: Value, expected: PatchError) {
+        let mut flat = FlattenedDocument::from(&doc);
+
+        assert_eq!(flat.apply_patch(&patch), Err(expected));
+        assert_eq!(flat, FlattenedDocument::from(&doc));
+    }
+
+    #[test]
+    fn rfc_add() {
+        // A.1, A.2, A.10 and A.16
+        check(
+            json!({ "foo": "bar" }),
+            json!([{ "op": "add", "path": "/baz", "value": "qux" }]),
+            json!({ "baz": "qux", "foo": "bar" }),
+        );
+        check(
+            json!({ "foo": ["bar", "baz"] }),
+            json!([{ "op": "add", "path": "/foo/1", "value": "qux" }]),
+            json!({ "foo": ["bar", "qux", "baz"] }),
+        );
+        check(
+            json!({ "foo": "bar" }),
+            json!([{ "op": "add", "path": "/child", "value": { "grandchild": {} } }]),
+            json!({ "foo": "bar", "child": { "grandchild": {} } }),
+        );
+        check(
+            json!({ "foo": ["bar"] }),
+            json!([{ "op": "add", "path": "/foo/-", "value": ["abc", "def"] }]),
+            json!({ "foo": ["bar", ["abc", "def"]] }),
+        );
+    }
+
+    #[test]
+    fn rfc_remove_and_replace() {
+        // A.3, A.4 and A.5
+        check(
+            json!({ "baz": "qux", "foo": "bar" }),
+            json!([{ "op": "remove", "path": "/baz" }]),
+            json!({ "foo": "bar" }),
+        );
+        check(
+            json!({ "foo": ["bar", "qux", "baz"] }),
+            json!([{ "op": "remove", "path": "/foo/1" }]),
+            json!({ "foo": ["bar", "baz"] }),
+        );
+        check(
+            json!({ "baz": "qux", "foo": "bar" }),
+            json!([{ "op": "replace", "path": "/baz", "value": "boo" }]),
+            json!({ "baz": "boo", "foo": "bar" }),
+        );
+    }
+
+    #[test]
+    fn rfc_move() {
+        // A.6 and A.7
+        check(
+            json!({
+                "foo": { "bar": "baz", "waldo": "fred" },
+                "qux": { "corge": "grault" }
+            }),
+            json!([{ "op": "move", "from": "/foo/waldo", "path": "/qux/thud" }]),
+            json!({
+                "foo": { "bar": "baz" },
+                "qux": { "corge": "grault", "thud": "fred" }
+            }),
+        );
+        check(
+            json!({ "foo": ["all", "grass", "cows", "eat"] }),
+            json!([{ "op": "move", "from": "/foo/1", "path": "/foo/3" }]),
+            json!({ "foo": ["all", "cows", "eat", "grass"] }),
+        );
+    }
+
+    #[test]
+    fn rfc_test() {
+        // A.8, A.9, A.14 and A.15
+        check(
+            json!({ "baz": "qux", "foo": ["a", 2, "c"] }),
+            json!([
+                { "op": "test", "path": "/baz", "value": "qux" },
+                { "op": "test", "path": "/foo/1", "value": 2 }
+            ]),
+            json!({ "baz": "qux", "foo": ["a", 2, "c"] }),
+        );
+        check_error(
+            json!({ "baz": "qux" }),
+            json!([{ "op": "test", "path": "/baz", "value": "bar" }]),
+            PatchError::TestFailed {
+                pointer: "/baz".to_owned(),
+            },
+        );
+        check(
+            json!({ "/": 9, "~1": 10 }),
+            json!([{ "op": "test", "path": "/~01", "value": 10 }]),
+            json!({ "/": 9, "~1": 10 }),
+        );
+        check_error(
+            json!({ "/": 9, "~1": 10 }),
+            json!([{ "op": "test", "path": "/~01", "value": "10" }]),
+            PatchError::TestFailed {
+                pointer: "/~01".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_fails_on_entries_that_do_not_unflatten() {
+        let entries = json!({ "": {}, "/a": [], "/a/1": 1 });
+        let mut doc = FlattenedDocument::try_from(entries.as_object().unwrap().clone()).unwrap();
+
+        assert_eq!(
+            doc.apply_patch(&json!([{ "op": "test", "path": "/a", "value": [1] }])),
+            Err(PatchError::TestFailed {
+                pointer: "/a".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn rfc_errors() {
+        // A.11 and A.12
+        check(
+            json!({ "foo": "bar" }),
+            json!([{ "op": "add", "path": "/baz", "value": "qux", "xyz": 123 }]),
+            json!({ "foo": "bar", "baz": "qux" }),
+        );
+        check_error(
+            json!({ "foo": "bar" }),
+            json!([{ "op": "add", "path": "/baz/bat", "value": "qux" }]),
+            PatchError::NotFound {
+                pointer: "/baz/bat".to_owned(),
+            },
+        );
+    }
+
+    #[test]
+    fn copy_whole_subtree_and_test_container() {
+        check(
+            json!({ "a": { "b": [1, { "c": 2 }] }, "d": [] }),
+            json!([
+                { "op": "copy", "from": "/a", "path": "/d/0" },
+                { "op": "test", "path": "/d", "value": [{ "b": [1, { "c": 2 }] }] },
+                { "op": "replace", "path": "", "value": { "d": [] } },
+                { "op": "add", "path": "/d/0", "value": 1 },
+                { "op": "add", "path": "/d/0", "value": 0 }
+            ]),
+            json!({ "d": [0, 1] }),
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn entries_keep_their_order() {
+        let value: Value =
+            serde_json::from_str(r#"{ "z": [1, 2, 3], "b": { "y": 1, "x": 2 } }"#).unwrap();
+        let mut doc = FlattenedDocument::from(&value);
+
+        doc.apply_patch(&json!([
+            { "op": "remove", "path": "/z/0" },
+            { "op": "remove", "path": "/b/y" }
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            doc.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            vec!["", "/z", "/z/0", "/z/1", "/b", "/b/x"]
+        );
+        assert_eq!(
+            serde_json::to_string(&doc.unflatten().unwrap()).unwrap(),
+            r#"{"z":[2,3],"b":{"x":2}}"#
+        );
+    }
+
+    #[test]
+    fn failures_roll_back_earlier_operations() {
+        let doc = json!({ "items": ["a", { "b": 1 }, "c"], "x": 1 });
+
+        check_error(
+            doc.clone(),
+            json!([
+                { "op": "remove", "path": "/items/0" },
+                { "op": "move", "from": "/x", "path": "/items/9" }
+            ]),
+            PatchError::InvalidIndex {
+                pointer: "/items/9".to_owned(),
+            },
+        );
+        check_error(
+            doc.clone(),
+            json!([
+                { "op": "add", "path": "/items/1", "value": [] },
+                { "op": "add", "path": "/x/y", "value": 2 }
+            ]),
+            PatchError::NotAContainer {
+                pointer: "/x/y".to_owned(),
+            },
+        );
+        check_error(
+            doc.clone(),
+            json!([{ "op": "move", "from": "/items", "path": "/items/0" }]),
+            PatchError::MoveIntoDescendant {
+                from: "/items".to_owned(),
+                path: "/items/0".to_owned(),
+            },
+        );
+        check_error(
+            doc.clone(),
+            json!([{ "op": "remove", "path": "/items/01" }]),
+            PatchError::NotFound {
+                pointer: "/items/01".to_owned(),
+            },
+        );
+        check_error(
+            doc,
+            json!([{ "op": "frobnicate", "path": "" }]),
+            PatchError::InvalidOperation("operation 0 has an unknown \"op\"".to_owned()),
+        );
+    }
+
+    /// A random operation on pointers of `doc`, valid or not
+    fn random_operation(rng: &mut Rng, doc: &Value, pointers: &[String]) -> Value {
+        let pick = |rng: &mut Rng| pointers[rng.below(pointers.len() as u64) as usize].clone();
+        let existing = pick(rng);
+        // removing the root isn't defined, and json-patch rejects it
+        let removable = if existing.is_empty() {
+            "/a".to_owned()
+        } else {
+            existing.clone()
+        };
+        let target = {
+            let base = pick(rng);
+            match rng.below(4) {
+                0 => base,
+                1 => format!("{}/-", base),
+                2 => format!("{}/{}", base, rng.below(4)),
+                _ => format!(
+                    "{}/{}",
+                    base,
+                    pointer::escape(["a", "b", "a/b", "~"][rng.below(4) as usize])
+                ),
+            }
+        };
+        match rng.below(6) {
+            0 => json!({ "op": "add", "path": target, "value": rng.value(2) }),
+            1 => json!({ "op": "remove", "path": removable }),
+            2 => json!({ "op": "replace", "path": existing, "value": rng.value(2) }),
+            3 => json!({ "op": "move", "from": removable, "path": target }),
+            4 => json!({ "op": "copy", "from": existing, "path": target }),
+            _ => {
+                let value = match rng.below(2) {
+                    0 => doc.pointer(&existing).cloned().unwrap_or(Value::Null),
+                    _ => rng.value(1),
+                };
+                json!({ "op": "test", "path": existing, "value": value })
+            }
+        }
+    }
+
+    /// The report describing the differences between two flattened documents
+    fn expected_report(before: &FlattenedDocument, after: &FlattenedDocument) -> UpdateReport {
+        let mut report = UpdateReport::default();
+        for (key, value) in before {
+            match after.get(key) {
+                None => report.removed.push(key.to_owned()),
+                Some(new) if new != value => report.changed.push(key.to_owned()),
+                Some(_) => {}
+            }
+        }
+        for (key, _) in after {
+            if before.get(key).is_none() {
+                report.added.push(key.to_owned());
+            }
+        }
+        let sort = |pointers: &mut Vec<String>| pointers.sort_by(|a, b| pointer::compare(a, b));
+        sort(&mut report.added);
+        sort(&mut report.removed);
+        sort(&mut report.changed);
+        report
+    }
+
+    #[test]
+    fn update_matches_flattening_the_patched_document() {
+        let mut rng = Rng(0x5851_f42d_4c95_7f2d);
+
+        for _ in 0..2000 {
+            let doc = rng.value(4);
+            let before = FlattenedDocument::from(&doc);
+            let pointers: Vec<String> = before.iter().map(|(key, _)| key.to_owned()).collect();
+            let patch: Value = (0..1 + rng.below(3))
+                .map(|_| random_operation(&mut rng, &doc, &pointers))
+                .collect();
+
+            // one operation at a time, as json-patch panics rolling back some moves
+            let mut nested = doc.clone();
+            let expected = patch.as_array().unwrap().iter().try_for_each(|operation| {
+                let parsed: json_patch::Patch = serde_json::from_value(json!([operation])).unwrap();
+                json_patch::patch(&mut nested, &parsed)
+            });
+            let mut flat = before.clone();
+            let result = flat.update_from_patch(&patch);
+            let mut applied = before.clone();
+            let applied_result = applied.apply_patch(&patch);
+
+            match (expected, result) {
+                (Ok(()), Ok(report)) => {
+                    assert_eq!(flat, FlattenedDocument::from(&nested), "{} {}", doc, patch);
+                    assert_eq!(report, expected_report(&before, &flat), "{} {}", doc, patch);
+                    assert_eq!(applied_result, Ok(()));
+                    assert_eq!(applied, flat);
+                }
+                (Err(_), Err(error)) => {
+                    assert_eq!(flat, before, "{} {}", doc, patch);
+                    assert_eq!(applied_result, Err(error));
+                    assert_eq!(applied, before);
+                }
+                (expected, result) => panic!(
+                    "{} {}: expected {:?}, got {:?}",
+                    doc, patch, expected, result
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn report_leaves_out_entries_changed_back() {
+        let mut doc = FlattenedDocument::from(&json!({ "a": [1, { "b": 2 }], "c": 3 }));
+
+        let report = doc
+            .update_from_patch(&json!([
+                { "op": "replace", "path": "/c", "value": 4 },
+                { "op": "replace", "path": "/c", "value": 3 },
+                { "op": "move", "from": "/a/1", "path": "/d" }
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            report,
+            UpdateReport {
+                added: vec!["/d".to_owned(), "/d/b".to_owned()],
+                removed: vec!["/a/1".to_owned(), "/a/1/b".to_owned()],
+                changed: vec![],
+            }
+        );
+        assert_eq!(report.len(), 4);
+        assert!(doc
+            .update_from_patch(&json!([{ "op": "test", "path": "/c", "value": 3 }]))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::diff::{add_op, remove_op, replace_op};
+use crate::{diff, pointer};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+
+/// Options for [`json_patch_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchOptions {
+    detect_moves: bool,
+    detect_copies: bool,
+    min_value_bytes: usize,
+}
+
+impl Default for PatchOptions {
+    fn default() -> Self {
+        PatchOptions {
+            detect_moves: false,
+            detect_copies: false,
+            min_value_bytes: 32,
+        }
+    }
+}
+
+impl PatchOptions {
+    /// Create options producing the same patch as [`FlatDiff::to_json_patch`](crate::FlatDiff::to_json_patch)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn an added value equal to a removed one into a `move`
+    pub fn detect_moves(mut self, detect: bool) -> Self {
+        self.detect_moves = detect;
+        self
+    }
+
+    /// Turn an added value equal to a value both documents share unchanged into a `copy`
+    ///
+    /// This indexes every unchanged subtree of the old document above
+    /// [`PatchOptions::min_value_bytes`], so it costs a pass and some memory
+    /// proportional to the old document.
+    pub fn detect_copies(mut self, detect: bool) -> Self {
+        self.detect_copies = detect;
+        self
+    }
+
+    /// Only consider values of at least `bytes` as compact JSON for a `move` or `copy`, 32 by default
+    pub fn min_value_bytes(mut self, bytes: usize) -> Self {
+        self.min_value_bytes = bytes;
+        self
+    }
+}
+
+/// Result of [`json_patch_with`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedPatch {
+    /// The RFC 6902 operations
+    pub patch: Value,
+    /// Number of `move` operations emitted
+    pub moves: usize,
+    /// Number of `copy` operations emitted
+    pub copies: usize,
+    /// How much shorter the patch is as compact JSON than without moves and copies
+    pub bytes_saved: usize,
+}
+
+/// How an added subtree is produced
+enum Source<'a> {
+    /// Added entry by entry
+    Add,
+    /// Moved from the removal at this index
+    Move(usize),
+    /// Copied from this untouched pointer
+    Copy(&'a str),
+}
+
+/// Generate an [RFC 6902](https://tools.ietf.org/html/rfc6902) JSON Patch taking `old` to `new`
+///
+/// Without detection enabled this is [`FlatDiff::to_json_patch`](crate::FlatDiff::to_json_patch)
+/// of [`diff`](crate::diff). With it, every added subtree that is equal to a
+/// removed subtree, or to an unchanged one for copies, and at least
+/// [`PatchOptions::min_value_bytes`] long becomes a single `move` or `copy`
+/// instead of a removal and one addition per entry. Only whole added and
+/// removed subtrees are matched: arrays are compared by index, so elements
+/// that are reordered in place show up as changes and are not detected.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::PatchOptions;
+/// use serde_json::json;
+///
+/// let old = json!({ "v1": { "host": "localhost", "port": 8080 } });
+/// let new = json!({ "v2": { "host": "localhost", "port": 8080 } });
+///
+/// let options = PatchOptions::new().detect_moves(true).min_value_bytes(8);
+/// let generated = jsonpointer_flatten::json_patch_with(&old, &new, &options);
+///
+/// assert_eq!(generated.patch, json!([{ "op": "move", "from": "/v1", "path": "/v2" }]));
+/// assert_eq!(generated.moves, 1);
+/// assert!(generated.bytes_saved > 0);
+/// ```
+pub fn json_patch_with(old: &Value, new: &Value, options: &PatchOptions) -> GeneratedPatch {
+    let diff = diff(old, new);
+    let plan = diff.patch_plan();
+
+    let mut removals: HashMap<u64, Vec<usize>> = HashMap::new();
+    if options.detect_moves {
+        for (idx, ptr) in plan.removes.iter().enumerate() {
+            if let Some(value) = old.pointer(ptr) {
+                let (hash, size) = fingerprint(value, &mut String::new(), &mut |_, _, _| {});
+                if size >= options.min_value_bytes {
+                    removals.entry(hash).or_default().push(idx);
+                }
+            }
+        }
+    }
+    let mut untouched: HashMap<u64, Vec<String>> = HashMap::new();
+    if options.detect_copies {
+        // a subtree is touched when a diff entry is at or below it, or an entry
+        // the patch adds again below a replaced container
+        let mut touched = HashSet::new();
+        let pointers = diff.entries().iter().map(|entry| entry.pointer());
+        for ptr in pointers.chain(diff.retained_pointers()) {
+            let mut ptr = Some(ptr);
+            while let Some(p) = ptr.filter(|p| touched.insert(*p)) {
+                ptr = pointer::parent(p);
+            }
+        }
+        fingerprint(old, &mut String::new(), &mut |ptr, hash, size| {
+            if size >= options.min_value_bytes && !touched.contains(ptr) {
+                untouched.entry(hash).or_default().push(ptr.to_owned());
+            }
+        });
+    }
+
+    let mut moved = vec![false; plan.removes.len()];
+    // additions by the subtree they build, each tried as a whole before its children
+    let mut planned: Vec<(&[(&str, &Value)], Source)> = Vec::new();
+    let mut pending = subtrees(&plan.adds);
+    pending.reverse();
+    while let Some(adds) = pending.pop() {
+        let root = adds[0].0;
+        let value = match new.pointer(root) {
+            Some(value) if !removals.is_empty() || !untouched.is_empty() => value,
+            _ => {
+                planned.push((adds, Source::Add));
+                continue;
+            }
+        };
+        let (hash, size) = fingerprint(value, &mut String::new(), &mut |_, _, _| {});
+        if size < options.min_value_bytes {
+            planned.push((adds, Source::Add));
+            continue;
+        }
+        let added = adds.iter().map(|(p, v)| len(&add_op(p, v))).sum::<usize>();
+
+        let removal = removals.get(&hash).and_then(|candidates| {
+            candidates
+                .iter()
+                .copied()
+                .find(|idx| !moved[*idx] && old.pointer(plan.removes[*idx]) == Some(value))
+        });
+        let copy = untouched.get(&hash).and_then(|candidates| {
+            candidates
+                .iter()
+                .find(|ptr| old.pointer(ptr) == Some(value))
+        });
+        match (removal, copy) {
+            (Some(idx), _)
+                if len(&move_op(plan.removes[idx], root))
+                    < added + len(&remove_op(plan.removes[idx])) =>
+            {
+                moved[idx] = true;
+                planned.push((adds, Source::Move(idx)));
+            }
+            (_, Some(from)) if len(&copy_op(from, root)) < added => {
+                planned.push((adds, Source::Copy(from)));
+            }
+            _ => {
+                planned.push((&adds[..1], Source::Add));
+                let mut children = subtrees(&adds[1..]);
+                children.reverse();
+                pending.extend(children);
+            }
+        }
+    }
+
+    let mut operations = Vec::new();
+    // compact length and count of the operations without moves and copies
+    let (mut unoptimized, mut count) = (0, 0);
+    // moved elements of a shrinking array stay at its end until they are moved
+    let mut deferred: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, ptr) in plan.removes.iter().enumerate().rev() {
+        unoptimized += len(&remove_op(ptr));
+        count += 1;
+        if !moved[idx] {
+            operations.push(remove_op(ptr));
+        } else if let Some((parent, idx)) = array_element(old, ptr) {
+            deferred.entry(parent).or_default().push(idx);
+        }
+    }
+    for remaining in deferred.values_mut() {
+        remaining.sort_unstable();
+    }
+    for (ptr, value) in &plan.replaces {
+        unoptimized += len(&replace_op(ptr, value));
+        count += 1;
+        operations.push(replace_op(ptr, value));
+    }
+
+    let (mut moves, mut copies) = (0, 0);
+    for (adds, source) in planned {
+        let root = adds[0].0;
+        for (ptr, value) in adds {
+            let op = add_op(ptr, value);
+            unoptimized += len(&op);
+            count += 1;
+            if let Source::Add = source {
+                operations.push(op);
+            }
+        }
+        match source {
+            Source::Add => {}
+            Source::Move(idx) => {
+                let from = plan.removes[idx];
+                let from = match array_element(old, from) {
+                    Some((parent, original)) => {
+                        let remaining = deferred.entry(parent).or_default();
+                        let rank = remaining.iter().position(|i| *i == original).unwrap_or(0);
+                        remaining.remove(rank);
+                        let kept = new
+                            .pointer(parent)
+                            .and_then(Value::as_array)
+                            .map_or(0, Vec::len);
+                        format!("{}/{}", parent, kept + rank)
+                    }
+                    None => from.to_owned(),
+                };
+                operations.push(move_op(&from, root));
+                moves += 1;
+            }
+            Source::Copy(from) => {
+                operations.push(copy_op(from, root));
+                copies += 1;
+            }
+        }
+    }
+
+    let patch = Value::Array(operations);
+    // brackets and commas around the operations
+    let unoptimized = unoptimized + 1 + count.max(1);
+    let bytes_saved = unoptimized.saturating_sub(len(&patch));
+    GeneratedPatch {
+        patch,
+        moves,
+        copies,
+        bytes_saved,
+    }
+}
+
+/// Split additions in document order into the subtrees they build
+fn subtrees<'a, 'p>(adds: &'a [(&'p str, &'p Value)]) -> Vec<&'a [(&'p str, &'p Value)]> {
+    let mut subtrees = Vec::new();
+    let mut start = 0;
+    for (idx, (ptr, _)) in adds.iter().enumerate() {
+        if idx > start && !pointer::starts_with(ptr, adds[start].0) {
+            subtrees.push(&adds[start..idx]);
+            start = idx;
+        }
+    }
+    if start < adds.len() {
+        subtrees.push(&adds[start..]);
+    }
+    subtrees
+}
+
+fn move_op(from: &str, ptr: &str) -> Value {
+    json!({ "op": "move", "from": from, "path": ptr })
+}
+
+fn copy_op(from: &str, ptr: &str) -> Value {
+    json!({ "op": "copy", "from": from, "path": ptr })
+}
+
+/// Compact JSON length of a value
+pub(crate) fn len(value: &Value) -> usize {
+    serde_json::to_string(value).map_or(0, |text| text.len())
+}
+
+/// The parent array and index of `ptr`, if its parent is an array in `doc`
+fn array_element<'p>(doc: &Value, ptr: &'p str) -> Option<(&'p str, usize)> {
+    let parent = pointer::parent(ptr)?;
+    doc.pointer(parent)?.as_array()?;
+    ptr[parent.len() + 1..]
+        .parse()
+        .ok()
+        .map(|idx| (parent, idx))
+}
+
+/// Hash and compact JSON length of `value`, calling `visit` for every node below and at it
+///
+/// Equal values hash the same, object members in any order.
+pub(crate) fn fingerprint(
+    value: &Value,
+    pointer: &mut String,
+    visit: &mut dyn FnMut(&str, u64, usize),
+) -> (u64, usize) {
+    let mut hasher = DefaultHasher::new();
+    let parent_len = pointer.len();
+    let size = match value {
+        Value::Array(arr) => {
+            4u8.hash(&mut hasher);
+            let mut size = 1 + arr.len().max(1);
+            for (idx, val) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                let (hash, val_size) = fingerprint(val, pointer, visit);
+                pointer.truncate(parent_len);
+                hash.hash(&mut hasher);
+                size += val_size;
+            }
+            size
+        }
+        Value::Object(obj) => {
+            5u8.hash(&mut hasher);
+            let mut size = 1 + obj.len().max(1);
+            let mut members = 0u64;
+            for (key, val) in obj {
+                pointer.push('/');
+                pointer::escape_into(key, pointer);
+                let (hash, val_size) = fingerprint(val, pointer, visit);
+                pointer.truncate(parent_len);
+                let mut member = DefaultHasher::new();
+                key.hash(&mut member);
+                hash.hash(&mut member);
+                members = members.wrapping_add(member.finish());
+                size += len(&Value::from(key.as_str())) + 1 + val_size;
+            }
+            members.hash(&mut hasher);
+            size
+        }
+        leaf => {
+            let text = serde_json::to_string(leaf).unwrap_or_default();
+            text.hash(&mut hasher);
+            text.len()
+        }
+    };
+    let hash = hasher.finish();
+    visit(pointer, hash, size);
+    (hash, size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Apply `patch` with an independent RFC 6902 implementation
+    fn apply(doc: &Value, patch: &Value) -> Value {
+        let patch: json_patch::Patch = serde_json::from_value(patch.clone()).unwrap();
+        let mut doc = doc.clone();
+        json_patch::patch(&mut doc, &patch).unwrap();
+        doc
+    }
+
+    fn options() -> PatchOptions {
+        PatchOptions::new()
+            .detect_moves(true)
+            .detect_copies(true)
+            .min_value_bytes(16)
+    }
+
+    fn record(id: u64) -> Value {
+        json!({ "id": id, "name": format!("record {}", id), "tags": ["a", "b"] })
+    }
+
+    #[test]
+    fn moves_and_copies_reach_the_same_end_state() {
+        let cases = [
+            (
+                json!({ "config_v1": record(1), "other": 1 }),
+                json!({ "config_v2": record(1), "other": 2 }),
+            ),
+            (
+                json!({ "done": [], "queue": [1, 2, record(3), record(4), 5] }),
+                json!({ "done": [record(4), 5, record(3)], "queue": [1, 2] }),
+            ),
+            (
+                json!({ "defaults": record(1), "users": [{ "id": 1 }] }),
+                json!({ "defaults": record(1), "users": [{ "id": 1 }, { "settings": record(1) }] }),
+            ),
+            (
+                json!({ "a": [[record(1), record(2)], [record(3)]] }),
+                json!({ "a": [[record(2)]], "b": { "x": record(1), "y": [record(3), record(2)] } }),
+            ),
+        ];
+
+        for (old, new) in &cases {
+            let plain = diff(old, new).to_json_patch();
+            assert_eq!(&apply(old, &plain), new, "plain {}", plain);
+
+            let generated = json_patch_with(old, new, &options());
+            assert_eq!(
+                &apply(old, &generated.patch),
+                new,
+                "optimized {}",
+                generated.patch
+            );
+            assert!(
+                generated.moves + generated.copies > 0,
+                "{}",
+                generated.patch
+            );
+            assert_eq!(
+                generated.bytes_saved,
+                len(&plain) - len(&generated.patch),
+                "{}",
+                generated.patch
+            );
+        }
+    }
+
+    #[test]
+    fn object_turned_array_keeps_unchanged_elements() {
+        let old = json!({ "x": { "0": record(1), "1": 2 } });
+        let new = json!({ "x": [record(1), 2, 3], "y": record(1) });
+
+        for options in &[PatchOptions::new(), options()] {
+            let generated = json_patch_with(&old, &new, options);
+            assert_eq!(apply(&old, &generated.patch), new, "{}", generated.patch);
+        }
+    }
+
+    #[test]
+    fn elements_moved_out_of_a_shrinking_array() {
+        let old = json!({ "done": [], "queue": [1, record(2), record(3)] });
+        let new = json!({ "done": [record(3), record(2)], "queue": [1] });
+
+        let generated = json_patch_with(&old, &new, &options());
+
+        assert_eq!(
+            generated.patch,
+            json!([
+                { "op": "move", "from": "/queue/2", "path": "/done/0" },
+                { "op": "move", "from": "/queue/1", "path": "/done/1" }
+            ])
+        );
+        assert_eq!(generated.moves, 2);
+    }
+
+    #[test]
+    fn copy_of_untouched_value() {
+        let old = json!({ "defaults": record(1) });
+        let new = json!({ "defaults": record(1), "profile": record(1) });
+
+        let generated = json_patch_with(&old, &new, &options());
+
+        assert_eq!(
+            generated.patch,
+            json!([{ "op": "copy", "from": "/defaults", "path": "/profile" }])
+        );
+        assert_eq!(generated.copies, 1);
+    }
+
+    #[test]
+    fn small_values_and_disabled_detection_are_added() {
+        let old = json!({ "a": [1, 2] });
+        let new = json!({ "b": [1, 2] });
+
+        let generated = json_patch_with(&old, &new, &options().min_value_bytes(64));
+        assert_eq!(generated.patch, diff(&old, &new).to_json_patch());
+        assert_eq!(generated.bytes_saved, 0);
+
+        let generated = json_patch_with(&old, &new, &PatchOptions::new());
+        assert_eq!(generated.patch, diff(&old, &new).to_json_patch());
+        assert_eq!((generated.moves, generated.copies), (0, 0));
+    }
+}
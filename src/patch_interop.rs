@@ -0,0 +1,255 @@
+use crate::error::{PatchError, PointerError};
+use crate::patch::{Operation, ParsedOperation};
+use crate::{pointer, FlatDiff, FlattenedDocument, GeneratedPatch, UpdateReport};
+use json_patch::{AddOperation, Patch, PatchOperation, RemoveOperation, ReplaceOperation};
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+impl From<&FlatDiff> for Patch {
+    /// The operations of [`FlatDiff::to_json_patch`], built without going through a [`Value`](serde_json::Value)
+    fn from(diff: &FlatDiff) -> Self {
+        let plan = diff.patch_plan();
+        let mut operations: Vec<PatchOperation> = plan
+            .removes
+            .iter()
+            .rev()
+            .map(|ptr| {
+                PatchOperation::Remove(RemoveOperation {
+                    path: (*ptr).to_owned(),
+                })
+            })
+            .collect();
+        operations.extend(plan.replaces.iter().map(|(ptr, value)| {
+            PatchOperation::Replace(ReplaceOperation {
+                path: (*ptr).to_owned(),
+                value: (*value).clone(),
+            })
+        }));
+        operations.extend(plan.adds.iter().map(|(ptr, value)| {
+            PatchOperation::Add(AddOperation {
+                path: (*ptr).to_owned(),
+                value: (*value).clone(),
+            })
+        }));
+        Patch(operations)
+    }
+}
+
+impl TryFrom<&GeneratedPatch> for Patch {
+    type Error = PatchError;
+
+    /// The operations of [`GeneratedPatch::patch`], `move` and `copy` included
+    ///
+    /// Only fails when the patch was edited into something that is not an
+    /// array of operations.
+    fn try_from(generated: &GeneratedPatch) -> Result<Self, Self::Error> {
+        let operations = generated.patch.as_array().ok_or_else(|| {
+            PatchError::InvalidOperation("patch must be an array of operations".to_owned())
+        })?;
+        operations
+            .iter()
+            .enumerate()
+            .map(|(idx, operation)| {
+                PatchOperation::deserialize(operation).map_err(|error| {
+                    PatchError::InvalidOperation(format!("operation {}: {}", idx, error))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Patch)
+    }
+}
+
+impl FlattenedDocument {
+    /// Apply a json-patch [`Patch`], see [`FlattenedDocument::apply_patch`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// let old = json!({ "tags": ["a"], "title": "x" });
+    /// let new = json!({ "tags": ["a", "b"] });
+    /// let patch = json_patch::Patch::from(&jsonpointer_flatten::diff(&old, &new));
+    ///
+    /// let mut doc = FlattenedDocument::from(&old);
+    /// doc.apply_json_patch(&patch).unwrap();
+    ///
+    /// assert_eq!(doc, FlattenedDocument::from(&new));
+    /// ```
+    pub fn apply_json_patch(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        self.apply_operations(patch.0.iter().map(operation))
+    }
+
+    /// Apply a json-patch [`Patch`], see [`FlattenedDocument::update_from_patch`]
+    pub fn update_from_json_patch(&mut self, patch: &Patch) -> Result<UpdateReport, PatchError> {
+        self.update_from_operations(patch.0.iter().map(operation))
+    }
+}
+
+fn operation(operation: &PatchOperation) -> ParsedOperation<'_> {
+    fn valid(ptr: &str) -> Result<&str, PointerError> {
+        pointer::validate(ptr).map(|()| ptr)
+    }
+
+    Ok(match operation {
+        PatchOperation::Add(op) => Operation::Add {
+            path: valid(&op.path)?,
+            value: &op.value,
+        },
+        PatchOperation::Remove(op) => Operation::Remove {
+            path: valid(&op.path)?,
+        },
+        PatchOperation::Replace(op) => Operation::Replace {
+            path: valid(&op.path)?,
+            value: &op.value,
+        },
+        PatchOperation::Move(op) => Operation::Move {
+            from: valid(&op.from)?,
+            path: valid(&op.path)?,
+        },
+        PatchOperation::Copy(op) => Operation::Copy {
+            from: valid(&op.from)?,
+            path: valid(&op.path)?,
+        },
+        PatchOperation::Test(op) => Operation::Test {
+            path: valid(&op.path)?,
+            value: &op.value,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{diff, json_patch_with, PatchOptions};
+    use json_patch::{CopyOperation, MoveOperation, TestOperation};
+    use serde_json::{json, Value};
+
+    fn apply(value: &Value, patch: &Patch) -> Value {
+        let mut value = value.clone();
+        json_patch::patch(&mut value, patch).unwrap();
+        value
+    }
+
+    #[test]
+    fn diffs_apply_with_json_patch() {
+        let old = json!({ "a": [1, 2, 3], "b": { "c": null }, "d": "x" });
+        let new = json!({ "a": [1], "b": [{ "c": true }], "e": { "f": [] } });
+        let diff = diff(&old, &new);
+
+        let patch = Patch::from(&diff);
+
+        assert_eq!(serde_json::to_value(&patch).unwrap(), diff.to_json_patch());
+        assert_eq!(apply(&old, &patch), new);
+    }
+
+    #[test]
+    fn kind_changes_keep_unchanged_children_with_json_patch() {
+        let old = json!({ "a": { "0": "x", "1": { "b": null } } });
+        let new = json!({ "a": ["x", { "b": null }, 1] });
+        let diff = diff(&old, &new);
+
+        assert_eq!(apply(&old, &Patch::from(&diff)), new);
+        assert_eq!(apply(&new, &Patch::from(&diff.invert())), old);
+    }
+
+    #[test]
+    fn generated_moves_and_copies_apply_with_json_patch() {
+        let shared = json!({ "host": "localhost", "port": 8080 });
+        let old = json!({ "v1": shared, "keep": shared, "list": [shared, 1] });
+        let new = json!({ "v2": shared, "keep": shared, "also": shared, "list": [1] });
+        let options = PatchOptions::new()
+            .detect_moves(true)
+            .detect_copies(true)
+            .min_value_bytes(8);
+        let generated = json_patch_with(&old, &new, &options);
+
+        let patch = Patch::try_from(&generated).unwrap();
+
+        let count =
+            |kind: fn(&PatchOperation) -> bool| patch.0.iter().filter(|op| kind(op)).count();
+        assert_eq!(
+            count(|op| matches!(op, PatchOperation::Move(_))),
+            generated.moves
+        );
+        assert_eq!(
+            count(|op| matches!(op, PatchOperation::Copy(_))),
+            generated.copies
+        );
+        assert!(generated.moves > 0 && generated.copies > 0);
+        assert_eq!(apply(&old, &patch), new);
+    }
+
+    #[test]
+    fn every_json_patch_operation_applies_to_documents() {
+        let value = json!({ "a": [1, { "b": "x" }], "c": {} });
+        let patch = Patch(vec![
+            PatchOperation::Test(TestOperation {
+                path: "/a/1".to_owned(),
+                value: json!({ "b": "x" }),
+            }),
+            PatchOperation::Copy(CopyOperation {
+                from: "/a/1".to_owned(),
+                path: "/c/d".to_owned(),
+            }),
+            PatchOperation::Move(MoveOperation {
+                from: "/a/0".to_owned(),
+                path: "/a/-".to_owned(),
+            }),
+            PatchOperation::Replace(ReplaceOperation {
+                path: "/c/d/b".to_owned(),
+                value: json!(["y"]),
+            }),
+            PatchOperation::Add(AddOperation {
+                path: "/a/0".to_owned(),
+                value: json!(0),
+            }),
+            PatchOperation::Remove(RemoveOperation {
+                path: "/a/1".to_owned(),
+            }),
+        ]);
+
+        let mut doc = FlattenedDocument::from(&value);
+        doc.apply_json_patch(&patch).unwrap();
+        assert_eq!(doc.unflatten(), Ok(apply(&value, &patch)));
+
+        let mut doc = FlattenedDocument::from(&value);
+        let report = doc.update_from_json_patch(&patch).unwrap();
+        assert_eq!(report.added, vec!["/c/d", "/c/d/b", "/c/d/b/0"]);
+        assert_eq!(doc, FlattenedDocument::from(&apply(&value, &patch)));
+    }
+
+    #[test]
+    fn json_patch_failures_leave_documents_unchanged() {
+        let mut doc = FlattenedDocument::from(&json!({ "a": 1 }));
+        let before = doc.clone();
+        let failing = |op| {
+            Patch(vec![
+                PatchOperation::Remove(RemoveOperation {
+                    path: "/a".to_owned(),
+                }),
+                op,
+            ])
+        };
+
+        assert_eq!(
+            doc.apply_json_patch(&failing(PatchOperation::Test(TestOperation {
+                path: "/a".to_owned(),
+                value: json!(1),
+            }))),
+            Err(PatchError::NotFound {
+                pointer: "/a".to_owned()
+            })
+        );
+        assert_eq!(
+            doc.update_from_json_patch(&failing(PatchOperation::Remove(RemoveOperation {
+                path: "a".to_owned(),
+            }))),
+            Err(PatchError::InvalidPointer(
+                PointerError::MissingLeadingSlash("a".to_owned())
+            ))
+        );
+        assert_eq!(doc, before);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::error::PatternError;
+use crate::pointer;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    /// `*`, exactly one segment
+    Any,
+    /// `**`, zero or more segments
+    AnyDepth,
+}
+
+/// A compiled glob pattern over JSON Pointers
+///
+/// Patterns are written like pointers. A segment that is exactly `*` matches
+/// any single segment and a segment that is exactly `**` matches zero or more
+/// segments. Other segments match literally after decoding `~0` as `~`, `~1`
+/// as `/` and `~2` as a literal `*`, so escaped keys behave the same as in
+/// pointers. Matching is always against the whole pointer.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::PointerPattern;
+///
+/// let pattern = PointerPattern::new("/users/*/email").unwrap();
+///
+/// assert!(pattern.matches("/users/0/email"));
+/// assert!(!pattern.matches("/users/0/address/email"));
+///
+/// let pattern: PointerPattern = "/**/password".parse().unwrap();
+///
+/// assert!(pattern.matches("/password"));
+/// assert!(pattern.matches("/db/primary/password"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerPattern {
+    source: String,
+    tokens: Vec<Token>,
+}
+
+impl PointerPattern {
+    /// Compile a pattern, failing on syntax errors
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        if !pattern.is_empty() && !pattern.starts_with('/') {
+            return Err(PatternError::MissingLeadingSlash(pattern.to_owned()));
+        }
+
+        let tokens = pattern
+            .split('/')
+            .skip(1)
+            .map(parse_segment)
+            .collect::<Result<_, _>>()?;
+
+        Ok(PointerPattern {
+            source: pattern.to_owned(),
+            tokens,
+        })
+    }
+
+    /// The pattern as it was written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Check whether `pointer` matches this pattern
+    pub fn matches(&self, pointer: &str) -> bool {
+        let segments: Vec<Cow<str>> = pointer::segments(pointer).collect();
+        glob(&self.tokens, &segments)
+    }
+
+    /// The match states before any segment is consumed, see [`PointerPattern::advance`]
+    ///
+    /// A state is the position of the next token to match. Matching segment by
+    /// segment lets a traversal drop branches no pointer below could match.
+    pub(crate) fn start(&self) -> Vec<usize> {
+        self.closure(vec![0])
+    }
+
+    /// The states after consuming the decoded `segment`, empty once nothing below can match
+    pub(crate) fn advance(&self, states: &[usize], segment: &str) -> Vec<usize> {
+        let next = states
+            .iter()
+            .filter_map(|&t| match self.tokens.get(t) {
+                Some(Token::Any) => Some(t + 1),
+                Some(Token::Literal(literal)) if literal == segment => Some(t + 1),
+                Some(Token::AnyDepth) => Some(t),
+                _ => None,
+            })
+            .collect();
+        self.closure(next)
+    }
+
+    /// Whether the segments consumed so far match the whole pattern
+    pub(crate) fn accepts(&self, states: &[usize]) -> bool {
+        states.contains(&self.tokens.len())
+    }
+
+    /// The decoded segments the states can consume next, `None` if a wildcard can consume any
+    pub(crate) fn next_literals<'a>(&'a self, states: &[usize]) -> Option<Vec<&'a str>> {
+        states
+            .iter()
+            .filter_map(|&t| match self.tokens.get(t) {
+                Some(Token::Literal(literal)) => Some(Some(literal.as_str())),
+                Some(_) => Some(None),
+                None => None,
+            })
+            .collect()
+    }
+
+    /// Add the states reachable by letting `**` match nothing, sorted and deduplicated
+    fn closure(&self, mut states: Vec<usize>) -> Vec<usize> {
+        let mut idx = 0;
+        while idx < states.len() {
+            let t = states[idx];
+            if let Some(Token::AnyDepth) = self.tokens.get(t) {
+                states.push(t + 1);
+            }
+            idx += 1;
+        }
+        states.sort_unstable();
+        states.dedup();
+        states
+    }
+}
+
+impl FromStr for PointerPattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PointerPattern::new(s)
+    }
+}
+
+impl fmt::Display for PointerPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_segment(segment: &str) -> Result<Token, PatternError> {
+    match segment {
+        "*" => return Ok(Token::Any),
+        "**" => return Ok(Token::AnyDepth),
+        _ if segment.contains('*') => {
+            return Err(PatternError::InvalidWildcard(segment.to_owned()))
+        }
+        _ => {}
+    }
+
+    let mut literal = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => literal.push('~'),
+            Some('1') => literal.push('/'),
+            Some('2') => literal.push('*'),
+            _ => return Err(PatternError::InvalidEscape(segment.to_owned())),
+        }
+    }
+    Ok(Token::Literal(literal))
+}
+
+/// Wildcard matching with backtracking to the most recent `**`
+fn glob(tokens: &[Token], segments: &[Cow<str>]) -> bool {
+    let (mut t, mut s) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < segments.len() {
+        match tokens.get(t) {
+            Some(Token::AnyDepth) => {
+                backtrack = Some((t + 1, s));
+                t += 1;
+                continue;
+            }
+            Some(Token::Any) => {
+                t += 1;
+                s += 1;
+                continue;
+            }
+            Some(Token::Literal(literal)) if *literal == segments[s] => {
+                t += 1;
+                s += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((resume, consumed)) => {
+                t = resume;
+                s = consumed + 1;
+                backtrack = Some((resume, consumed + 1));
+            }
+            None => return false,
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::AnyDepth)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(pattern: &str, pointer: &str) -> bool {
+        PointerPattern::new(pattern).unwrap().matches(pointer)
+    }
+
+    #[test]
+    fn literal_and_single_wildcard() {
+        assert!(matches("", ""));
+        assert!(!matches("", "/a"));
+        assert!(matches("/a/*", "/a/b"));
+        assert!(!matches("/a/*", "/a"));
+        assert!(!matches("/a/*", "/a/b/c"));
+        assert!(matches("/*", "/"));
+    }
+
+    #[test]
+    fn any_depth_wildcard() {
+        assert!(matches("/**", ""));
+        assert!(matches("/**", "/a/b"));
+        assert!(matches("/a/**", "/a"));
+        assert!(matches("/a/**/z", "/a/z"));
+        assert!(matches("/a/**/z", "/a/b/c/z"));
+        assert!(!matches("/a/**/z", "/a/b/c/z/y"));
+        assert!(matches("/**/b/*", "/a/b/b/c"));
+        assert!(matches("/**/*/**", "/x"));
+        assert!(!matches("/**/*/**", ""));
+    }
+
+    #[test]
+    fn matches_decoded_segments() {
+        assert!(matches("/a~1b", "/a~1b"));
+        assert!(!matches("/a~1b", "/a/b"));
+        assert!(matches("/*/c", "/a~1b/c"));
+        assert!(matches("/~2", "/*"));
+        assert!(!matches("/~2", "/x"));
+    }
+
+    #[test]
+    fn invalid_patterns_error() {
+        assert_eq!(
+            PointerPattern::new("a/b"),
+            Err(PatternError::MissingLeadingSlash("a/b".to_owned()))
+        );
+        assert_eq!(
+            PointerPattern::new("/a~3"),
+            Err(PatternError::InvalidEscape("a~3".to_owned()))
+        );
+        assert_eq!(
+            PointerPattern::new("/a~"),
+            Err(PatternError::InvalidEscape("a~".to_owned()))
+        );
+        assert_eq!(
+            PointerPattern::new("/user*"),
+            Err(PatternError::InvalidWildcard("user*".to_owned()))
+        );
+        assert_eq!(
+            PointerPattern::new("/***"),
+            Err(PatternError::InvalidWildcard("***".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//! Helpers for working with JSON Pointer strings as produced by this crate
+//!
+//! Pointers are handled in their escaped form, where `~` is written as `~0` and
+//! `/` as `~1`, so any unescaped `/` is a segment separator.
+use crate::error::PointerError;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::mem;
+
+/// Escape an object key so it can be used as a single pointer segment
+///
+/// ```
+/// assert_eq!(jsonpointer_flatten::pointer::escape("a/b~c"), "a~1b~0c");
+/// ```
+pub fn escape(key: &str) -> String {
+    let mut escaped = String::with_capacity(key.len());
+    escape_into(key, &mut escaped);
+    escaped
+}
+
+/// Decode a single pointer segment back into the original object key
+///
+/// ```
+/// assert_eq!(jsonpointer_flatten::pointer::unescape("a~1b~0c"), "a/b~c");
+/// ```
+pub fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Check whether `pointer` is `prefix` itself or one of its descendants
+///
+/// Matching is done segment by segment, so `/user` is a prefix of `/user/name`
+/// but not of `/username`. The empty root pointer is a prefix of every pointer.
+///
+/// ```
+/// use jsonpointer_flatten::pointer::starts_with;
+///
+/// assert!(starts_with("/user/name", "/user"));
+/// assert!(!starts_with("/username", "/user"));
+/// ```
+pub fn starts_with(pointer: &str, prefix: &str) -> bool {
+    match pointer.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Split a pointer into its decoded segments
+///
+/// The root pointer `""` has no segments.
+///
+/// ```
+/// let segments: Vec<_> = jsonpointer_flatten::pointer::segments("/a~1b/0").collect();
+///
+/// assert_eq!(segments, vec!["a/b", "0"]);
+/// ```
+pub fn segments(pointer: &str) -> impl Iterator<Item = Cow<'_, str>> {
+    pointer.split('/').skip(1).map(|segment| {
+        if segment.contains('~') {
+            Cow::Owned(unescape(segment))
+        } else {
+            Cow::Borrowed(segment)
+        }
+    })
+}
+
+/// The pointer of the parent container, or `None` for the root pointer
+///
+/// ```
+/// use jsonpointer_flatten::pointer::parent;
+///
+/// assert_eq!(parent("/a~1b/c"), Some("/a~1b"));
+/// assert_eq!(parent("/a"), Some(""));
+/// assert_eq!(parent(""), None);
+/// ```
+pub fn parent(pointer: &str) -> Option<&str> {
+    pointer.rfind('/').map(|idx| &pointer[..idx])
+}
+
+/// Number of segments in a pointer, zero for the root pointer
+pub fn depth(pointer: &str) -> usize {
+    pointer.matches('/').count()
+}
+
+/// Check that `pointer` is syntactically valid according to RFC 6901
+///
+/// ```
+/// use jsonpointer_flatten::pointer::validate;
+///
+/// assert!(validate("/a~1b/0").is_ok());
+/// assert!(validate("a").is_err());
+/// assert!(validate("/a~2").is_err());
+/// ```
+pub fn validate(pointer: &str) -> Result<(), PointerError> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(PointerError::MissingLeadingSlash(pointer.to_owned()));
+    }
+    let mut chars = pointer.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0') | Some('1')) {
+            return Err(PointerError::InvalidEscape(pointer.to_owned()));
+        }
+    }
+    Ok(())
+}
+
+/// A pointer argument in its escaped form
+///
+/// Implemented for strings and, with the `jsonptr` feature, for the pointer
+/// types of the jsonptr crate, so either can be passed to methods like
+/// [`FlattenedDocument::get`](crate::FlattenedDocument::get).
+pub trait AsPointer {
+    /// The escaped pointer, as used for flattened keys
+    fn as_pointer(&self) -> &str;
+}
+
+impl AsPointer for str {
+    fn as_pointer(&self) -> &str {
+        self
+    }
+}
+
+impl AsPointer for String {
+    fn as_pointer(&self) -> &str {
+        self
+    }
+}
+
+impl<T: AsPointer + ?Sized> AsPointer for &T {
+    fn as_pointer(&self) -> &str {
+        (**self).as_pointer()
+    }
+}
+
+#[cfg(feature = "jsonptr")]
+impl AsPointer for jsonptr::Pointer {
+    fn as_pointer(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[cfg(feature = "jsonptr")]
+impl AsPointer for jsonptr::PointerBuf {
+    fn as_pointer(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Borrow `pointer` as a [`jsonptr::Pointer`], failing as [`validate`] does
+///
+/// Both crates accept exactly the pointers of RFC 6901 and keep them escaped,
+/// so the conversion never changes a pointer, and going back is
+/// [`AsPointer::as_pointer`].
+///
+/// ```
+/// use jsonpointer_flatten::pointer::{to_jsonptr, AsPointer};
+///
+/// let pointer = to_jsonptr("/a~1b/0").unwrap();
+///
+/// assert_eq!(pointer.tokens().next().unwrap().decoded(), "a/b");
+/// assert_eq!(pointer.as_pointer(), "/a~1b/0");
+/// assert!(to_jsonptr("/a~2").is_err());
+/// ```
+#[cfg(feature = "jsonptr")]
+pub fn to_jsonptr(pointer: &str) -> Result<&jsonptr::Pointer, PointerError> {
+    jsonptr::Pointer::parse(pointer).map_err(|error| match error {
+        jsonptr::ParseError::NoLeadingSlash => {
+            PointerError::MissingLeadingSlash(pointer.to_owned())
+        }
+        jsonptr::ParseError::InvalidEncoding { .. } => {
+            PointerError::InvalidEscape(pointer.to_owned())
+        }
+    })
+}
+
+/// Longest common ancestor of a set of pointers
+///
+/// Pointers are compared segment by segment, so `/ab` and `/abc` only share
+/// the root pointer `""`. Returns `None` when `pointers` is empty and an error
+/// for the first pointer that isn't valid.
+///
+/// ```
+/// use jsonpointer_flatten::pointer::common_prefix;
+///
+/// let prefix = common_prefix(vec!["/users/3/email", "/users/3/name"]).unwrap();
+///
+/// assert_eq!(prefix.as_deref(), Some("/users/3"));
+/// assert_eq!(common_prefix(vec!["/ab", "/abc"]).unwrap().as_deref(), Some(""));
+/// ```
+pub fn common_prefix<'a, I>(pointers: I) -> Result<Option<String>, PointerError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut prefix: Option<&str> = None;
+    for pointer in pointers {
+        validate(pointer)?;
+        let mut common = prefix.unwrap_or(pointer);
+        while !starts_with(pointer, common) {
+            // The root pointer is a prefix of everything, so this always ends
+            common = parent(common).unwrap_or("");
+        }
+        prefix = Some(common);
+    }
+    Ok(prefix.map(str::to_owned))
+}
+
+/// Order pointers for display: parents before children, array indices
+/// numerically and object keys alphabetically
+///
+/// Pointers are compared segment by segment after decoding. The first
+/// differing segment decides: two array indices (non-negative decimal integers
+/// without leading zeros) compare numerically, an index sorts before any other
+/// key, and other keys compare by their UTF-8 bytes. When one pointer runs out
+/// of segments first it is an ancestor of the other and sorts first. This is a
+/// total order, equal only for identical pointers.
+///
+/// ```
+/// use jsonpointer_flatten::pointer::compare;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(compare("/items/2", "/items/10"), Ordering::Less);
+/// assert_eq!(compare("/a", "/a/b"), Ordering::Less);
+/// assert_eq!(compare("/a b", "/a/b"), Ordering::Greater);
+/// ```
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let mut a = segments(a);
+    let mut b = segments(b);
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match compare_segments(&x, &y) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            },
+        }
+    }
+}
+
+pub(crate) fn compare_segments(a: &str, b: &str) -> Ordering {
+    match (is_array_index(a), is_array_index(b)) {
+        (true, true) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.cmp(b),
+    }
+}
+
+/// Whether a segment is a canonical array index: `0` or digits without a leading zero
+pub(crate) fn is_array_index(segment: &str) -> bool {
+    match segment.as_bytes() {
+        [] => false,
+        [b'0'] => true,
+        [b'0', ..] => false,
+        bytes => bytes.iter().all(u8::is_ascii_digit),
+    }
+}
+
+pub(crate) fn escape_into(key: &str, target: &mut String) {
+    for c in key.chars() {
+        match c {
+            '~' => target.push_str("~0"),
+            '/' => target.push_str("~1"),
+            _ => target.push(c),
+        }
+    }
+}
+
+/// Rebuild `entries` from `f` applied to each entry in order, dropping the
+/// entries it maps to `None`
+///
+/// With serde_json's `preserve_order` feature, `Map::remove` moves the last
+/// entry into the gap it leaves, so entries are removed or renamed through
+/// this instead to keep the order of the others.
+pub(crate) fn rebuild_entries<F>(entries: &mut Map<String, Value>, f: F)
+where
+    F: FnMut((String, Value)) -> Option<(String, Value)>,
+{
+    *entries = mem::take(entries).into_iter().filter_map(f).collect();
+}
+
+/// Remove the entry at `prefix` and every entry below it
+pub(crate) fn remove_subtree(entries: &mut Map<String, Value>, prefix: &str) {
+    rebuild_entries(entries, |(key, value)| {
+        (!starts_with(&key, prefix)).then_some((key, value))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_round_trip() {
+        for key in ["", "plain", "a/b", "m~n", "~1", "/~0/"] {
+            assert_eq!(unescape(&escape(key)), key);
+        }
+    }
+
+    #[test]
+    fn segments_of_root_and_empty_keys() {
+        assert_eq!(segments("").count(), 0);
+        assert_eq!(segments("/").collect::<Vec<_>>(), vec![""]);
+        assert_eq!(segments("//a/").collect::<Vec<_>>(), vec!["", "a", ""]);
+    }
+
+    #[test]
+    fn common_prefix_of_sets() {
+        let prefix = |pointers: Vec<&str>| common_prefix(pointers).unwrap();
+
+        assert_eq!(prefix(vec![]), None);
+        assert_eq!(prefix(vec!["/a/b"]), Some("/a/b".to_owned()));
+        assert_eq!(prefix(vec!["/a/b", "/a/b"]), Some("/a/b".to_owned()));
+        assert_eq!(
+            prefix(vec!["/a/b/c", "/a/b", "/a/b/d"]),
+            Some("/a/b".to_owned())
+        );
+        assert_eq!(prefix(vec!["/a~1b/c", "/a~1b/d"]), Some("/a~1b".to_owned()));
+        assert_eq!(prefix(vec!["/a~1b", "/a/b"]), Some("".to_owned()));
+        assert_eq!(prefix(vec!["/x", ""]), Some("".to_owned()));
+        assert_eq!(prefix(vec!["//a", "//b"]), Some("/".to_owned()));
+    }
+
+    #[test]
+    fn common_prefix_rejects_invalid_pointers() {
+        assert_eq!(
+            common_prefix(vec!["/a", "b"]),
+            Err(PointerError::MissingLeadingSlash("b".to_owned()))
+        );
+    }
+
+    #[test]
+    fn compare_orders_for_display() {
+        let mut pointers = vec![
+            "/b", "/a/10", "/a/9", "/a", "", "/a/x", "/a/01", "/a~1b", "/a/9/z", "/10", "/9",
+        ];
+
+        pointers.sort_by(|a, b| compare(a, b));
+
+        assert_eq!(
+            pointers,
+            vec!["", "/9", "/10", "/a", "/a/9", "/a/9/z", "/a/10", "/a/01", "/a/x", "/a~1b", "/b",]
+        );
+    }
+
+    #[test]
+    fn compare_huge_indices() {
+        assert_eq!(
+            compare("/99999999999999999999", "/100000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn array_index_syntax() {
+        assert!(is_array_index("0"));
+        assert!(is_array_index("10"));
+        assert!(!is_array_index(""));
+        assert!(!is_array_index("01"));
+        assert!(!is_array_index("-1"));
+        assert!(!is_array_index("1 "));
+        assert!(!is_array_index("1e2"));
+    }
+
+    #[test]
+    fn starts_with_is_segment_aware() {
+        assert!(starts_with("/user", "/user"));
+        assert!(starts_with("/user/0", "/user"));
+        assert!(starts_with("/a", ""));
+        assert!(starts_with("", ""));
+        assert!(starts_with("//x", "/"));
+        assert!(!starts_with("/username", "/user"));
+        assert!(!starts_with("/a~1b", "/a"));
+        assert!(!starts_with("", "/a"));
+    }
+
+    #[cfg(feature = "jsonptr")]
+    #[test]
+    fn jsonptr_agrees_on_validity_and_segments() {
+        for pointer in [
+            "", "/", "//", "/ ", "/a~0b~1c", "/~01", "/0/-", "/%25", "a", "~0", "/a~", "/a~2",
+            "/~~", "/é~1",
+        ] {
+            assert_eq!(
+                to_jsonptr(pointer).err(),
+                validate(pointer).err(),
+                "{}",
+                pointer
+            );
+            if let Ok(converted) = to_jsonptr(pointer) {
+                assert_eq!(converted.as_pointer(), pointer);
+                assert_eq!(
+                    converted
+                        .tokens()
+                        .map(|token| token.decoded().into_owned())
+                        .collect::<Vec<_>>(),
+                    segments(pointer).map(Cow::into_owned).collect::<Vec<_>>(),
+                    "{}",
+                    pointer
+                );
+            }
+        }
+    }
+}
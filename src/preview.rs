@@ -0,0 +1,318 @@
+use crate::flattener::{entry_value, is_container};
+use crate::pointer::escape_into;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+/// Bounds for [`flatten_preview`]
+///
+/// Every bound is optional and unset by default, which gives the output of
+/// [`from_json`](crate::from_json) with nothing omitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreviewOptions {
+    /// Maximum number of entries, containers included
+    pub max_entries: Option<usize>,
+    /// Maximum length in characters of string values
+    pub max_string_len: Option<usize>,
+    /// Maximum number of elements shown of any array
+    pub max_array_len: Option<usize>,
+}
+
+impl PreviewOptions {
+    /// Create options with nothing bounded
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show at most `entries` entries, containers included
+    pub fn max_entries(mut self, entries: usize) -> Self {
+        self.max_entries = Some(entries);
+        self
+    }
+
+    /// Cut string values after `chars` characters
+    pub fn max_string_len(mut self, chars: usize) -> Self {
+        self.max_string_len = Some(chars);
+        self
+    }
+
+    /// Show only the first `elements` elements of every array
+    pub fn max_array_len(mut self, elements: usize) -> Self {
+        self.max_array_len = Some(elements);
+        self
+    }
+}
+
+/// Result of [`flatten_preview`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    /// The flattened entries shown, an object of pointer keys
+    pub value: Value,
+    /// What was left out, in the order it was found
+    pub omissions: Vec<Omission>,
+}
+
+impl Preview {
+    /// Whether the preview shows the whole document
+    pub fn is_complete(&self) -> bool {
+        self.omissions.is_empty()
+    }
+}
+
+/// Something [`flatten_preview`] left out, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Omission {
+    /// Pointer of the container or string concerned
+    pub pointer: String,
+    /// What was left out there
+    pub kind: OmissionKind,
+}
+
+/// What an [`Omission`] left out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OmissionKind {
+    /// The entries budget ran out, so `count` entries below the container are not shown
+    ///
+    /// Only entries the preview would show count, so elements already left
+    /// out of a sampled array don't.
+    EntriesOmitted { count: usize },
+    /// The string was cut, from `original_len` characters
+    StringTruncated { original_len: usize },
+    /// Only the first `shown` of the `total` elements of the array are shown
+    ArraySampled { shown: usize, total: usize },
+}
+
+/// Flatten a bounded preview of `value`, recording everything left out
+///
+/// Entries come in flattening order until [`PreviewOptions::max_entries`]
+/// are shown, and every container that loses entries that way gets an
+/// [`OmissionKind::EntriesOmitted`]. Long strings are cut and long arrays
+/// only show their first elements. The preview is plain flattened JSON
+/// without marker entries, so it still unflattens, and omissions are only
+/// described by [`Preview::omissions`].
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{Omission, OmissionKind, PreviewOptions};
+/// use serde_json::json;
+///
+/// let value = json!({ "log": ["started", "ok", "ok", "ok"], "note": "a long sentence" });
+/// let options = PreviewOptions::new().max_array_len(1).max_string_len(6);
+///
+/// let preview = jsonpointer_flatten::flatten_preview(&value, &options);
+///
+/// assert_eq!(preview.value, json!({ "": {}, "/log": [], "/log/0": "starte", "/note": "a long" }));
+/// assert_eq!(preview.omissions, vec![
+///     Omission {
+///         pointer: "/log".to_owned(),
+///         kind: OmissionKind::ArraySampled { shown: 1, total: 4 },
+///     },
+///     Omission {
+///         pointer: "/log/0".to_owned(),
+///         kind: OmissionKind::StringTruncated { original_len: 7 },
+///     },
+///     Omission {
+///         pointer: "/note".to_owned(),
+///         kind: OmissionKind::StringTruncated { original_len: 15 },
+///     },
+/// ]);
+/// ```
+pub fn flatten_preview(value: &Value, options: &PreviewOptions) -> Preview {
+    let mut previewer = Previewer {
+        options,
+        entries: Map::new(),
+        omissions: Vec::new(),
+        pointer: String::new(),
+    };
+    if previewer.is_full() {
+        previewer.omit(String::new(), previewer.size(value));
+    } else {
+        previewer.visit(value);
+    }
+    Preview {
+        value: Value::Object(previewer.entries),
+        omissions: previewer.omissions,
+    }
+}
+
+struct Previewer<'o> {
+    options: &'o PreviewOptions,
+    entries: Map<String, Value>,
+    omissions: Vec<Omission>,
+    pointer: String,
+}
+
+impl Previewer<'_> {
+    fn is_full(&self) -> bool {
+        self.options
+            .max_entries
+            .is_some_and(|max| self.entries.len() >= max)
+    }
+
+    /// The children shown of a container, after sampling arrays
+    fn children<'v>(&self, value: &'v Value) -> Vec<(Option<&'v str>, &'v Value)> {
+        match value {
+            Value::Array(arr) => {
+                let shown = self
+                    .options
+                    .max_array_len
+                    .map_or(arr.len(), |max| max.min(arr.len()));
+                arr[..shown].iter().map(|child| (None, child)).collect()
+            }
+            Value::Object(obj) if is_container(value) => obj
+                .iter()
+                .map(|(key, child)| (Some(key.as_str()), child))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Number of entries the preview would show for `value` without an entries budget
+    fn size(&self, value: &Value) -> usize {
+        1 + self
+            .children(value)
+            .into_iter()
+            .map(|(_, child)| self.size(child))
+            .sum::<usize>()
+    }
+
+    fn omit(&mut self, pointer: String, count: usize) {
+        self.omissions.push(Omission {
+            pointer,
+            kind: OmissionKind::EntriesOmitted { count },
+        });
+    }
+
+    fn visit(&mut self, value: &Value) {
+        let entry = match value {
+            Value::String(s) => match self.options.max_string_len {
+                Some(max) => match s.char_indices().nth(max) {
+                    Some((cut, _)) => {
+                        self.omissions.push(Omission {
+                            pointer: self.pointer.clone(),
+                            kind: OmissionKind::StringTruncated {
+                                original_len: s.chars().count(),
+                            },
+                        });
+                        Value::String(s[..cut].to_owned())
+                    }
+                    None => value.clone(),
+                },
+                None => value.clone(),
+            },
+            _ => entry_value(value),
+        };
+        self.entries.insert(self.pointer.clone(), entry);
+
+        if let Value::Array(arr) = value {
+            if let Some(shown) = self.options.max_array_len.filter(|max| *max < arr.len()) {
+                self.omissions.push(Omission {
+                    pointer: self.pointer.clone(),
+                    kind: OmissionKind::ArraySampled {
+                        shown,
+                        total: arr.len(),
+                    },
+                });
+            }
+        }
+
+        let children = self.children(value);
+        for (idx, (key, child)) in children.iter().enumerate() {
+            if self.is_full() {
+                let count = children[idx..]
+                    .iter()
+                    .map(|(_, child)| self.size(child))
+                    .sum();
+                self.omit(self.pointer.clone(), count);
+                return;
+            }
+            let len = self.pointer.len();
+            match key {
+                Some(key) => {
+                    self.pointer.push('/');
+                    escape_into(key, &mut self.pointer);
+                }
+                None => {
+                    let _ = write!(self.pointer, "/{}", idx);
+                }
+            }
+            self.visit(child);
+            self.pointer.truncate(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::from_json;
+    use serde_json::json;
+
+    fn omission(pointer: &str, kind: OmissionKind) -> Omission {
+        Omission {
+            pointer: pointer.to_owned(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn unbounded_preview_is_the_flattened_document() {
+        let value = json!({ "a": [1, { "b": "text" }], "c~d": null });
+
+        let preview = flatten_preview(&value, &PreviewOptions::new());
+
+        assert_eq!(preview.value, from_json(&value));
+        assert!(preview.is_complete());
+    }
+
+    #[test]
+    fn entries_budget_records_omissions_per_container() {
+        let value = json!([[1, 2, 3], { "a": [4, 5] }, 6]);
+        let options = PreviewOptions::new().max_entries(3).max_array_len(2);
+
+        let preview = flatten_preview(&value, &options);
+
+        assert_eq!(preview.value, json!({ "": [], "/0": [], "/0/0": 1 }));
+        assert_eq!(
+            preview.omissions,
+            vec![
+                omission("", OmissionKind::ArraySampled { shown: 2, total: 3 }),
+                omission("/0", OmissionKind::ArraySampled { shown: 2, total: 3 }),
+                omission("/0", OmissionKind::EntriesOmitted { count: 1 }),
+                // "/1", "/1/a", "/1/a/0" and "/1/a/1", as "/2" is not sampled
+                omission("", OmissionKind::EntriesOmitted { count: 4 }),
+            ]
+        );
+        assert_eq!(crate::unflatten(&preview.value), Ok(json!([[1]])));
+    }
+
+    #[test]
+    fn zero_entries_omits_the_root() {
+        let preview = flatten_preview(
+            &json!({ "a": "é".repeat(3) }),
+            &PreviewOptions::new().max_entries(0),
+        );
+
+        assert_eq!(preview.value, json!({}));
+        assert_eq!(
+            preview.omissions,
+            vec![omission("", OmissionKind::EntriesOmitted { count: 2 })]
+        );
+    }
+
+    #[test]
+    fn strings_are_cut_on_character_boundaries() {
+        let options = PreviewOptions::new().max_string_len(2);
+
+        let preview = flatten_preview(&json!(["ééé", "ab"]), &options);
+
+        assert_eq!(preview.value, json!({ "": [], "/0": "éé", "/1": "ab" }));
+        assert_eq!(
+            preview.omissions,
+            vec![omission(
+                "/0",
+                OmissionKind::StringTruncated { original_len: 3 }
+            )]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::pointer::escape_into;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Flatten a JSON string without decoding its leaves
+///
+/// Every node is paired with the slice of `s` it was written as, in text
+/// order: containers first, spanning their whole subtree, then their
+/// children. Leaves are only scanned, never parsed into a [`Value`](serde_json::Value)
+/// or re-encoded, so numbers and strings keep their exact original spelling.
+/// The text is validated by serde_json once and then scanned once for the
+/// spans, so the cost is linear in its size whatever its depth.
+///
+/// Unlike [`from_str`](crate::from_str), duplicate object keys are all kept.
+///
+/// # Example
+///
+/// ```
+/// let text = r#"{ "id": 1.50, "tags": ["a\u0062"] }"#;
+/// let entries = jsonpointer_flatten::flatten_raw(text).unwrap();
+/// let entries: Vec<(&str, &str)> = entries.iter().map(|(p, raw)| (p.as_str(), raw.get())).collect();
+///
+/// assert_eq!(entries, vec![
+///     ("", r#"{ "id": 1.50, "tags": ["a\u0062"] }"#),
+///     ("/id", "1.50"),
+///     ("/tags", r#"["a\u0062"]"#),
+///     ("/tags/0", r#""a\u0062""#),
+/// ]);
+/// ```
+pub fn flatten_raw(s: &str) -> serde_json::Result<Vec<(String, &RawValue)>> {
+    let root: &RawValue = serde_json::from_str(s)?;
+    let mut scanner = Scanner {
+        text: root.get(),
+        pos: 0,
+        pointer: String::new(),
+        spans: Vec::new(),
+    };
+    scanner.value()?;
+    let text = scanner.text;
+    Ok(scanner
+        .spans
+        .into_iter()
+        .map(|(pointer, start, end)| (pointer, borrow_raw(&text[start..end])))
+        .collect())
+}
+
+/// Borrow a slice of validated JSON text as a [`RawValue`]
+fn borrow_raw(json: &str) -> &RawValue {
+    // SAFETY: `RawValue` is a `#[repr(transparent)]` wrapper around `str`, which
+    // is how serde_json itself borrows it from its input, and `json` is a single
+    // complete value out of text that serde_json has validated
+    unsafe { &*(json as *const str as *const RawValue) }
+}
+
+/// Finds the span of every node of JSON text that is known to be valid
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+    pointer: String,
+    /// Pointer, start and end of every node in text order
+    spans: Vec<(String, usize, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> u8 {
+        self.text.as_bytes()[self.pos]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(
+            self.text.as_bytes().get(self.pos),
+            Some(b' ' | b'\t' | b'\n' | b'\r')
+        ) {
+            self.pos += 1;
+        }
+    }
+
+    /// Record the value starting at the current position and everything below it
+    fn value(&mut self) -> serde_json::Result<()> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let span = self.spans.len();
+        self.spans.push((self.pointer.clone(), start, start));
+
+        let len = self.pointer.len();
+        match self.peek() {
+            b'[' => {
+                self.pos += 1;
+                let mut idx = 0;
+                while !self.close(b']') {
+                    let _ = write!(self.pointer, "/{}", idx);
+                    self.value()?;
+                    self.pointer.truncate(len);
+                    idx += 1;
+                }
+            }
+            b'{' => {
+                self.pos += 1;
+                while !self.close(b'}') {
+                    let key = self.string();
+                    let key: Cow<'_, str> = if key.contains('\\') {
+                        Cow::Owned(serde_json::from_str(key)?)
+                    } else {
+                        Cow::Borrowed(&key[1..key.len() - 1])
+                    };
+                    self.pointer.push('/');
+                    escape_into(&key, &mut self.pointer);
+                    self.skip_whitespace();
+                    // the colon
+                    self.pos += 1;
+                    self.value()?;
+                    self.pointer.truncate(len);
+                }
+            }
+            b'"' => {
+                self.string();
+            }
+            _ => {
+                while !matches!(
+                    self.text.as_bytes().get(self.pos),
+                    None | Some(b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+            }
+        }
+        self.spans[span].2 = self.pos;
+        Ok(())
+    }
+
+    /// Step over the separator before the next element, returning whether `close` ended the container instead
+    fn close(&mut self, close: u8) -> bool {
+        self.skip_whitespace();
+        match self.peek() {
+            byte if byte == close => {
+                self.pos += 1;
+                true
+            }
+            b',' => {
+                self.pos += 1;
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Step over the string at the current position, returning it with its quotes
+    fn string(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        let text = self.text;
+        &text[start..self.pos]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    fn flatten(text: &str) -> Vec<(String, &str)> {
+        flatten_raw(text)
+            .unwrap()
+            .into_iter()
+            .map(|(pointer, raw)| (pointer, raw.get()))
+            .collect()
+    }
+
+    #[test]
+    fn pointers_match_from_str() {
+        let text = r#"{"a/b": [1, {"c~": null}], "": {}, "e": [[]], "n": -0.0e5}"#;
+
+        let raw: Vec<String> = flatten(text).into_iter().map(|(p, _)| p).collect();
+        let flattened = crate::from_str(text).unwrap();
+        let mut expected: Vec<&String> = flattened.as_object().unwrap().keys().collect();
+        let mut actual: Vec<&String> = raw.iter().collect();
+        expected.sort();
+        actual.sort();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn leaves_keep_their_text() {
+        let text = "[ 1e3 , \"\\u00e9\" ,true, {\"k\" : 100000000000000000000000000001 } ]";
+
+        assert_eq!(
+            flatten(text),
+            vec![
+                ("".to_owned(), text.trim()),
+                ("/0".to_owned(), "1e3"),
+                ("/1".to_owned(), "\"\\u00e9\""),
+                ("/2".to_owned(), "true"),
+                ("/3".to_owned(), "{\"k\" : 100000000000000000000000000001 }"),
+                ("/3/k".to_owned(), "100000000000000000000000000001"),
+            ]
+        );
+        let leaf: Value = serde_json::from_str(flatten(text)[2].1).unwrap();
+        assert_eq!(leaf, Value::from("é"));
+    }
+
+    #[test]
+    fn escaped_and_duplicate_keys() {
+        assert_eq!(
+            flatten(r#"{"x\/y": 1, "x\/y": 2}"#),
+            vec![
+                ("".to_owned(), r#"{"x\/y": 1, "x\/y": 2}"#),
+                ("/x~1y".to_owned(), "1"),
+                ("/x~1y".to_owned(), "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn deeply_nested_containers_span_their_text() {
+        let text = format!("{} 1 {}", "[{\"a\" :".repeat(60), "}]".repeat(60));
+
+        let entries = flatten(&text);
+
+        assert_eq!(entries.len(), 121);
+        assert_eq!(entries[0].1, text);
+        assert_eq!(entries[2].0, "/0/a");
+        assert_eq!(entries[2].1, &text[7..text.len() - 2]);
+        assert_eq!(entries[120], ("/0/a".repeat(60), "1"));
+    }
+
+    #[test]
+    fn invalid_json_errors() {
+        assert!(flatten_raw("{\"a\": }").is_err());
+        assert!(flatten_raw("[1] [2]").is_err());
+    }
+}
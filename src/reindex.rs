@@ -0,0 +1,356 @@
+use crate::error::ReindexError;
+use crate::pointer::{self, is_array_index};
+use crate::FlattenedDocument;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// How the elements of one array were renumbered, see [`FlattenedDocument::reindex_array`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReindexReport {
+    /// Pointer of the array
+    pub pointer: String,
+    /// New index of every element, by old index
+    pub indices: BTreeMap<usize, usize>,
+}
+
+impl ReindexReport {
+    /// The index now held by the element that had index `old`
+    pub fn new_index(&self, old: usize) -> Option<usize> {
+        self.indices.get(&old).copied()
+    }
+
+    /// Whether every element kept its index
+    pub fn is_unchanged(&self) -> bool {
+        self.indices.iter().all(|(old, new)| old == new)
+    }
+}
+
+impl FlattenedDocument {
+    /// Renumber the elements of the array at `pointer` from 0, closing gaps
+    ///
+    /// Elements keep their relative order, and the keys of their descendants
+    /// are rewritten along with them. The array is the one whose entry is an
+    /// array, or, without an entry, whose children all have index segments as
+    /// [`unflatten`](crate::unflatten) would build it. Any other child fails
+    /// with [`ReindexError::MixedChildren`] and leaves the document unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::FlattenedDocument;
+    /// use serde_json::json;
+    ///
+    /// // "/items/1" and "/items/3" were removed
+    /// let mut doc: FlattenedDocument = serde_json::from_value(json!({
+    ///     "/items": [],
+    ///     "/items/0": "a",
+    ///     "/items/2": {},
+    ///     "/items/2/id": 2,
+    ///     "/items/4": "e"
+    /// }))
+    /// .unwrap();
+    ///
+    /// let report = doc.reindex_array("/items").unwrap();
+    ///
+    /// assert_eq!(report.new_index(2), Some(1));
+    /// assert_eq!(report.new_index(4), Some(2));
+    /// assert_eq!(doc.get("/items/1/id"), Some(&json!(2)));
+    /// assert_eq!(doc.unflatten(), Ok(json!({ "items": ["a", { "id": 2 }, "e"] })));
+    /// ```
+    pub fn reindex_array(&mut self, pointer: &str) -> Result<ReindexReport, ReindexError> {
+        pointer::validate(pointer)?;
+        let depth = pointer::depth(pointer);
+
+        let entries = self.as_map();
+        let children = children_at(entries, depth);
+        let empty = BTreeSet::new();
+        let segments = children.get(pointer).unwrap_or(&empty);
+        let report = ReindexReport {
+            pointer: pointer.to_owned(),
+            indices: renumbering(pointer, entries.get(pointer), segments)?,
+        };
+
+        if !report.is_unchanged() {
+            rewrite(self.entries_mut(), depth, std::slice::from_ref(&report));
+        }
+        Ok(report)
+    }
+
+    /// Renumber every array of the document, see [`FlattenedDocument::reindex_array`]
+    ///
+    /// Reports are returned for the arrays whose elements moved, outer arrays
+    /// first, each naming its array by the pointer it has once its ancestors
+    /// were renumbered. Children without a container entry that are not all
+    /// indices are taken for an object and left alone, while an array entry
+    /// with such children fails before anything is renumbered.
+    pub fn reindex_all_arrays(&mut self) -> Result<Vec<ReindexReport>, ReindexError> {
+        let entries = self.as_map();
+        for key in entries.keys() {
+            if let (Some(parent), Some(segment)) = (pointer::parent(key), last_segment(key)) {
+                if matches!(entries.get(parent), Some(Value::Array(_))) && index(segment).is_none()
+                {
+                    return Err(mixed(parent, segment));
+                }
+            }
+        }
+
+        let max_depth = entries.keys().map(|key| pointer::depth(key)).max();
+        let mut reports = Vec::new();
+        for depth in 0..max_depth.unwrap_or(0) {
+            let entries = self.as_map();
+            let mut level = Vec::new();
+            for (pointer, segments) in children_at(entries, depth) {
+                let entry = entries.get(pointer);
+                let is_array = match entry {
+                    Some(value) => value.is_array(),
+                    None => segments.iter().all(|segment| index(segment).is_some()),
+                };
+                if is_array {
+                    level.push(ReindexReport {
+                        pointer: pointer.to_owned(),
+                        indices: renumbering(pointer, entry, &segments)?,
+                    });
+                }
+            }
+
+            level.retain(|report| !report.is_unchanged());
+            if !level.is_empty() {
+                rewrite(self.entries_mut(), depth, &level);
+            }
+            reports.extend(level);
+        }
+        Ok(reports)
+    }
+}
+
+/// The distinct escaped child segments of every container with `depth` segments
+fn children_at(entries: &Map<String, Value>, depth: usize) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut children: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for key in entries.keys() {
+        if let Some((parent, segment, _)) = split_at(key, depth) {
+            children.entry(parent).or_default().insert(segment);
+        }
+    }
+    children
+}
+
+/// Split `key` around its segment after the first `depth` ones, if it has one
+fn split_at(key: &str, depth: usize) -> Option<(&str, &str, &str)> {
+    let mut slashes = key.match_indices('/').map(|(idx, _)| idx);
+    let start = slashes.nth(depth)?;
+    let end = slashes.next().unwrap_or(key.len());
+    Some((&key[..start], &key[start + 1..end], &key[end..]))
+}
+
+fn last_segment(key: &str) -> Option<&str> {
+    key.rfind('/').map(|idx| &key[idx + 1..])
+}
+
+fn index(segment: &str) -> Option<usize> {
+    if is_array_index(segment) {
+        segment.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn mixed(pointer: &str, segment: &str) -> ReindexError {
+    ReindexError::MixedChildren {
+        pointer: pointer.to_owned(),
+        segment: pointer::unescape(segment),
+    }
+}
+
+/// Contiguous indices for the children of the array at `pointer`, by old index
+fn renumbering(
+    pointer: &str,
+    entry: Option<&Value>,
+    segments: &BTreeSet<&str>,
+) -> Result<BTreeMap<usize, usize>, ReindexError> {
+    match entry {
+        Some(Value::Array(_)) => {}
+        None if segments.iter().any(|segment| is_array_index(segment)) => {}
+        None if segments.is_empty() => {
+            return Err(ReindexError::NotFound {
+                pointer: pointer.to_owned(),
+            })
+        }
+        _ => {
+            return Err(ReindexError::NotAnArray {
+                pointer: pointer.to_owned(),
+            })
+        }
+    }
+
+    let mut indices = Vec::with_capacity(segments.len());
+    for segment in segments {
+        indices.push(index(segment).ok_or_else(|| mixed(pointer, segment))?);
+    }
+    indices.sort_unstable();
+    Ok(indices
+        .into_iter()
+        .enumerate()
+        .map(|(new, old)| (old, new))
+        .collect())
+}
+
+/// Rename the keys below the arrays of `reports`, which all have `depth` segments
+fn rewrite(entries: &mut Map<String, Value>, depth: usize, reports: &[ReindexReport]) {
+    let renumbered: HashMap<&str, &BTreeMap<usize, usize>> = reports
+        .iter()
+        .map(|report| (report.pointer.as_str(), &report.indices))
+        .collect();
+
+    pointer::rebuild_entries(entries, |(key, value)| {
+        let renamed = split_at(&key, depth).and_then(|(parent, segment, rest)| {
+            let new = renumbered.get(parent)?.get(&index(segment)?)?;
+            Some(format!("{}/{}{}", parent, new, rest))
+        });
+        Some((renamed.unwrap_or(key), value))
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn document(entries: Value) -> FlattenedDocument {
+        serde_json::from_value(entries).unwrap()
+    }
+
+    #[test]
+    fn closes_gaps_and_moves_descendants() {
+        let mut doc = document(json!({
+            "/a": [],
+            "/a/1": {},
+            "/a/1/b": [],
+            "/a/1/b/3": 1,
+            "/a/10": "x",
+            "/a/5": [],
+            "/a/5/0": true,
+            "/a10": 2
+        }));
+
+        let report = doc.reindex_array("/a").unwrap();
+
+        assert_eq!(
+            report.indices.into_iter().collect::<Vec<_>>(),
+            vec![(1, 0), (5, 1), (10, 2)]
+        );
+        assert_eq!(
+            doc,
+            document(json!({
+                "/a": [],
+                "/a/0": {},
+                "/a/0/b": [],
+                "/a/0/b/3": 1,
+                "/a/2": "x",
+                "/a/1": [],
+                "/a/1/0": true,
+                "/a10": 2
+            }))
+        );
+        assert!(doc.reindex_array("/a").unwrap().is_unchanged());
+    }
+
+    #[test]
+    fn arrays_without_entries_are_inferred() {
+        let mut doc = document(json!({ "/2/x": 1, "/7": 2 }));
+
+        let report = doc.reindex_array("").unwrap();
+
+        assert_eq!(report.new_index(7), Some(1));
+        assert_eq!(doc.unflatten(), Ok(json!([{ "x": 1 }, 2])));
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_array() {
+        let mut doc = document(json!({
+            "/o": {},
+            "/o/1": 1,
+            "/l": 3,
+            "/a": [],
+            "/a/1": 1,
+            "/a/x~1y": 2,
+            "/m/1": 1,
+            "/m/01": 2
+        }));
+        let before = doc.clone();
+
+        assert_eq!(
+            doc.reindex_array("/o"),
+            Err(ReindexError::NotAnArray {
+                pointer: "/o".to_owned()
+            })
+        );
+        assert_eq!(
+            doc.reindex_array("/l"),
+            Err(ReindexError::NotAnArray {
+                pointer: "/l".to_owned()
+            })
+        );
+        assert_eq!(
+            doc.reindex_array("/a"),
+            Err(ReindexError::MixedChildren {
+                pointer: "/a".to_owned(),
+                segment: "x/y".to_owned()
+            })
+        );
+        assert_eq!(
+            doc.reindex_array("/m"),
+            Err(ReindexError::MixedChildren {
+                pointer: "/m".to_owned(),
+                segment: "01".to_owned()
+            })
+        );
+        assert_eq!(
+            doc.reindex_array("/missing"),
+            Err(ReindexError::NotFound {
+                pointer: "/missing".to_owned()
+            })
+        );
+        assert!(matches!(
+            doc.reindex_array("a"),
+            Err(ReindexError::InvalidPointer(_))
+        ));
+        assert_eq!(
+            doc.reindex_all_arrays(),
+            Err(ReindexError::MixedChildren {
+                pointer: "/a".to_owned(),
+                segment: "x/y".to_owned()
+            })
+        );
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn reindex_all_renumbers_nested_arrays() {
+        let mut doc = document(json!({
+            "": {},
+            "/rows": [],
+            "/rows/3": [],
+            "/rows/3/2": "a",
+            "/rows/3/9": "b",
+            "/rows/8": [],
+            "/rows/8/0": "c",
+            "/obj": {},
+            "/obj/5": 1,
+            "/obj/x": 2,
+            "/mixed/5": 3,
+            "/mixed/x": 4
+        }));
+
+        let reports = doc.reindex_all_arrays().unwrap();
+
+        assert_eq!(
+            reports
+                .iter()
+                .map(|report| (report.pointer.as_str(), report.indices.len()))
+                .collect::<Vec<_>>(),
+            vec![("/rows", 2), ("/rows/0", 2)]
+        );
+        assert_eq!(doc.get("/mixed/5"), Some(&json!(3)));
+        assert_eq!(doc.unflatten_under("/rows"), Ok(json!([["a", "b"], ["c"]])));
+    }
+}
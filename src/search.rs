@@ -0,0 +1,453 @@
+use crate::flattener::is_container;
+use crate::{iter, pointer, FlattenedDocument, Flattener};
+use serde_json::{Number, Value};
+
+/// Options for [`find_value_with`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FindOptions {
+    /// Compare numbers by numeric value, so `1` matches `1.0` (off by default)
+    pub numbers_by_value: bool,
+}
+
+impl FindOptions {
+    /// Create options with strict JSON equality
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare numbers by numeric value instead of by representation
+    pub fn numbers_by_value(mut self, enabled: bool) -> Self {
+        self.numbers_by_value = enabled;
+        self
+    }
+
+    fn eq(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Number(a), Value::Number(b)) if self.numbers_by_value => numbers_eq(a, b),
+            _ => a == b,
+        }
+    }
+}
+
+/// Find the pointers of every leaf in a nested document equal to `needle`
+///
+/// Only leaves (null, booleans, numbers and strings) are compared, containers
+/// never match. Pointers come back sorted by [`pointer::compare`], so array
+/// elements are in index order.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "a": "ACC-1234", "b": ["x", "ACC-1234"] });
+///
+/// let pointers = jsonpointer_flatten::find_value(&value, &json!("ACC-1234"));
+///
+/// assert_eq!(pointers, vec!["/a", "/b/1"]);
+/// ```
+pub fn find_value(value: &Value, needle: &Value) -> Vec<String> {
+    find_value_with(value, needle, &FindOptions::default())
+}
+
+/// Like [`find_value`], with configurable equality
+pub fn find_value_with(value: &Value, needle: &Value, options: &FindOptions) -> Vec<String> {
+    find_value_by(value, |leaf| options.eq(leaf, needle))
+}
+
+/// Find the pointers of every leaf in a nested document accepted by `predicate`
+pub fn find_value_by<P>(value: &Value, mut predicate: P) -> Vec<String>
+where
+    P: FnMut(&Value) -> bool,
+{
+    let mut found = Vec::new();
+
+    Flattener::new().visit(value, |pointer, _, _, node| {
+        if is_leaf(node) && predicate(node) {
+            found.push(pointer.to_owned());
+        }
+    });
+
+    found.sort_by(|a, b| pointer::compare(a, b));
+    found
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Lowercased needle
+    Substring(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+/// What [`search`] looks for
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::SearchQuery;
+/// use serde_json::json;
+///
+/// let value = json!({ "http": { "error": "Timeout reached", "read_timeout": 30 } });
+/// let query = SearchQuery::substring("timeout").match_pointers(true);
+///
+/// let hits = jsonpointer_flatten::search(&value, &query);
+///
+/// assert_eq!(hits, vec![
+///     ("/http/error".to_owned(), &json!("Timeout reached")),
+///     ("/http/read_timeout".to_owned(), &json!(30)),
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    matcher: Matcher,
+    match_pointers: bool,
+    match_non_strings: bool,
+}
+
+impl SearchQuery {
+    /// Case-insensitive substring search over string leaves
+    pub fn substring(needle: &str) -> Self {
+        Self::with_matcher(Matcher::Substring(needle.to_lowercase()))
+    }
+
+    /// Regular expression search over string leaves
+    #[cfg(feature = "regex")]
+    pub fn regex(regex: regex::Regex) -> Self {
+        Self::with_matcher(Matcher::Regex(regex))
+    }
+
+    /// Also report entries whose pointer matches, containers included (off by default)
+    pub fn match_pointers(mut self, enabled: bool) -> Self {
+        self.match_pointers = enabled;
+        self
+    }
+
+    /// Also match null, boolean and number leaves against their JSON text (off by default)
+    pub fn match_non_strings(mut self, enabled: bool) -> Self {
+        self.match_non_strings = enabled;
+        self
+    }
+
+    fn with_matcher(matcher: Matcher) -> Self {
+        SearchQuery {
+            matcher,
+            match_pointers: false,
+            match_non_strings: false,
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.matcher {
+            Matcher::Substring(needle) => text.to_lowercase().contains(needle.as_str()),
+            #[cfg(feature = "regex")]
+            Matcher::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    fn matches_entry(&self, pointer: &str, value: &Value) -> bool {
+        if self.match_pointers && self.is_match(pointer) {
+            return true;
+        }
+        match value {
+            Value::String(s) => self.is_match(s),
+            _ if is_container(value) => false,
+            _ => self.match_non_strings && self.is_match(&value.to_string()),
+        }
+    }
+}
+
+/// Find every entry of a nested document matching `query`
+///
+/// Results come back in flattening order with the full pointer of each match.
+pub fn search<'v>(value: &'v Value, query: &SearchQuery) -> Vec<(String, &'v Value)> {
+    let mut found = Vec::new();
+
+    Flattener::new().visit(value, |pointer, _, _, node| {
+        if query.matches_entry(pointer, node) {
+            found.push((pointer.to_owned(), node));
+        }
+    });
+
+    found
+}
+
+/// The first entry of a nested document accepted by `predicate`, in flattening order
+///
+/// The document is walked lazily like [`iter`](crate::iter), calling
+/// `predicate` with each pointer and node, containers included, and no node
+/// after the match is visited. Containers come back as references to
+/// themselves, not as placeholders.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "user": { "name": "Jo", "password": "hunter2" }, "zip": "00000" });
+///
+/// let (pointer, found) =
+///     jsonpointer_flatten::find_first(&value, |pointer, _| pointer.ends_with("/password")).unwrap();
+///
+/// assert_eq!(pointer, "/user/password");
+/// assert_eq!(found, &json!("hunter2"));
+/// ```
+pub fn find_first<P>(value: &Value, predicate: P) -> Option<(String, &Value)>
+where
+    P: FnMut(&str, &Value) -> bool,
+{
+    matches(value, predicate).next()
+}
+
+/// Whether any entry of a nested document is accepted by `predicate`, see [`find_first`]
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "notes": ["short", "a bit longer"] });
+///
+/// assert!(jsonpointer_flatten::any(&value, |_, node| node.as_str().map_or(0, str::len) > 10));
+/// assert!(!jsonpointer_flatten::any(&value, |_, node| node.is_number()));
+/// ```
+pub fn any<P>(value: &Value, predicate: P) -> bool
+where
+    P: FnMut(&str, &Value) -> bool,
+{
+    find_first(value, predicate).is_some()
+}
+
+/// The first `n` entries of a nested document accepted by `predicate`, see [`find_first`]
+///
+/// The walk stops at the `n`-th match, and nothing is visited when `n` is 0.
+pub fn find_n<P>(value: &Value, predicate: P, n: usize) -> Vec<(String, &Value)>
+where
+    P: FnMut(&str, &Value) -> bool,
+{
+    matches(value, predicate).take(n).collect()
+}
+
+/// Lazily yield the entries accepted by `predicate`, copying only their pointers
+fn matches<P>(value: &Value, mut predicate: P) -> impl Iterator<Item = (String, &Value)>
+where
+    P: FnMut(&str, &Value) -> bool,
+{
+    let mut nodes = iter(value);
+    std::iter::from_fn(move || {
+        while let Some(node) = nodes.next_node() {
+            if predicate(nodes.pointer(), node) {
+                return Some((nodes.pointer().to_owned(), node));
+            }
+        }
+        None
+    })
+}
+
+impl FlattenedDocument {
+    /// Find the pointers of every leaf equal to `needle`, see [`find_value`](crate::find_value)
+    pub fn find_value(&self, needle: &Value) -> Vec<String> {
+        self.find_value_with(needle, &FindOptions::default())
+    }
+
+    /// Like [`FlattenedDocument::find_value`], with configurable equality
+    pub fn find_value_with(&self, needle: &Value, options: &FindOptions) -> Vec<String> {
+        self.find_value_by(|leaf| options.eq(leaf, needle))
+    }
+
+    /// Find the pointers of every leaf accepted by `predicate`
+    pub fn find_value_by<P>(&self, mut predicate: P) -> Vec<String>
+    where
+        P: FnMut(&Value) -> bool,
+    {
+        let mut found: Vec<String> = self
+            .iter()
+            .filter(|(_, value)| is_leaf(value) && predicate(value))
+            .map(|(pointer, _)| pointer.to_owned())
+            .collect();
+        found.sort_by(|a, b| pointer::compare(a, b));
+        found
+    }
+}
+
+fn is_leaf(value: &Value) -> bool {
+    !is_container(value)
+}
+
+fn numbers_eq(a: &Number, b: &Number) -> bool {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        return a == b;
+    }
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "accounts": [{ "balance": 1, "id": "ACC-1234" }, { "id": "ACC-9" }],
+            "id": "ACC-1234",
+            "meta": { "ref": ["ACC-1234"], "weight": 1.0 }
+        })
+    }
+
+    #[test]
+    fn nested_and_flattened_agree() {
+        let value = sample();
+        let doc = FlattenedDocument::from(&value);
+
+        let nested = find_value(&value, &json!("ACC-1234"));
+        let flat = doc.find_value(&json!("ACC-1234"));
+
+        assert_eq!(nested, vec!["/accounts/0/id", "/id", "/meta/ref/0"]);
+        assert_eq!(nested, flat);
+
+        // flattened maps sort "/10" before "/2", the results don't
+        let value = json!({ "xs": [0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1] });
+        let doc = FlattenedDocument::from(&value);
+        let expected = vec!["/xs/2", "/xs/10", "/xs/11"];
+
+        assert_eq!(find_value(&value, &json!(1)), expected);
+        assert_eq!(doc.find_value(&json!(1)), expected);
+    }
+
+    #[test]
+    fn numeric_equality_is_opt_in() {
+        let value = sample();
+        let options = FindOptions::new().numbers_by_value(true);
+
+        assert_eq!(find_value(&value, &json!(1)), vec!["/accounts/0/balance"]);
+        assert_eq!(
+            find_value_with(&value, &json!(1), &options),
+            vec!["/accounts/0/balance", "/meta/weight"]
+        );
+        assert_eq!(
+            FlattenedDocument::from(&value).find_value_with(&json!(1), &options),
+            vec!["/accounts/0/balance", "/meta/weight"]
+        );
+    }
+
+    #[test]
+    fn containers_never_match() {
+        let value = json!({ "a": [], "b": {} });
+
+        assert!(find_value(&value, &json!([])).is_empty());
+        assert!(FlattenedDocument::from(&value)
+            .find_value(&json!({}))
+            .is_empty());
+    }
+
+    #[test]
+    fn search_is_case_insensitive() {
+        let value = json!({ "a": "Connection TIMEOUT", "b": "ok", "c": ["timeout"] });
+
+        let hits = search(&value, &SearchQuery::substring("Timeout"));
+
+        assert_eq!(
+            hits,
+            vec![
+                ("/a".to_owned(), &json!("Connection TIMEOUT")),
+                ("/c/0".to_owned(), &json!("timeout"))
+            ]
+        );
+    }
+
+    #[test]
+    fn search_pointers_and_non_strings() {
+        let value = json!({ "port": 8030, "timeouts": { "read": 30 } });
+
+        let values_only = search(&value, &SearchQuery::substring("30"));
+        let non_strings = search(
+            &value,
+            &SearchQuery::substring("30").match_non_strings(true),
+        );
+        let pointers = search(
+            &value,
+            &SearchQuery::substring("timeout").match_pointers(true),
+        );
+
+        assert!(values_only.is_empty());
+        assert_eq!(
+            non_strings
+                .iter()
+                .map(|(p, _)| p.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/port", "/timeouts/read"]
+        );
+        assert_eq!(
+            pointers.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>(),
+            vec!["/timeouts", "/timeouts/read"]
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn search_with_regex() {
+        let value = json!({ "a": "id-123", "b": "id-abc" });
+        let query = SearchQuery::regex(regex::Regex::new(r"^id-\d+$").unwrap());
+
+        let hits = search(&value, &query);
+
+        assert_eq!(hits, vec![("/a".to_owned(), &json!("id-123"))]);
+    }
+
+    #[test]
+    fn predicate_variant() {
+        let value = sample();
+
+        let pointers = find_value_by(&value, |v| v.as_str().is_some_and(|s| s.ends_with('9')));
+
+        assert_eq!(pointers, vec!["/accounts/1/id"]);
+    }
+
+    #[test]
+    fn find_first_stops_at_the_match() {
+        let value = sample();
+        let all: Vec<String> = iter(&value).map(|(pointer, _)| pointer).collect();
+        let at = all.iter().position(|p| p == "/accounts/1/id").unwrap();
+        let mut visited = Vec::new();
+
+        let found = find_first(&value, |pointer, node| {
+            visited.push(pointer.to_owned());
+            node == "ACC-9"
+        });
+
+        assert_eq!(found, Some(("/accounts/1/id".to_owned(), &json!("ACC-9"))));
+        assert_eq!(visited, &all[..=at]);
+        assert!(any(&value, |pointer, _| pointer.is_empty()));
+        assert!(!any(&value, |_, node| node == "missing"));
+    }
+
+    #[test]
+    fn find_n_stops_at_the_nth_match() {
+        let value = sample();
+        let mut calls = 0;
+
+        let found = find_n(
+            &value,
+            |_, node| {
+                calls += 1;
+                node == "ACC-1234"
+            },
+            2,
+        );
+
+        let all = find_value(&value, &json!("ACC-1234"));
+        assert_eq!(
+            found.iter().map(|(pointer, _)| pointer).collect::<Vec<_>>(),
+            all[..2].iter().collect::<Vec<_>>()
+        );
+        let second = iter(&value).position(|(pointer, _)| pointer == all[1]);
+        assert_eq!(Some(calls - 1), second);
+        assert!(find_n(&value, |_, _| panic!("no node is visited"), 0).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::flattener::is_container;
+use crate::pointer::{escape_into, is_array_index};
+use crate::{flatten_into, PointerPattern};
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+/// Flatten only the entries matching any of `selectors`, with their descendants
+///
+/// The result is the same as flattening all of `value` and keeping the
+/// entries at or below a matching pointer, but only branches some selector
+/// can still match are descended into. Where every selector continues with a
+/// literal segment, that child is looked up directly instead of visiting its
+/// siblings, so a narrow selection costs little even in a wide document.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::PointerPattern;
+/// use serde_json::json;
+///
+/// let value = json!({
+///     "id": 7,
+///     "user": { "email": "jo@example.com", "name": "Jo" },
+///     "items": [{ "sku": "A1", "qty": 2 }, { "sku": "B2", "qty": 1 }]
+/// });
+/// let selectors: Vec<PointerPattern> = ["/id", "/user/email", "/items/*/sku"]
+///     .iter()
+///     .map(|p| p.parse().unwrap())
+///     .collect();
+///
+/// assert_eq!(
+///     jsonpointer_flatten::flatten_selected(&value, &selectors),
+///     json!({
+///         "/id": 7,
+///         "/user/email": "jo@example.com",
+///         "/items/0/sku": "A1",
+///         "/items/1/sku": "B2"
+///     })
+/// );
+/// ```
+pub fn flatten_selected(value: &Value, selectors: &[PointerPattern]) -> Value {
+    let states = selectors.iter().map(PointerPattern::start).collect();
+    let mut target = Map::new();
+    select(value, &mut String::new(), selectors, states, &mut target);
+    Value::Object(target)
+}
+
+fn select(
+    value: &Value,
+    pointer: &mut String,
+    selectors: &[PointerPattern],
+    states: Vec<Vec<usize>>,
+    target: &mut Map<String, Value>,
+) {
+    if selectors
+        .iter()
+        .zip(&states)
+        .any(|(selector, states)| selector.accepts(states))
+    {
+        flatten_into(value, target, pointer);
+        return;
+    }
+    if !is_container(value) || states.iter().all(Vec::is_empty) {
+        return;
+    }
+
+    let mut visit = |segment: &str, child: &Value, pointer: &mut String| {
+        let len = pointer.len();
+        pointer.push('/');
+        escape_into(segment, pointer);
+        let next = selectors
+            .iter()
+            .zip(&states)
+            .map(|(selector, states)| selector.advance(states, segment))
+            .collect();
+        select(child, pointer, selectors, next, target);
+        pointer.truncate(len);
+    };
+
+    let literals: Option<Vec<&str>> = selectors
+        .iter()
+        .zip(&states)
+        .map(|(selector, states)| selector.next_literals(states))
+        .collect::<Option<Vec<_>>>()
+        .map(|literals| literals.concat());
+
+    match (value, literals) {
+        (Value::Object(obj), Some(mut keys)) => {
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                if let Some(child) = obj.get(key) {
+                    visit(key, child, pointer);
+                }
+            }
+        }
+        (Value::Array(arr), Some(mut segments)) => {
+            segments.retain(|segment| is_array_index(segment));
+            let mut indices: Vec<usize> = segments.iter().filter_map(|s| s.parse().ok()).collect();
+            indices.sort_unstable();
+            indices.dedup();
+            for idx in indices {
+                if let Some(child) = arr.get(idx) {
+                    visit(&idx.to_string(), child, pointer);
+                }
+            }
+        }
+        (Value::Object(obj), None) => {
+            for (key, child) in obj {
+                visit(key, child, pointer);
+            }
+        }
+        (Value::Array(arr), None) => {
+            let mut segment = String::new();
+            for (idx, child) in arr.iter().enumerate() {
+                segment.clear();
+                let _ = write!(segment, "{}", idx);
+                visit(&segment, child, pointer);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::Rng;
+    use crate::{from_json, pointer};
+    use serde_json::json;
+
+    fn patterns(patterns: &[&str]) -> Vec<PointerPattern> {
+        patterns
+            .iter()
+            .map(|p| PointerPattern::new(p).unwrap())
+            .collect()
+    }
+
+    /// Flatten everything, then keep entries at or below a match
+    fn filtered(value: &Value, selectors: &[PointerPattern]) -> Value {
+        let flat = from_json(value).as_object().cloned().unwrap_or_default();
+        let selected = |ptr: &str| {
+            let mut ptr = Some(ptr);
+            while let Some(p) = ptr {
+                if selectors.iter().any(|selector| selector.matches(p)) {
+                    return true;
+                }
+                ptr = pointer::parent(p);
+            }
+            false
+        };
+        Value::Object(flat.into_iter().filter(|(ptr, _)| selected(ptr)).collect())
+    }
+
+    #[test]
+    fn matches_full_flatten_then_filter_on_random_documents() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let selections = [
+            patterns(&["/a"]),
+            patterns(&["/a/b", "/10/*"]),
+            patterns(&["/*/2", "/~1x"]),
+            patterns(&["/**/a"]),
+            patterns(&["/a/**/~0", "/2/1"]),
+            patterns(&[""]),
+            patterns(&[]),
+            patterns(&["/a~1b/*/**"]),
+        ];
+
+        for _ in 0..300 {
+            let value = rng.value(4);
+            for selectors in &selections {
+                assert_eq!(
+                    flatten_selected(&value, selectors),
+                    filtered(&value, selectors),
+                    "{} {:?}",
+                    value,
+                    selectors
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn selected_containers_bring_their_descendants() {
+        let value = json!({ "a": { "b": [1, { "c": 2 }] }, "d": 3 });
+
+        assert_eq!(
+            flatten_selected(&value, &patterns(&["/a/b"])),
+            json!({ "/a/b": [], "/a/b/0": 1, "/a/b/1": {}, "/a/b/1/c": 2 })
+        );
+        assert_eq!(
+            flatten_selected(&value, &patterns(&["/a/b/01", "/a/b/5", "/x"])),
+            json!({})
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::pointer::{self, escape_into};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::io;
+
+/// Serialized size in bytes of the subtree rooted at every pointer
+///
+/// Sizes are exact for compact serialization (`serde_json::to_string`),
+/// string escapes included, and are computed in a single post-order pass:
+/// a container's size is the sum of its children plus brackets, separators
+/// and, for objects, the serialized keys.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "a": [1, 22], "b": "x" });
+/// let sizes = jsonpointer_flatten::size_map(&value);
+///
+/// assert_eq!(sizes[""], serde_json::to_string(&value).unwrap().len() as u64);
+/// assert_eq!(sizes["/a"], 6);
+/// assert_eq!(sizes["/a/1"], 2);
+/// ```
+pub fn size_map(value: &Value) -> BTreeMap<String, u64> {
+    let mut sizes = BTreeMap::new();
+    let mut pointer = String::new();
+    measure(value, &mut pointer, &mut sizes);
+    sizes
+}
+
+/// The `n` largest subtrees, none of which is an ancestor of another
+///
+/// A subtree always gives way to the subtrees below it, so the candidates
+/// are the most specific ones, scalars and empty containers, and the result
+/// points at the values that account for the size rather than at the root or
+/// the containers around them. Results are sorted by size, largest first,
+/// ties in [`pointer::compare`] order.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "a": { "blob": "xxxxxxxxxxxxxxxxxxxx", "n": 1 }, "b": "yyyyyyyyyy", "c": 1 });
+/// let largest = jsonpointer_flatten::largest_subtrees(&value, 2);
+///
+/// assert_eq!(largest, vec![("/a/blob".to_owned(), 22), ("/b".to_owned(), 12)]);
+/// ```
+pub fn largest_subtrees(value: &Value, n: usize) -> Vec<(String, u64)> {
+    let sizes = size_map(value);
+    let parents: HashSet<&str> = sizes.keys().filter_map(|p| pointer::parent(p)).collect();
+    let mut candidates: Vec<(&str, u64)> = sizes
+        .iter()
+        .filter(|(p, _)| !parents.contains(p.as_str()))
+        .map(|(p, size)| (p.as_str(), *size))
+        .collect();
+    candidates.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| pointer::compare(a, b)));
+
+    candidates
+        .into_iter()
+        .take(n)
+        .map(|(p, size)| (p.to_owned(), size))
+        .collect()
+}
+
+fn measure(value: &Value, pointer: &mut String, sizes: &mut BTreeMap<String, u64>) -> u64 {
+    let len = pointer.len();
+    let size = match value {
+        Value::Array(arr) => {
+            let mut size = 2 + arr.len().saturating_sub(1) as u64;
+            for (idx, val) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                size += measure(val, pointer, sizes);
+                pointer.truncate(len);
+            }
+            size
+        }
+        Value::Object(obj) => {
+            let mut size = 2 + obj.len().saturating_sub(1) as u64;
+            for (key, val) in obj {
+                pointer.push('/');
+                escape_into(key, pointer);
+                size += serialized_len(key) + 1 + measure(val, pointer, sizes);
+                pointer.truncate(len);
+            }
+            size
+        }
+        _ => serialized_len(value),
+    };
+    sizes.insert(pointer.clone(), size);
+    size
+}
+
+fn serialized_len<T: serde::Serialize + ?Sized>(value: &T) -> u64 {
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+struct Counter(u64);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sizes_match_serialization() {
+        let value = json!({
+            "quote\"d": ["a\nb", null, true, -1.5e10, {}],
+            "empty": [],
+            "nested": { "x": { "y": "ü" } }
+        });
+
+        let sizes = size_map(&value);
+
+        for (pointer, size) in &sizes {
+            let subtree = value.pointer(pointer).unwrap();
+            assert_eq!(
+                *size,
+                serde_json::to_string(subtree).unwrap().len() as u64,
+                "{}",
+                pointer
+            );
+        }
+        assert_eq!(
+            sizes.len(),
+            crate::from_json(&value).as_object().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn largest_subtrees_excludes_ancestors() {
+        let value = json!({
+            "items": [{ "payload": "0123456789012345678901234567890123456789" }],
+            "meta": "0123456789",
+            "id": 1
+        });
+
+        let largest = largest_subtrees(&value, 3);
+
+        assert_eq!(
+            largest.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>(),
+            vec!["/items/0/payload", "/meta", "/id"]
+        );
+    }
+
+    #[test]
+    fn largest_subtree_is_never_an_ancestor() {
+        let value = json!({
+            "a": { "b": { "c": "0123456789", "d": [1, 2, 3] }, "e": "01234" },
+            "f": [[], {}],
+            "g": "0123"
+        });
+
+        assert_eq!(largest_subtrees(&value, 1), vec![("/a/b/c".to_owned(), 12)]);
+        assert_eq!(
+            largest_subtrees(&value, 5),
+            vec![
+                ("/a/b/c".to_owned(), 12),
+                ("/a/e".to_owned(), 7),
+                ("/g".to_owned(), 6),
+                ("/f/0".to_owned(), 2),
+                ("/f/1".to_owned(), 2),
+            ]
+        );
+        assert_eq!(largest_subtrees(&value, 100).len(), 8);
+    }
+
+    #[test]
+    fn largest_subtrees_of_small_document() {
+        assert_eq!(largest_subtrees(&json!(true), 5), vec![("".to_owned(), 4)]);
+        assert!(largest_subtrees(&json!({ "a": 1 }), 0).is_empty());
+    }
+}
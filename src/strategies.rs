@@ -0,0 +1,194 @@
+//! [proptest](https://docs.rs/proptest) strategies for documents, pointers and flattened documents
+//!
+//! Generated documents are meant to be hard on code consuming flattened
+//! output: keys needing escapes, empty and numeric keys, empty containers and
+//! deep nesting all come up often. Everything is tuned with a [`ValueConfig`].
+//!
+//! # Example
+//!
+//! ```
+//! use jsonpointer_flatten::strategies::{json_value, ValueConfig};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     fn unflatten_inverts_flatten(value in json_value(&ValueConfig::new().max_depth(3))) {
+//!         let flattened = jsonpointer_flatten::from_json(&value);
+//!         prop_assert_eq!(jsonpointer_flatten::unflatten(&flattened), Ok(value));
+//!     }
+//! }
+//! # unflatten_inverts_flatten();
+//! ```
+use crate::FlattenedDocument;
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+use serde_json::Value;
+
+/// Shape of the documents generated by [`json_value`] and the other strategies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueConfig {
+    /// Maximum nesting of containers, 0 for leaves only (4 by default)
+    pub max_depth: u32,
+    /// Maximum number of elements or members of any container (4 by default)
+    pub max_children: usize,
+    /// Characters object keys are made of, up to 3 at a time
+    ///
+    /// By default `a`, `b`, `0`, `1`, `~`, `/`, `é` and a space, so that
+    /// keys like `""`, `"01"`, `"~1"` or `"a/b"` are common.
+    pub key_alphabet: Vec<char>,
+    /// Whether keys may contain `~` and `/`, which pointers escape (on by default)
+    pub escapable_keys: bool,
+}
+
+impl Default for ValueConfig {
+    fn default() -> Self {
+        ValueConfig {
+            max_depth: 4,
+            max_children: 4,
+            key_alphabet: vec!['a', 'b', '0', '1', '~', '/', 'é', ' '],
+            escapable_keys: true,
+        }
+    }
+}
+
+impl ValueConfig {
+    /// Create the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the nesting of containers
+    pub fn max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Limit the number of children of any container
+    pub fn max_children(mut self, children: usize) -> Self {
+        self.max_children = children;
+        self
+    }
+
+    /// Make object keys of these characters
+    pub fn key_alphabet(mut self, alphabet: impl IntoIterator<Item = char>) -> Self {
+        self.key_alphabet = alphabet.into_iter().collect();
+        self
+    }
+
+    /// Allow or leave out `~` and `/` in keys, whatever the alphabet
+    pub fn escapable_keys(mut self, enabled: bool) -> Self {
+        self.escapable_keys = enabled;
+        self
+    }
+
+    fn keys(&self) -> BoxedStrategy<String> {
+        let alphabet: Vec<char> = self
+            .key_alphabet
+            .iter()
+            .copied()
+            .filter(|c| self.escapable_keys || (*c != '~' && *c != '/'))
+            .collect();
+        if alphabet.is_empty() {
+            return Just(String::new()).boxed();
+        }
+        vec(select(alphabet), 0..=3)
+            .prop_map(|chars| chars.into_iter().collect())
+            .boxed()
+    }
+}
+
+/// Nested JSON values shaped by `config`
+///
+/// Leaves are nulls, booleans, integers, finite floats and strings, some of
+/// them made of the key alphabet.
+pub fn json_value(config: &ValueConfig) -> BoxedStrategy<Value> {
+    let keys = config.keys();
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        (-1e9..1e9f64).prop_map(Value::from),
+        keys.clone().prop_map(Value::from),
+        any::<String>().prop_map(Value::from),
+    ];
+    let max_children = config.max_children;
+    leaf.prop_recursive(config.max_depth, 256, max_children as u32, move |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..=max_children).prop_map(Value::Array),
+            vec((keys.clone(), inner), 0..=max_children)
+                .prop_map(|members| Value::Object(members.into_iter().collect())),
+        ]
+    })
+    .boxed()
+}
+
+/// Valid JSON Pointers of up to `max_depth` segments, keys and indices mixed
+pub fn pointer(config: &ValueConfig) -> BoxedStrategy<String> {
+    let index = (0..config.max_children.max(1)).prop_map(|idx| idx.to_string());
+    let segment = prop_oneof![index, config.keys()];
+    vec(segment, 0..=config.max_depth as usize)
+        .prop_map(|segments| {
+            segments
+                .iter()
+                .map(|segment| format!("/{}", crate::pointer::escape(segment)))
+                .collect()
+        })
+        .boxed()
+}
+
+/// Flattened forms of the documents of [`json_value`]
+pub fn flattened_document(config: &ValueConfig) -> BoxedStrategy<FlattenedDocument> {
+    json_value(config)
+        .prop_map(|value| FlattenedDocument::from(&value))
+        .boxed()
+}
+
+impl Arbitrary for FlattenedDocument {
+    type Parameters = ValueConfig;
+    type Strategy = BoxedStrategy<FlattenedDocument>;
+
+    fn arbitrary_with(config: ValueConfig) -> Self::Strategy {
+        flattened_document(&config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_json, has_keys_requiring_escape, unflatten};
+
+    proptest! {
+        #[test]
+        fn unflatten_inverts_flatten(value in json_value(&ValueConfig::default())) {
+            prop_assert_eq!(unflatten(&from_json(&value)), Ok(value));
+        }
+
+        #[test]
+        fn documents_flatten_their_unflattened_value(doc in any::<FlattenedDocument>()) {
+            let value = doc.unflatten().unwrap();
+            prop_assert_eq!(FlattenedDocument::from(&value), doc);
+        }
+
+        #[test]
+        fn pointers_are_valid(ptr in pointer(&ValueConfig::default())) {
+            prop_assert!(crate::pointer::validate(&ptr).is_ok());
+            let segments: Vec<_> = crate::pointer::segments(&ptr).collect();
+            prop_assert_eq!(segments.len(), crate::pointer::depth(&ptr));
+        }
+
+        #[test]
+        fn config_bounds_generated_values(
+            value in json_value(&ValueConfig::new().max_depth(2).max_children(2).escapable_keys(false))
+        ) {
+            prop_assert!(!has_keys_requiring_escape(&value));
+            prop_assert!(crate::iter(&value).all(|(ptr, _)| crate::pointer::depth(&ptr) <= 2));
+            let width = |node: &Value| match node {
+                Value::Array(arr) => arr.len(),
+                Value::Object(obj) => obj.len(),
+                _ => 0,
+            };
+            prop_assert!(crate::iter(&value).all(|(_, node)| width(node) <= 2));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::StreamError;
+use crate::Flattener;
+use serde_json::de::IoRead;
+use serde_json::{Deserializer, StreamDeserializer, Value};
+use std::io;
+
+/// Iterator over the flattened documents of a JSON stream, see [`flatten_stream`]
+pub struct FlattenStream<R: io::Read> {
+    documents: StreamDeserializer<'static, IoRead<R>, Value>,
+    flattener: Flattener,
+}
+
+/// Lazily flatten every document of a stream of concatenated JSON documents
+///
+/// Documents may be separated by whitespace or follow each other directly, as
+/// in `{"a":1}{"b":2}`; numbers and literals like `true` need whitespace
+/// between them. Each document is read and flattened as soon as it is
+/// complete, so only one document is held in memory at a time. Reading is
+/// byte by byte, so wrap unbuffered readers in an [`io::BufReader`].
+///
+/// A parse error is yielded as [`StreamError::Parse`] and ends the stream.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let stream = r#"{ "id": 1 } {"id":2}{"id":3}"#.as_bytes();
+/// let documents: Vec<_> = jsonpointer_flatten::flatten_stream(stream)
+///     .map(Result::unwrap)
+///     .collect();
+///
+/// assert_eq!(documents.len(), 3);
+/// assert_eq!(documents[2], json!({ "": {}, "/id": 3 }));
+/// ```
+pub fn flatten_stream<R: io::Read>(rdr: R) -> FlattenStream<R> {
+    Flattener::new().flatten_stream(rdr)
+}
+
+impl Flattener {
+    /// Like [`flatten_stream`], flattening each document with
+    /// [`Flattener::try_flatten`] so the configured [`Limits`](crate::Limits) apply
+    ///
+    /// A document exceeding a limit is yielded as [`StreamError::Flatten`] and
+    /// the stream continues with the next one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{Flattener, Limits, StreamError};
+    ///
+    /// let stream = "[1] [1, 2, 3] [4]".as_bytes();
+    /// let flattener = Flattener::new().limits(Limits::new().max_entries(3));
+    /// let results: Vec<_> = flattener.flatten_stream(stream).collect();
+    ///
+    /// assert!(results[0].is_ok());
+    /// assert!(matches!(results[1], Err(StreamError::Flatten { offset: 13, .. })));
+    /// assert!(results[2].is_ok());
+    /// ```
+    pub fn flatten_stream<R: io::Read>(self, rdr: R) -> FlattenStream<R> {
+        FlattenStream {
+            documents: Deserializer::from_reader(rdr).into_iter(),
+            flattener: self,
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for FlattenStream<R> {
+    type Item = Result<Value, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let document = self.documents.next()?;
+        let offset = self.documents.byte_offset();
+
+        Some(match document {
+            Ok(value) => self
+                .flattener
+                .try_flatten(&value)
+                .map_err(|error| StreamError::Flatten { offset, error }),
+            Err(error) => Err(StreamError::Parse { offset, error }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FlattenError, Limits};
+    use serde_json::json;
+
+    /// Hands out at most one byte per read, like a slow socket
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(slot)) => {
+                    *slot = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn splits_whitespace_separated_and_back_to_back_documents() {
+        let stream = b"{\"a\":[1]}\n\n[true]{\"b\":{}}\"s\" 1 null";
+
+        let documents: Vec<Value> = flatten_stream(Trickle(stream))
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            documents,
+            vec![
+                json!({ "": {}, "/a": [], "/a/0": 1 }),
+                json!({ "": [], "/0": true }),
+                json!({ "": {}, "/b": {} }),
+                json!({ "": "s" }),
+                json!({ "": 1 }),
+                json!({ "": null }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_error_reports_offset_and_ends_stream() {
+        let stream = "{\"a\":1}  {\"b\" 2} {\"c\":3}";
+
+        let mut documents = flatten_stream(stream.as_bytes());
+
+        assert!(documents.next().unwrap().is_ok());
+        match documents.next() {
+            Some(Err(StreamError::Parse { offset, .. })) => assert_eq!(offset, 9),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(documents.next().is_none());
+    }
+
+    #[test]
+    fn flatten_errors_skip_the_document() {
+        let flattener = Flattener::new().limits(Limits::new().max_depth(1));
+
+        let results: Vec<_> = flattener
+            .flatten_stream("[1] [[2]] [3]".as_bytes())
+            .collect();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].as_ref().unwrap(), &json!({ "": [], "/0": 3 }));
+        match &results[1] {
+            Err(StreamError::Flatten { offset, error }) => {
+                assert_eq!(*offset, 9);
+                assert_eq!(
+                    *error,
+                    FlattenError::DepthLimitExceeded {
+                        limit: 1,
+                        pointer: "/0/0".to_owned()
+                    }
+                );
+            }
+            other => panic!("expected a flatten error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::error::StrictParseError;
+use crate::pointer::escape_into;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt::{self, Write};
+
+/// Flatten a JSON string, failing on objects that repeat a key
+///
+/// [`from_str`](crate::from_str) keeps the last of the members sharing a key,
+/// like serde_json does. Here the document is checked while it is parsed,
+/// at any depth, and the first repeated key is reported with the pointer of
+/// its member and where in the text it was found.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::StrictParseError;
+///
+/// let text = r#"{ "users": [{ "id": 1, "name": "Jo", "id": 2 }] }"#;
+///
+/// assert!(jsonpointer_flatten::from_str(text).is_ok());
+/// match jsonpointer_flatten::from_str_strict(text) {
+///     Err(StrictParseError::DuplicateKey { key, pointer, .. }) => {
+///         assert_eq!(key, "id");
+///         assert_eq!(pointer, "/users/0/id");
+///     }
+///     other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+pub fn from_str_strict(s: &str) -> Result<Value, StrictParseError> {
+    let mut duplicate = None;
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let parsed = Node {
+        pointer: &mut String::new(),
+        duplicate: &mut duplicate,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|value| deserializer.end().map(|()| value));
+
+    match (parsed, duplicate) {
+        (Ok(value), _) => Ok(crate::from_json(&value)),
+        (Err(error), Some((key, pointer))) => Err(StrictParseError::DuplicateKey {
+            key,
+            pointer,
+            line: error.line(),
+            column: error.column(),
+        }),
+        (Err(error), None) => Err(StrictParseError::Parse(error)),
+    }
+}
+
+/// Deserializes the value at `pointer`, recording the first repeated key as `(key, pointer)`
+struct Node<'a> {
+    pointer: &'a mut String,
+    duplicate: &'a mut Option<(String, String)>,
+}
+
+impl Node<'_> {
+    fn child(&mut self) -> Node<'_> {
+        Node {
+            pointer: self.pointer,
+            duplicate: self.duplicate,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Node<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Node<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Value, A::Error> {
+        let len = self.pointer.len();
+        let mut elements = Vec::new();
+        loop {
+            let _ = write!(self.pointer, "/{}", elements.len());
+            let element = seq.next_element_seed(self.child())?;
+            self.pointer.truncate(len);
+            match element {
+                Some(element) => elements.push(element),
+                None => return Ok(Value::Array(elements)),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Value, A::Error> {
+        let len = self.pointer.len();
+        let mut members = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            if members.is_empty() && key == "$serde_json::private::Number" {
+                let number: String = map.next_value()?;
+                return serde_json::from_str(&number).map_err(de::Error::custom);
+            }
+            self.pointer.push('/');
+            escape_into(&key, self.pointer);
+            if members.contains_key(&key) {
+                let message = format!("duplicate key \"{}\" at \"{}\"", key, self.pointer);
+                *self.duplicate = Some((key, self.pointer.clone()));
+                return Err(de::Error::custom(message));
+            }
+            let value = map.next_value_seed(self.child())?;
+            self.pointer.truncate(len);
+            members.insert(key, value);
+        }
+        Ok(Value::Object(members))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn duplicate(text: &str) -> (String, String, usize, usize) {
+        match from_str_strict(text) {
+            Err(StrictParseError::DuplicateKey {
+                key,
+                pointer,
+                line,
+                column,
+            }) => (key, pointer, line, column),
+            other => panic!("expected a duplicate key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn documents_without_duplicates_flatten_as_usual() {
+        let text = r#"{ "a": [{ "b": 1 }, { "b": 2.5 }], "c~d": { "": null }, "e": "x" }"#;
+
+        assert_eq!(
+            from_str_strict(text).unwrap(),
+            crate::from_str(text).unwrap()
+        );
+        assert_eq!(from_str_strict("[]").unwrap(), json!({ "": [] }));
+    }
+
+    #[test]
+    fn nested_duplicates_name_their_pointer() {
+        assert_eq!(
+            duplicate(r#"{"a":1,"a":2}"#),
+            ("a".to_owned(), "/a".to_owned(), 1, 10)
+        );
+        assert_eq!(
+            duplicate("[0, {\"x\": [{\"a/b\": 1,\n \"a/b\": 2}]}]"),
+            ("a/b".to_owned(), "/1/x/0/a~1b".to_owned(), 2, 6)
+        );
+        // the same key in sibling objects is fine, and only the first repeat is reported
+        assert_eq!(
+            duplicate(r#"{"s": {"k": 1}, "t": {"k": 2, "~": 0, "~": 1, "k": 3}}"#).1,
+            "/t/~0"
+        );
+        // keys compare after unescaping
+        assert_eq!(duplicate(r#"{"A": 1, "\u0041": 2}"#).1, "/A");
+    }
+
+    #[test]
+    fn other_errors_come_from_serde_json() {
+        assert!(matches!(
+            from_str_strict(r#"{"a": }"#),
+            Err(StrictParseError::Parse(_))
+        ));
+        assert!(matches!(
+            from_str_strict("[1] [2]"),
+            Err(StrictParseError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+use crate::error::TableError;
+use crate::flattener::{entry_value, is_container};
+use crate::pointer::{self, escape_into};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How arrays inside the elements of [`to_table`] are turned into cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NestedArrays {
+    /// One cell holding the array encoded as a JSON string (the default)
+    #[default]
+    Encode,
+    /// One column per array element, through this many levels of nested
+    /// arrays. Arrays nested deeper are encoded.
+    Explode(usize),
+    /// Fail with [`TableError::NestedArray`]
+    Error,
+}
+
+/// Options for [`to_table_with`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableOptions {
+    nested_arrays: NestedArrays,
+}
+
+impl TableOptions {
+    /// Create options that encode nested arrays
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how arrays inside elements are handled
+    pub fn nested_arrays(mut self, nested_arrays: NestedArrays) -> Self {
+        self.nested_arrays = nested_arrays;
+        self
+    }
+}
+
+/// An array of objects reshaped into rows and columns, see [`to_table`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    /// Pointers of the cells, relative to their element, in the order they first appear
+    pub columns: Vec<String>,
+    /// One row per element with a cell per column, `null` where the element has no value
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Write the table as CSV, with the columns as the header row
+    ///
+    /// `null` cells are left empty, strings are written as they are and any
+    /// other value as JSON. Fields are quoted when they contain a comma, a
+    /// quote or a line break, and every row ends with `\n`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_json::json;
+    ///
+    /// let value = json!([{ "id": 1, "name": "Smith, John" }, { "id": 2 }]);
+    /// let table = jsonpointer_flatten::to_table(&value, "").unwrap();
+    ///
+    /// assert_eq!(table.to_csv(), "/id,/name\n1,\"Smith, John\"\n2,\n");
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        write_record(&mut csv, self.columns.iter().map(String::as_str));
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(csv_cell).collect();
+            write_record(&mut csv, cells.iter().map(String::as_str));
+        }
+        csv
+    }
+}
+
+/// Reshape the array at `array_pointer` into a table with one row per element
+///
+/// Columns are the union of the pointers of every leaf inside the elements,
+/// relative to the element, so an element `{ "a": { "b": 1 } }` has a cell
+/// `/a/b`. Elements that aren't objects get a `""` column. Empty objects and
+/// arrays are cells of their own. Nested arrays are JSON-encoded, see
+/// [`to_table_with`] for alternatives.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "users": [
+///     { "address": { "zip": "00000" }, "name": "John" },
+///     { "name": "Jane", "tags": ["admin"] }
+/// ]});
+/// let table = jsonpointer_flatten::to_table(&value, "/users").unwrap();
+///
+/// assert_eq!(table.columns, vec!["/address/zip", "/name", "/tags"]);
+/// assert_eq!(table.rows[1], vec![json!(null), json!("Jane"), json!(r#"["admin"]"#)]);
+/// ```
+pub fn to_table(value: &Value, array_pointer: &str) -> Result<Table, TableError> {
+    to_table_with(value, array_pointer, &TableOptions::default())
+}
+
+/// Like [`to_table`], with the given options
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{to_table_with, NestedArrays, TableOptions};
+/// use serde_json::json;
+///
+/// let value = json!([{ "tags": ["a", "b"] }, { "tags": ["c"] }]);
+/// let options = TableOptions::new().nested_arrays(NestedArrays::Explode(1));
+/// let table = to_table_with(&value, "", &options).unwrap();
+///
+/// assert_eq!(table.columns, vec!["/tags/0", "/tags/1"]);
+/// assert_eq!(table.rows[1], vec![json!("c"), json!(null)]);
+/// ```
+pub fn to_table_with(
+    value: &Value,
+    array_pointer: &str,
+    options: &TableOptions,
+) -> Result<Table, TableError> {
+    pointer::validate(array_pointer)?;
+    let elements = match value.pointer(array_pointer) {
+        Some(Value::Array(elements)) => elements,
+        Some(_) => {
+            return Err(TableError::NotAnArray {
+                pointer: array_pointer.to_owned(),
+            })
+        }
+        None => {
+            return Err(TableError::NotFound {
+                pointer: array_pointer.to_owned(),
+            })
+        }
+    };
+
+    let mut builder = Builder {
+        nested_arrays: options.nested_arrays,
+        table: Table::default(),
+        indices: HashMap::new(),
+        pointer: String::new(),
+    };
+    for (idx, element) in elements.iter().enumerate() {
+        builder.pointer.clear();
+        builder.table.rows.push(Vec::new());
+        let prefix = format!("{}/{}", array_pointer, idx);
+        builder.cells(element, &prefix, 0)?;
+    }
+
+    let mut table = builder.table;
+    for row in &mut table.rows {
+        row.resize(table.columns.len(), Value::Null);
+    }
+    Ok(table)
+}
+
+struct Builder {
+    nested_arrays: NestedArrays,
+    table: Table,
+    /// Column index by relative pointer
+    indices: HashMap<String, usize>,
+    /// Pointer of the current node, relative to its element
+    pointer: String,
+}
+
+impl Builder {
+    /// Add the cells of `value` to the last row, `prefix` being the pointer of its element
+    fn cells(&mut self, value: &Value, prefix: &str, arrays: usize) -> Result<(), TableError> {
+        let len = self.pointer.len();
+        match value {
+            Value::Array(arr) => match self.nested_arrays {
+                NestedArrays::Error => {
+                    return Err(TableError::NestedArray {
+                        pointer: format!("{}{}", prefix, self.pointer),
+                    })
+                }
+                NestedArrays::Explode(depth) if arrays < depth && !arr.is_empty() => {
+                    for (idx, element) in arr.iter().enumerate() {
+                        self.pointer.push('/');
+                        self.pointer.push_str(&idx.to_string());
+                        self.cells(element, prefix, arrays + 1)?;
+                        self.pointer.truncate(len);
+                    }
+                }
+                NestedArrays::Explode(depth) if arrays < depth => {
+                    self.cell(Value::Array(Vec::new()))
+                }
+                _ => self.cell(Value::String(value.to_string())),
+            },
+            Value::Object(obj) if is_container(value) && !obj.is_empty() => {
+                for (key, val) in obj {
+                    self.pointer.push('/');
+                    escape_into(key, &mut self.pointer);
+                    self.cells(val, prefix, arrays)?;
+                    self.pointer.truncate(len);
+                }
+            }
+            _ => self.cell(entry_value(value)),
+        }
+        Ok(())
+    }
+
+    fn cell(&mut self, value: Value) {
+        let next = self.indices.len();
+        let column = *self.indices.entry(self.pointer.clone()).or_insert(next);
+        if column == next {
+            self.table.columns.push(self.pointer.clone());
+        }
+
+        let row = self
+            .table
+            .rows
+            .last_mut()
+            .expect("a row is pushed per element");
+        if row.len() <= column {
+            row.resize(column + 1, Value::Null);
+        }
+        row[column] = value;
+    }
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn write_record<'a>(csv: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (idx, field) in fields.enumerate() {
+        if idx > 0 {
+            csv.push(',');
+        }
+        if field.contains(&[',', '"', '\r', '\n'][..]) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(field);
+        }
+    }
+    csv.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn explode(depth: usize) -> TableOptions {
+        TableOptions::new().nested_arrays(NestedArrays::Explode(depth))
+    }
+
+    #[test]
+    fn union_of_columns_in_first_seen_order() {
+        let value = json!([
+            { "a": 1, "b": { "c": true } },
+            { "d": null, "a": 2 },
+            "scalar",
+            { "e": {}, "f": [] }
+        ]);
+
+        let table = to_table(&value, "").unwrap();
+
+        assert_eq!(table.columns, vec!["/a", "/b/c", "/d", "", "/e", "/f"]);
+        assert_eq!(
+            table.rows,
+            vec![
+                vec![
+                    json!(1),
+                    json!(true),
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!(null)
+                ],
+                vec![
+                    json!(2),
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!(null)
+                ],
+                vec![
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!("scalar"),
+                    json!(null),
+                    json!(null)
+                ],
+                vec![
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!(null),
+                    json!({}),
+                    json!("[]")
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_arrays() {
+        let value = json!({ "rows": [{ "m": [[1, 2], [3]] }] });
+
+        let encoded = to_table(&value, "/rows").unwrap();
+        assert_eq!(encoded.columns, vec!["/m"]);
+        assert_eq!(encoded.rows, vec![vec![json!("[[1,2],[3]]")]]);
+
+        let once = to_table_with(&value, "/rows", &explode(1)).unwrap();
+        assert_eq!(once.columns, vec!["/m/0", "/m/1"]);
+        assert_eq!(once.rows, vec![vec![json!("[1,2]"), json!("[3]")]]);
+
+        let twice = to_table_with(&value, "/rows", &explode(2)).unwrap();
+        assert_eq!(twice.columns, vec!["/m/0/0", "/m/0/1", "/m/1/0"]);
+        assert_eq!(
+            to_table_with(&json!([{ "e": [] }]), "", &explode(1))
+                .unwrap()
+                .rows,
+            vec![vec![json!([])]]
+        );
+
+        let strict = TableOptions::new().nested_arrays(NestedArrays::Error);
+        assert_eq!(
+            to_table_with(&value, "/rows", &strict),
+            Err(TableError::NestedArray {
+                pointer: "/rows/0/m".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn array_pointer_errors() {
+        let value = json!({ "a": { "b": 1 } });
+
+        assert_eq!(
+            to_table(&value, "/a"),
+            Err(TableError::NotAnArray {
+                pointer: "/a".to_owned()
+            })
+        );
+        assert_eq!(
+            to_table(&value, "/x"),
+            Err(TableError::NotFound {
+                pointer: "/x".to_owned()
+            })
+        );
+        assert!(matches!(
+            to_table(&value, "a"),
+            Err(TableError::InvalidPointer(_))
+        ));
+        assert_eq!(to_table(&json!([]), ""), Ok(Table::default()));
+    }
+
+    #[test]
+    fn csv_quoting() {
+        let table = Table {
+            columns: vec!["/a,b".to_owned(), "/c".to_owned()],
+            rows: vec![
+                vec![json!("say \"hi\""), json!({})],
+                vec![json!("two\nlines"), json!(1.5)],
+            ],
+        };
+
+        assert_eq!(
+            table.to_csv(),
+            "\"/a,b\",/c\n\"say \"\"hi\"\"\",{}\n\"two\nlines\",1.5\n"
+        );
+    }
+}
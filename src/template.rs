@@ -0,0 +1,324 @@
+use crate::error::TemplateError;
+use crate::pointer::{self, escape_into};
+use crate::FlattenedDocument;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    /// A decoded segment matched literally
+    Literal(String),
+    /// `{name}`, exactly one segment
+    Placeholder(String),
+}
+
+/// A JSON Pointer with named placeholders, for matching and building pointers
+///
+/// Templates are written like pointers. A segment that is exactly `{name}`,
+/// the name made of ASCII letters, digits and `_`, matches any single segment
+/// and captures it decoded. Other segments match literally after decoding `~0`
+/// and `~1` as in pointers, with `{{` and `}}` standing for literal braces.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::PointerTemplate;
+///
+/// let template = PointerTemplate::new("/users/{user}/roles/{role}").unwrap();
+///
+/// let captures = template.match_pointer("/users/3/roles/a~1b").unwrap();
+/// assert_eq!(captures.get("user"), Some("3"));
+/// assert_eq!(captures.get("role"), Some("a/b"));
+/// assert!(template.match_pointer("/users/3/roles").is_none());
+///
+/// assert_eq!(
+///     template.render(&[("user", "7"), ("role", "x~y")]),
+///     Ok("/users/7/roles/x~0y".to_owned())
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+/// Segments captured by [`PointerTemplate::match_pointer`], decoded, in template order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    captures: Vec<(String, String)>,
+}
+
+impl Captures {
+    /// The segment captured by the placeholder `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.captures
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Iterate over `(name, segment)` pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.captures
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Number of captured segments
+    pub fn len(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// Whether the template has no placeholders
+    pub fn is_empty(&self) -> bool {
+        self.captures.is_empty()
+    }
+}
+
+impl PointerTemplate {
+    /// Compile a template, failing on syntax errors
+    pub fn new(template: &str) -> Result<Self, TemplateError> {
+        if !template.is_empty() && !template.starts_with('/') {
+            return Err(TemplateError::MissingLeadingSlash(template.to_owned()));
+        }
+
+        let mut parts = Vec::new();
+        for segment in template.split('/').skip(1) {
+            let part = parse_segment(segment)?;
+            if let Part::Placeholder(name) = &part {
+                if parts.contains(&part) {
+                    return Err(TemplateError::DuplicatePlaceholder(name.clone()));
+                }
+            }
+            parts.push(part);
+        }
+
+        Ok(PointerTemplate {
+            source: template.to_owned(),
+            parts,
+        })
+    }
+
+    /// The template as it was written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The placeholder names in template order
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Placeholder(name) => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Match the whole `pointer`, capturing the segments of every placeholder
+    pub fn match_pointer(&self, pointer: &str) -> Option<Captures> {
+        let mut segments = pointer::segments(pointer);
+        let mut captures = Vec::new();
+        for part in &self.parts {
+            let segment = segments.next()?;
+            match part {
+                Part::Literal(literal) if *literal == segment => {}
+                Part::Literal(_) => return None,
+                Part::Placeholder(name) => captures.push((name.clone(), segment.into_owned())),
+            }
+        }
+        match segments.next() {
+            Some(_) => None,
+            None => Some(Captures { captures }),
+        }
+    }
+
+    /// Build a pointer, replacing each placeholder by its decoded segment in `values`
+    ///
+    /// Values are escaped as pointer segments, and names not in the template are ignored.
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<String, TemplateError> {
+        let mut pointer = String::with_capacity(self.source.len());
+        for part in &self.parts {
+            pointer.push('/');
+            match part {
+                Part::Literal(literal) => escape_into(literal, &mut pointer),
+                Part::Placeholder(name) => {
+                    let value = values
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .ok_or_else(|| TemplateError::MissingValue(name.clone()))?;
+                    escape_into(value.1, &mut pointer);
+                }
+            }
+        }
+        Ok(pointer)
+    }
+}
+
+impl FromStr for PointerTemplate {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PointerTemplate::new(s)
+    }
+}
+
+impl fmt::Display for PointerTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FlattenedDocument {
+    /// Iterate over the entries whose pointer matches `template`, with their captures
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use jsonpointer_flatten::{FlattenedDocument, PointerTemplate};
+    /// use serde_json::json;
+    ///
+    /// let doc = FlattenedDocument::from(&json!({ "users": [{ "roles": ["admin", "dev"] }] }));
+    /// let template: PointerTemplate = "/users/{i}/roles/{j}".parse().unwrap();
+    ///
+    /// let mut roles: Vec<String> = doc
+    ///     .match_template(&template)
+    ///     .map(|(c, value)| format!("{}.{}={}", c.get("i").unwrap(), c.get("j").unwrap(), value))
+    ///     .collect();
+    /// roles.sort();
+    ///
+    /// assert_eq!(roles, vec!["0.0=\"admin\"", "0.1=\"dev\""]);
+    /// ```
+    pub fn match_template<'a>(
+        &'a self,
+        template: &'a PointerTemplate,
+    ) -> impl Iterator<Item = (Captures, &'a Value)> + 'a {
+        self.iter().filter_map(move |(key, value)| {
+            template
+                .match_pointer(key)
+                .map(|captures| (captures, value))
+        })
+    }
+}
+
+fn parse_segment(segment: &str) -> Result<Part, TemplateError> {
+    if let Some(name) = segment
+        .strip_prefix('{')
+        .and_then(|rest| rest.strip_suffix('}'))
+    {
+        if !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            return Ok(Part::Placeholder(name.to_owned()));
+        }
+    }
+
+    let invalid = || TemplateError::InvalidPlaceholder(segment.to_owned());
+    let mut literal = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => literal.push('~'),
+                Some('1') => literal.push('/'),
+                _ => return Err(TemplateError::InvalidEscape(segment.to_owned())),
+            },
+            '{' | '}' if chars.next() == Some(c) => literal.push(c),
+            '{' | '}' => return Err(invalid()),
+            _ => literal.push(c),
+        }
+    }
+    Ok(Part::Literal(literal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn template(template: &str) -> PointerTemplate {
+        PointerTemplate::new(template).unwrap()
+    }
+
+    #[test]
+    fn placeholders_match_exactly_one_segment() {
+        let t = template("/users/{i}/roles/{j}");
+
+        let captures = t.match_pointer("/users/0/roles/12").unwrap();
+        assert_eq!(
+            captures.iter().collect::<Vec<_>>(),
+            vec![("i", "0"), ("j", "12")]
+        );
+        assert!(t.match_pointer("/users/0/roles").is_none());
+        assert!(t.match_pointer("/users/0/roles/1/x").is_none());
+        assert!(t.match_pointer("/users/0/x/roles/1").is_none());
+        assert!(t.match_pointer("/admins/0/roles/1").is_none());
+        assert_eq!(
+            template("/{key}").match_pointer("/").unwrap().get("key"),
+            Some("")
+        );
+        assert!(template("").match_pointer("").unwrap().is_empty());
+        assert_eq!(t.placeholders().collect::<Vec<_>>(), vec!["i", "j"]);
+    }
+
+    #[test]
+    fn literal_braces_and_escapes() {
+        let t = template("/{{id}}/a~1b/{x}");
+
+        assert!(t.match_pointer("/{id}/a~1b/1").is_some());
+        assert!(t.match_pointer("/7/a~1b/1").is_none());
+        assert_eq!(t.render(&[("x", "v")]), Ok("/{id}/a~1b/v".to_owned()));
+    }
+
+    #[test]
+    fn render_round_trips_captures() {
+        let t = template("/users/{user}/roles/{role}");
+        let pointer = "/users/~0me/roles/a~1b";
+
+        let captures = t.match_pointer(pointer).unwrap();
+        let values: Vec<(&str, &str)> = captures.iter().collect();
+
+        assert_eq!(t.render(&values), Ok(pointer.to_owned()));
+        assert_eq!(
+            t.render(&[("user", "1")]),
+            Err(TemplateError::MissingValue("role".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_templates_error() {
+        assert_eq!(
+            PointerTemplate::new("users/{i}"),
+            Err(TemplateError::MissingLeadingSlash("users/{i}".to_owned()))
+        );
+        assert_eq!(
+            PointerTemplate::new("/users/{i}x"),
+            Err(TemplateError::InvalidPlaceholder("{i}x".to_owned()))
+        );
+        assert_eq!(
+            PointerTemplate::new("/{a b}"),
+            Err(TemplateError::InvalidPlaceholder("{a b}".to_owned()))
+        );
+        assert_eq!(
+            PointerTemplate::new("/{}"),
+            Err(TemplateError::InvalidPlaceholder("{}".to_owned()))
+        );
+        assert_eq!(
+            PointerTemplate::new("/a~2"),
+            Err(TemplateError::InvalidEscape("a~2".to_owned()))
+        );
+        assert_eq!(
+            PointerTemplate::new("/{i}/{i}"),
+            Err(TemplateError::DuplicatePlaceholder("i".to_owned()))
+        );
+    }
+
+    #[test]
+    fn match_template_over_document() {
+        let doc = FlattenedDocument::from(&json!({ "a": { "x": 1, "y": { "z": 2 } } }));
+
+        let t = template("/a/{k}");
+        let matched: Vec<(Captures, &Value)> = doc.match_template(&t).collect();
+
+        assert_eq!(matched.len(), 2);
+        assert!(matched
+            .iter()
+            .any(|(c, v)| c.get("k") == Some("y") && **v == json!({})));
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers shared by the unit tests
+use serde_json::{json, Map, Value};
+
+/// Small deterministic generator, so failures reproduce
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+
+    pub(crate) fn value(&mut self, depth: u32) -> Value {
+        const KEYS: [&str; 8] = ["a", "b", "10", "2", "a/b", "~", "", "é"];
+        let kind = if depth == 0 {
+            self.below(4)
+        } else {
+            self.below(6)
+        };
+        match kind {
+            0 => Value::Null,
+            1 => json!(self.below(3) == 0),
+            2 => json!(self.below(4)),
+            3 => json!(KEYS[self.below(8) as usize]),
+            4 => (0..self.below(4)).map(|_| self.value(depth - 1)).collect(),
+            _ => {
+                let mut obj = Map::new();
+                for _ in 0..self.below(5) {
+                    let key = KEYS[self.below(8) as usize].to_owned();
+                    obj.insert(key, self.value(depth - 1));
+                }
+                Value::Object(obj)
+            }
+        }
+    }
+
+    /// A copy of `value` with a few random edits, so both documents share structure
+    pub(crate) fn mutate(&mut self, value: &Value, depth: u32) -> Value {
+        if self.below(8) == 0 {
+            return self.value(depth);
+        }
+        match value {
+            Value::Array(arr) => {
+                let mut arr: Vec<Value> = arr.iter().map(|v| self.mutate(v, depth)).collect();
+                if !arr.is_empty() && self.below(4) == 0 {
+                    arr.pop();
+                }
+                Value::Array(arr)
+            }
+            Value::Object(obj) => {
+                let mut mutated = Map::new();
+                for (key, val) in obj {
+                    if self.below(6) != 0 {
+                        mutated.insert(key.clone(), self.mutate(val, depth));
+                    }
+                }
+                Value::Object(mutated)
+            }
+            _ => value.clone(),
+        }
+    }
+}
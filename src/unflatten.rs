@@ -0,0 +1,651 @@
+use crate::error::UnflattenError;
+use crate::pointer::{self, is_array_index};
+use crate::EntryFormat;
+#[cfg(feature = "unicode-normalization")]
+use crate::KeyNormalization;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Rebuild a nested document from its flattened form
+///
+/// This is the inverse of [`from_json`](crate::from_json). Container
+/// placeholders produced by flattening are optional: a missing container is
+/// created as an array when one of the segments addressing it is an array
+/// index and as an object otherwise. Entry values that are non-empty
+/// containers are inserted as nested values. An empty input produces `null`.
+///
+/// Array indices must be canonical as in RFC 6901: `0` or digits without a
+/// leading zero. Any other segment below an array, like `01`, `-1` or the
+/// append token `-`, fails with [`UnflattenError::InvalidIndex`]. See
+/// [`unflatten_with`] to turn such arrays into objects instead.
+///
+/// Array elements are placed by index, in any entry order. Object members are
+/// inserted in the order their pointers first appear in the input, which only
+/// shows with serde_json's `preserve_order` feature.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = json!({ "name": "John", "phones": ["123", "456"] });
+/// let flattened = jsonpointer_flatten::from_json(&value);
+///
+/// assert_eq!(jsonpointer_flatten::unflatten(&flattened), Ok(value));
+/// ```
+pub fn unflatten(value: &Value) -> Result<Value, UnflattenError> {
+    unflatten_with(value, &UnflattenOptions::default())
+}
+
+/// Options for [`unflatten_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnflattenOptions {
+    strict_indices: bool,
+    root_key: String,
+    format: EntryFormat,
+    #[cfg(feature = "unicode-normalization")]
+    normalization: Option<KeyNormalization>,
+    max_depth: Option<usize>,
+}
+
+impl Default for UnflattenOptions {
+    fn default() -> Self {
+        UnflattenOptions {
+            strict_indices: true,
+            root_key: String::new(),
+            format: EntryFormat::default(),
+            #[cfg(feature = "unicode-normalization")]
+            normalization: None,
+            max_depth: None,
+        }
+    }
+}
+
+impl UnflattenOptions {
+    /// Create options with strict array indices
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether segments that aren't canonical indices are rejected below arrays
+    ///
+    /// When disabled, such a segment makes its parent an object, even if the
+    /// parent has an empty array placeholder.
+    pub fn strict_indices(mut self, strict: bool) -> Self {
+        self.strict_indices = strict;
+        self
+    }
+
+    /// Set the format of entry values, as configured with [`Flattener::entry_format`](crate::Flattener::entry_format)
+    ///
+    /// With [`EntryFormat::Typed`], every entry must be a typed value, and
+    /// containers are rebuilt from their type while their counts are ignored.
+    pub fn entry_format(mut self, format: EntryFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Normalize keys, as configured with [`Flattener::normalize_keys`](crate::Flattener::normalize_keys)
+    ///
+    /// Every segment of every pointer is normalized before rebuilding. When
+    /// several entries normalize to the same pointer, the last one wins.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalize_keys(mut self, normalization: KeyNormalization) -> Self {
+        self.normalization = Some(normalization);
+        self
+    }
+
+    /// Set the key of the root entry, as configured with [`Flattener::root_key`](crate::Flattener::root_key)
+    ///
+    /// An entry with this key is the root, and is not parsed as a pointer.
+    pub fn root_key(mut self, key: impl Into<String>) -> Self {
+        self.root_key = key.into();
+        self
+    }
+
+    /// Reject pointers with more than `depth` segments (unlimited by default)
+    ///
+    /// The value is rebuilt recursively, so a single long key like
+    /// `/a/a/a/...` from untrusted input could otherwise exhaust the stack.
+    /// Keys are checked before anything is built.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+/// Like [`unflatten`], with the given options
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{unflatten_with, UnflattenOptions};
+/// use serde_json::json;
+///
+/// let flat = json!({ "/zip/0": "1", "/zip/01": "2" });
+/// let options = UnflattenOptions::new().strict_indices(false);
+///
+/// assert!(unflatten_with(&flat, &UnflattenOptions::new()).is_err());
+/// assert_eq!(
+///     unflatten_with(&flat, &options),
+///     Ok(json!({ "zip": { "0": "1", "01": "2" } }))
+/// );
+/// ```
+pub fn unflatten_with(value: &Value, options: &UnflattenOptions) -> Result<Value, UnflattenError> {
+    match value {
+        Value::Object(entries) => unflatten_map(entries, options),
+        _ => Err(UnflattenError::NotAnObject),
+    }
+}
+
+pub(crate) fn unflatten_map(
+    entries: &Map<String, Value>,
+    options: &UnflattenOptions,
+) -> Result<Value, UnflattenError> {
+    #[cfg(feature = "unicode-normalization")]
+    if let Some(normalization) = options.normalization {
+        let entries = normalization.apply_entries(entries, &options.root_key);
+        return build(&entries, options);
+    }
+    build(entries, options)
+}
+
+fn build(
+    entries: &Map<String, Value>,
+    options: &UnflattenOptions,
+) -> Result<Value, UnflattenError> {
+    let root = options.root_key.as_str();
+    let mut tree = Tree {
+        entries,
+        children: HashMap::new(),
+        strict: options.strict_indices,
+        typed: options.format == EntryFormat::Typed,
+        root,
+    };
+    let mut seen: HashSet<&str> = HashSet::with_capacity(entries.len());
+    for key in entries.keys() {
+        if key == root {
+            seen.insert("");
+            continue;
+        }
+        pointer::validate(key)?;
+        if let Some(limit) = options
+            .max_depth
+            .filter(|limit| pointer::depth(key) > *limit)
+        {
+            return Err(UnflattenError::DepthLimitExceeded {
+                limit,
+                pointer: key.clone(),
+            });
+        }
+        // register the entry and any ancestors without entries of their own
+        let mut ptr = key.as_str();
+        while seen.insert(ptr) {
+            match pointer::parent(ptr) {
+                Some(parent) => {
+                    tree.children.entry(parent).or_default().push(ptr);
+                    ptr = parent;
+                }
+                None => break,
+            }
+        }
+    }
+
+    if seen.is_empty() {
+        Ok(Value::Null)
+    } else {
+        tree.build("")
+    }
+}
+
+/// Entries indexed by parent, children in the order they were first seen
+struct Tree<'a> {
+    entries: &'a Map<String, Value>,
+    children: HashMap<&'a str, Vec<&'a str>>,
+    strict: bool,
+    typed: bool,
+    root: &'a str,
+}
+
+impl Tree<'_> {
+    fn entry(&self, ptr: &str) -> Option<&Value> {
+        if ptr.is_empty() {
+            self.entries.get(self.root)
+        } else {
+            self.entries.get(ptr)
+        }
+    }
+
+    fn build(&self, ptr: &str) -> Result<Value, UnflattenError> {
+        let children = self.children.get(ptr).map_or(&[][..], Vec::as_slice);
+        let indices = children
+            .iter()
+            .filter(|child| is_array_index(segment(ptr, child)))
+            .count();
+        let lenient_object = !self.strict && indices < children.len();
+        let entry = match self.entry(ptr) {
+            Some(value) if self.typed => {
+                Some(untyped(value).ok_or_else(|| UnflattenError::InvalidEntry {
+                    pointer: ptr.to_owned(),
+                })?)
+            }
+            Some(value) => Some(entry_to_value(value)),
+            None => None,
+        };
+        let mut node = match entry {
+            Some(Value::Array(arr)) if arr.is_empty() && lenient_object => {
+                Value::Object(Map::new())
+            }
+            Some(value) => value,
+            // a single index is enough for strict mode, so that its siblings are rejected
+            None if indices > 0 && !lenient_object => Value::Array(Vec::new()),
+            None => Value::Object(Map::new()),
+        };
+
+        match &mut node {
+            _ if children.is_empty() => {}
+            Value::Array(arr) => {
+                let mut indexed = Vec::with_capacity(children.len());
+                for child in children {
+                    indexed.push((index(segment(ptr, child), child)?, *child));
+                }
+                indexed.sort_unstable_by_key(|(idx, _)| *idx);
+
+                for (idx, child) in indexed {
+                    let value = self.build(child)?;
+                    if idx < arr.len() {
+                        place(&mut arr[idx], value);
+                    } else if idx == arr.len() {
+                        arr.push(value);
+                    } else {
+                        return Err(UnflattenError::MissingIndex {
+                            pointer: child.to_owned(),
+                        });
+                    }
+                }
+            }
+            Value::Object(obj) => {
+                for child in children {
+                    let key = pointer::unescape(segment(ptr, child));
+                    let value = self.build(child)?;
+                    match obj.get_mut(&key) {
+                        Some(slot) => place(slot, value),
+                        None => {
+                            obj.insert(key, value);
+                        }
+                    }
+                }
+            }
+            _ => {
+                return Err(UnflattenError::NotAContainer {
+                    pointer: children[0].to_owned(),
+                })
+            }
+        }
+        Ok(node)
+    }
+}
+
+/// The last, still escaped segment of `child`, a child of `parent`
+fn segment<'a>(parent: &str, child: &'a str) -> &'a str {
+    &child[parent.len() + 1..]
+}
+
+/// Store `value` in an occupied slot, keeping existing children when `value`
+/// is just an empty placeholder of the same kind
+fn place(slot: &mut Value, value: Value) {
+    let keep = match (&*slot, &value) {
+        (Value::Array(_), Value::Array(new)) => new.is_empty(),
+        (Value::Object(_), Value::Object(new)) => new.is_empty(),
+        _ => false,
+    };
+    if !keep {
+        *slot = value;
+    }
+}
+
+fn index(segment: &str, ptr: &str) -> Result<usize, UnflattenError> {
+    match segment.parse() {
+        Ok(idx) if is_array_index(segment) => Ok(idx),
+        _ => Err(UnflattenError::InvalidIndex {
+            pointer: ptr.to_owned(),
+            segment: segment.to_owned(),
+        }),
+    }
+}
+
+/// The value of an [`EntryFormat::Typed`] entry, with empty containers
+fn untyped(entry: &Value) -> Option<Value> {
+    let entry = entry.as_object().filter(|entry| entry.len() == 2)?;
+    let member = |name| entry.get(name);
+    let value = match entry.get("type")?.as_str()? {
+        "array" => return member("count").map(|_| Value::Array(Vec::new())),
+        "object" => return member("count").map(|_| Value::Object(Map::new())),
+        _ => entry_to_value(member("value")?),
+    };
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        _ => return None,
+    };
+    (entry["type"] == kind).then_some(value)
+}
+
+fn entry_to_value(value: &Value) -> Value {
+    #[cfg(feature = "arbitrary_precision")]
+    if let Value::Object(obj) = value {
+        if let Some(number) = crate::flattener::private_number(obj) {
+            return number;
+        }
+    }
+    value.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ContainerValue;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let value = json!({
+            "name": "John Smith",
+            "address": { "country": "US", "zip": "00000" },
+            "phones": ["123", "456"],
+            "a/b": { "m~n": [[], {}, null] },
+            "": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+        });
+
+        assert_eq!(unflatten(&crate::from_json(&value)), Ok(value));
+    }
+
+    #[test]
+    fn round_trip_scalars() {
+        for value in [json!(null), json!(1), json!("x"), json!([]), json!({})] {
+            assert_eq!(unflatten(&crate::from_json(&value)), Ok(value));
+        }
+    }
+
+    #[test]
+    fn missing_containers_are_inferred() {
+        let flat = json!({ "/a/0/b": 1, "/a/1": true, "/c": "x" });
+
+        assert_eq!(
+            unflatten(&flat),
+            Ok(json!({ "a": [{ "b": 1 }, true], "c": "x" }))
+        );
+        assert_eq!(unflatten(&json!({})), Ok(Value::Null));
+    }
+
+    #[test]
+    fn nested_entry_values_are_kept() {
+        let flat = json!({ "": {}, "/a": { "b": 1 }, "/a/c": 2 });
+
+        assert_eq!(unflatten(&flat), Ok(json!({ "a": { "b": 1, "c": 2 } })));
+    }
+
+    #[test]
+    fn array_entries_in_any_order() {
+        let mut flat = Map::new();
+        for (key, value) in [("/a/10", 10), ("/a/2", 2)] {
+            flat.insert(key.to_owned(), json!(value));
+        }
+        for idx in (0..10).filter(|idx| *idx != 2) {
+            flat.insert(format!("/a/{}", idx), json!(idx));
+        }
+
+        assert_eq!(
+            unflatten(&Value::Object(flat)),
+            Ok(json!({ "a": (0..11).collect::<Vec<_>>() }))
+        );
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn objects_follow_first_seen_order() {
+        let flat: Value =
+            serde_json::from_str(r#"{ "/z/y": 1, "/b": [], "/z/x": 2, "/b/0": 3, "/a": 4 }"#)
+                .unwrap();
+
+        let actual = unflatten(&flat).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&actual).unwrap(),
+            r#"{"z":{"y":1,"x":2},"b":[3],"a":4}"#
+        );
+        let value: Value =
+            serde_json::from_str(r#"{ "q": { "b": 1, "a": [{ "d": 2, "c": 3 }] } }"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&unflatten(&crate::from_json(&value)).unwrap()).unwrap(),
+            serde_json::to_string(&value).unwrap()
+        );
+    }
+
+    #[cfg(not(feature = "preserve_order"))]
+    #[test]
+    fn objects_follow_sorted_order() {
+        let flat: Value =
+            serde_json::from_str(r#"{ "/z/y": 1, "/b": [], "/z/x": 2, "/b/0": 3, "/a": 4 }"#)
+                .unwrap();
+
+        let actual = unflatten(&flat).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&actual).unwrap(),
+            r#"{"a":4,"b":[3],"z":{"x":2,"y":1}}"#
+        );
+    }
+
+    #[test]
+    fn structural_errors() {
+        assert_eq!(
+            unflatten(&json!({ "/a": 1, "/a/b": 2 })),
+            Err(UnflattenError::NotAContainer {
+                pointer: "/a/b".to_owned()
+            })
+        );
+        assert_eq!(
+            unflatten(&json!({ "/a": [], "/a/x": 2 })),
+            Err(UnflattenError::InvalidIndex {
+                pointer: "/a/x".to_owned(),
+                segment: "x".to_owned()
+            })
+        );
+        assert_eq!(
+            unflatten(&json!({ "/a": [], "/a/1": 2 })),
+            Err(UnflattenError::MissingIndex {
+                pointer: "/a/1".to_owned()
+            })
+        );
+        assert_eq!(
+            unflatten(&json!({ "a": 1 })),
+            Err(UnflattenError::InvalidPointer(
+                crate::PointerError::MissingLeadingSlash("a".to_owned())
+            ))
+        );
+        assert_eq!(unflatten(&json!([])), Err(UnflattenError::NotAnObject));
+    }
+
+    #[test]
+    fn strict_indices() {
+        for segment in ["01", "1 ", "-1", "", "-", "1e2"] {
+            let pointer = format!("/a/{}", segment);
+            let error = Err(UnflattenError::InvalidIndex {
+                pointer: pointer.clone(),
+                segment: segment.to_owned(),
+            });
+
+            let placeholder = json!({ "/a": [], pointer.clone(): 1 });
+            assert_eq!(unflatten(&placeholder), error, "{}", pointer);
+            let sibling = json!({ "/a/0": 0, pointer.clone(): 1 });
+            assert_eq!(unflatten(&sibling), error, "{}", pointer);
+        }
+        assert_eq!(
+            unflatten(&json!({ "/a": [], "/a/0": 1 })),
+            Ok(json!({ "a": [1] }))
+        );
+    }
+
+    #[test]
+    fn lenient_indices_make_objects() {
+        let options = UnflattenOptions::new().strict_indices(false);
+
+        for segment in ["01", "1 ", "-1", "", "-"] {
+            let pointer = format!("/a/{}", segment);
+            let expected = Ok(json!({ "a": { "0": 0, segment: 1 } }));
+
+            let placeholder = json!({ "/a": [], "/a/0": 0, pointer.clone(): 1 });
+            assert_eq!(
+                unflatten_with(&placeholder, &options),
+                expected,
+                "{}",
+                pointer
+            );
+            let sibling = json!({ "/a/0": 0, pointer.clone(): 1 });
+            assert_eq!(unflatten_with(&sibling, &options), expected, "{}", pointer);
+        }
+        assert_eq!(
+            unflatten_with(&json!({ "/a": [], "/a/0": 1 }), &options),
+            Ok(json!({ "a": [1] }))
+        );
+    }
+
+    #[test]
+    fn append_token_alone_is_a_key() {
+        let flat = json!({ "/a/-": 1 });
+
+        assert_eq!(unflatten(&flat), Ok(json!({ "a": { "-": 1 } })));
+        assert_eq!(
+            unflatten_with(&flat, &UnflattenOptions::new().strict_indices(false)),
+            Ok(json!({ "a": { "-": 1 } }))
+        );
+    }
+
+    #[test]
+    fn root_keys_round_trip() {
+        let values = [
+            json!(1),
+            json!([true, [null]]),
+            json!({ "a": { "b": "c" } }),
+        ];
+
+        for root in ["", "/", "$root"] {
+            let flattener = crate::Flattener::new().root_key(root);
+            let options = UnflattenOptions::new().root_key(root);
+            for value in &values {
+                let flat = flattener.flatten(value);
+
+                assert_eq!(flat[root], crate::flattener::entry_value(value), "{}", root);
+                assert_eq!(
+                    unflatten_with(&flat, &options).as_ref(),
+                    Ok(value),
+                    "{}",
+                    root
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prefix_replaces_root_key() {
+        let flattener = crate::Flattener::new().root_key("$root");
+        let mut target = Map::new();
+
+        flattener
+            .flatten_into(&json!([1]), &mut target, "/doc")
+            .unwrap();
+
+        assert_eq!(Value::Object(target), json!({ "/doc": [], "/doc/0": 1 }));
+    }
+
+    #[test]
+    fn typed_entries_round_trip() {
+        let value = json!({ "a": ["42", 42, null, true, {}], "b": { "c": [] } });
+        let options = UnflattenOptions::new().entry_format(EntryFormat::Typed);
+
+        for containers in [
+            ContainerValue::Empty,
+            ContainerValue::Count,
+            ContainerValue::Skip,
+        ] {
+            let flat = crate::Flattener::new()
+                .entry_format(EntryFormat::Typed)
+                .container_value(containers)
+                .flatten(&value);
+
+            let expected = match containers {
+                // empty containers are lost without their entries
+                ContainerValue::Skip => json!({ "a": ["42", 42, null, true] }),
+                _ => value.clone(),
+            };
+            assert_eq!(unflatten_with(&flat, &options), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn invalid_typed_entries() {
+        let options = UnflattenOptions::new().entry_format(EntryFormat::Typed);
+
+        for entry in [
+            json!(42),
+            json!({ "type": "string", "value": 42 }),
+            json!({ "type": "number" }),
+            json!({ "type": "array", "value": [] }),
+            json!({ "type": "null", "value": null, "extra": 1 }),
+            json!({ "type": "date", "value": "2021-01-01" }),
+        ] {
+            assert_eq!(
+                unflatten_with(&json!({ "/a": entry }), &options),
+                Err(UnflattenError::InvalidEntry {
+                    pointer: "/a".to_owned()
+                }),
+                "{}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn root_key_is_not_a_pointer() {
+        let options = UnflattenOptions::new().root_key("/");
+
+        assert_eq!(
+            unflatten_with(&json!({ "/": {}, "/a": 1 }), &options),
+            Ok(json!({ "a": 1 }))
+        );
+        assert_eq!(unflatten(&json!({ "/": 1 })), Ok(json!({ "": 1 })));
+        assert!(unflatten(&json!({ "$root": 1 })).is_err());
+    }
+
+    #[test]
+    fn depth_limit_is_checked_before_building() {
+        let options = UnflattenOptions::new().max_depth(2);
+
+        assert_eq!(
+            unflatten_with(&json!({ "/a/b": 1 }), &options),
+            Ok(json!({ "a": { "b": 1 } }))
+        );
+        assert_eq!(
+            unflatten_with(&json!({ "/a/b": 1, "/a/b/0": 1 }), &options),
+            Err(UnflattenError::DepthLimitExceeded {
+                limit: 2,
+                pointer: "/a/b/0".to_owned()
+            })
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn private_number_is_reconstituted() {
+        let digits = "1234567890123456789012345678901234567890";
+        let flat = json!({ "/n": { "$serde_json::private::Number": digits } });
+
+        let actual = unflatten(&flat).unwrap();
+
+        assert!(actual["n"].is_number());
+        assert_eq!(actual["n"].to_string(), digits);
+    }
+}
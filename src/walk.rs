@@ -0,0 +1,404 @@
+use crate::flattener::is_container;
+use crate::pointer::escape_into;
+use serde_json::Value;
+use std::fmt::Write;
+use std::ops::ControlFlow;
+
+/// Whether a container is an object or an array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerKind {
+    Object,
+    Array,
+}
+
+/// Callbacks of [`walk`], called with the escaped pointer of each node
+///
+/// Every method does nothing by default, so visitors only implement what
+/// they need. Pointers are borrowed from a buffer the walk reuses, so copy
+/// them to keep them.
+pub trait FlattenVisitor {
+    /// Called before the children of an object or array
+    ///
+    /// Returning [`ControlFlow::Break`] skips the children, and
+    /// [`FlattenVisitor::exit_container`] is not called for this container.
+    fn enter_container(&mut self, pointer: &str, kind: ContainerKind) -> ControlFlow<()> {
+        let _ = (pointer, kind);
+        ControlFlow::Continue(())
+    }
+
+    /// Called for every value that is not a container
+    ///
+    /// Returning [`ControlFlow::Break`] stops the whole walk. With the
+    /// `arbitrary_precision` feature, a number carried as serde_json's private
+    /// number object is a leaf and passed as that object.
+    fn leaf(&mut self, pointer: &str, value: &Value) -> ControlFlow<()> {
+        let _ = (pointer, value);
+        ControlFlow::Continue(())
+    }
+
+    /// Called after the last child of a container that was entered
+    fn exit_container(&mut self, pointer: &str) {
+        let _ = pointer;
+    }
+}
+
+/// Traverse `value` in the order of [`from_json`](crate::from_json) without collecting anything
+///
+/// Containers come before their children, and object keys in the value's
+/// own order. Returns [`ControlFlow::Break`] if a [`FlattenVisitor::leaf`]
+/// stopped the walk.
+///
+/// # Example
+///
+/// ```
+/// use jsonpointer_flatten::{ContainerKind, FlattenVisitor};
+/// use serde_json::{json, Value};
+/// use std::ops::ControlFlow;
+///
+/// /// Sums the numbers outside of "/private"
+/// struct Sum(f64);
+///
+/// impl FlattenVisitor for Sum {
+///     fn enter_container(&mut self, pointer: &str, _: ContainerKind) -> ControlFlow<()> {
+///         if pointer == "/private" {
+///             ControlFlow::Break(())
+///         } else {
+///             ControlFlow::Continue(())
+///         }
+///     }
+///
+///     fn leaf(&mut self, _: &str, value: &Value) -> ControlFlow<()> {
+///         self.0 += value.as_f64().unwrap_or(0.0);
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let value = json!({ "a": [1, 2], "b": { "c": 3 }, "private": { "d": 100 } });
+/// let mut sum = Sum(0.0);
+///
+/// let _ = jsonpointer_flatten::walk(&value, &mut sum);
+///
+/// assert_eq!(sum.0, 6.0);
+/// ```
+pub fn walk<V: FlattenVisitor + ?Sized>(value: &Value, visitor: &mut V) -> ControlFlow<()> {
+    visit(&mut String::new(), value, visitor)
+}
+
+fn visit<V: FlattenVisitor + ?Sized>(
+    pointer: &mut String,
+    value: &Value,
+    visitor: &mut V,
+) -> ControlFlow<()> {
+    let kind = match value {
+        Value::Array(_) => ContainerKind::Array,
+        Value::Object(_) if is_container(value) => ContainerKind::Object,
+        _ => return visitor.leaf(pointer, value),
+    };
+    if visitor.enter_container(pointer, kind).is_break() {
+        return ControlFlow::Continue(());
+    }
+
+    let len = pointer.len();
+    match value {
+        Value::Array(arr) => {
+            for (idx, child) in arr.iter().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                visit(pointer, child, visitor)?;
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                pointer.push('/');
+                escape_into(key, pointer);
+                visit(pointer, child, visitor)?;
+                pointer.truncate(len);
+            }
+        }
+        _ => unreachable!("only arrays and objects are containers"),
+    }
+    visitor.exit_container(pointer);
+    ControlFlow::Continue(())
+}
+
+/// Pass every leaf of `value` to `f` with its pointer, to change it in place
+///
+/// Leaves come in the order of [`walk`], with the pointers [`from_json`](crate::from_json)
+/// gives them, and the document stays nested. Returning [`ControlFlow::Break`]
+/// from `f` stops the walk, which then returns it too.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{json, Value};
+/// use std::ops::ControlFlow;
+///
+/// let mut value = json!({ "user": { "email": "jo@example.com", "age": 30 }, "plan": "pro" });
+///
+/// let _ = jsonpointer_flatten::visit_mut(&mut value, |pointer, leaf| {
+///     if pointer.starts_with("/user/") && leaf.is_string() {
+///         *leaf = Value::from("***");
+///     }
+///     ControlFlow::Continue(())
+/// });
+///
+/// assert_eq!(value, json!({ "user": { "email": "***", "age": 30 }, "plan": "pro" }));
+/// ```
+pub fn visit_mut<F>(value: &mut Value, mut f: F) -> ControlFlow<()>
+where
+    F: FnMut(&str, &mut Value) -> ControlFlow<()>,
+{
+    visit_nodes_in(&mut String::new(), value, false, &mut f)
+}
+
+/// Like [`visit_mut`], also passing containers before their children
+///
+/// The children visited are those of the container as `f` left it, so
+/// replacing a container with a leaf skips its old children.
+pub fn visit_nodes_mut<F>(value: &mut Value, mut f: F) -> ControlFlow<()>
+where
+    F: FnMut(&str, &mut Value) -> ControlFlow<()>,
+{
+    visit_nodes_in(&mut String::new(), value, true, &mut f)
+}
+
+fn visit_nodes_in<F>(
+    pointer: &mut String,
+    value: &mut Value,
+    containers: bool,
+    f: &mut F,
+) -> ControlFlow<()>
+where
+    F: FnMut(&str, &mut Value) -> ControlFlow<()>,
+{
+    if !is_container(value) {
+        return f(pointer, value);
+    }
+    if containers {
+        f(pointer, value)?;
+        if !is_container(value) {
+            return ControlFlow::Continue(());
+        }
+    }
+
+    let len = pointer.len();
+    match value {
+        Value::Array(arr) => {
+            for (idx, child) in arr.iter_mut().enumerate() {
+                let _ = write!(pointer, "/{}", idx);
+                visit_nodes_in(pointer, child, containers, f)?;
+                pointer.truncate(len);
+            }
+        }
+        Value::Object(obj) => {
+            for (key, child) in obj.iter_mut() {
+                pointer.push('/');
+                escape_into(key, pointer);
+                visit_nodes_in(pointer, child, containers, f)?;
+                pointer.truncate(len);
+            }
+        }
+        _ => unreachable!("only arrays and objects are containers"),
+    }
+    ControlFlow::Continue(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::flattener::entry_value;
+    use crate::Flattener;
+    use serde_json::{json, Map};
+
+    /// Records every callback as a line
+    #[derive(Default)]
+    struct Trace {
+        lines: Vec<String>,
+        skip: &'static str,
+        stop: &'static str,
+    }
+
+    impl FlattenVisitor for Trace {
+        fn enter_container(&mut self, pointer: &str, kind: ContainerKind) -> ControlFlow<()> {
+            self.lines.push(format!("enter {:?} {}", kind, pointer));
+            if pointer == self.skip {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+
+        fn leaf(&mut self, pointer: &str, value: &Value) -> ControlFlow<()> {
+            self.lines.push(format!("leaf {} {}", pointer, value));
+            if pointer == self.stop {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+
+        fn exit_container(&mut self, pointer: &str) {
+            self.lines.push(format!("exit {}", pointer));
+        }
+    }
+
+    #[test]
+    fn callbacks_nest_and_skip_subtrees() {
+        let value = json!({ "a/b": [1, {}], "c": { "d": null }, "e": "x" });
+        let mut trace = Trace {
+            skip: "/c",
+            ..Trace::default()
+        };
+
+        assert_eq!(walk(&value, &mut trace), ControlFlow::Continue(()));
+        assert_eq!(
+            trace.lines,
+            vec![
+                "enter Object ",
+                "enter Array /a~1b",
+                "leaf /a~1b/0 1",
+                "enter Object /a~1b/1",
+                "exit /a~1b/1",
+                "exit /a~1b",
+                "enter Object /c",
+                "leaf /e \"x\"",
+                "exit ",
+            ]
+        );
+    }
+
+    #[test]
+    fn breaking_at_a_leaf_stops_the_walk() {
+        let mut trace = Trace {
+            skip: "-",
+            stop: "/0/0",
+            ..Trace::default()
+        };
+
+        assert_eq!(
+            walk(&json!([[1, 2], 3]), &mut trace),
+            ControlFlow::Break(())
+        );
+        assert_eq!(
+            trace.lines,
+            vec!["enter Array ", "enter Array /0", "leaf /0/0 1"]
+        );
+    }
+
+    /// Collects the plain flattened entries
+    #[derive(Default)]
+    struct Collect {
+        entries: Map<String, Value>,
+    }
+
+    impl FlattenVisitor for Collect {
+        fn enter_container(&mut self, pointer: &str, kind: ContainerKind) -> ControlFlow<()> {
+            let placeholder = match kind {
+                ContainerKind::Object => Value::Object(Map::new()),
+                ContainerKind::Array => Value::Array(Vec::new()),
+            };
+            self.entries.insert(pointer.to_owned(), placeholder);
+            ControlFlow::Continue(())
+        }
+
+        fn leaf(&mut self, pointer: &str, value: &Value) -> ControlFlow<()> {
+            self.entries.insert(pointer.to_owned(), entry_value(value));
+            ControlFlow::Continue(())
+        }
+    }
+
+    #[test]
+    fn collecting_matches_the_flattener() {
+        let value = json!({ "a": [1, [], { "b~c": null }], "": { "": 2.5 }, "s": "t" });
+        let mut collect = Collect::default();
+
+        let _ = walk(&value, &mut collect);
+
+        assert_eq!(
+            Value::Object(collect.entries),
+            Flattener::new().flatten(&value)
+        );
+    }
+
+    #[test]
+    fn masked_strings_under_a_prefix_flatten_masked() {
+        let mut value = json!({
+            "user": { "name": "Jo", "a/b": ["x", 1], "tags": { "~": "y" } },
+            "username": "kept"
+        });
+
+        let flow = visit_mut(&mut value, |pointer, leaf| {
+            if crate::pointer::starts_with(pointer, "/user") && leaf.is_string() {
+                *leaf = Value::from("***");
+            }
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(
+            crate::from_json(&value),
+            json!({
+                "": {},
+                "/user": {},
+                "/user/a~1b": [],
+                "/user/a~1b/0": "***",
+                "/user/a~1b/1": 1,
+                "/user/name": "***",
+                "/user/tags": {},
+                "/user/tags/~0": "***",
+                "/username": "kept"
+            })
+        );
+    }
+
+    #[test]
+    fn mutable_pointers_match_the_flattened_keys() {
+        let mut value = json!({ "a": [1, { "b~c": [null] }], "d/e": true, "": "" });
+        let flattened = crate::from_json(&value);
+        let mut leaves = Vec::new();
+        let mut nodes = Vec::new();
+
+        let _ = visit_mut(&mut value, |pointer, _| {
+            leaves.push(pointer.to_owned());
+            ControlFlow::Continue(())
+        });
+        let _ = visit_nodes_mut(&mut value, |pointer, _| {
+            nodes.push(pointer.to_owned());
+            ControlFlow::Continue(())
+        });
+
+        let keys: Vec<&String> = flattened.as_object().unwrap().keys().collect();
+        assert_eq!(nodes.iter().collect::<Vec<_>>(), keys);
+        let leaf_keys: Vec<&String> = flattened
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(_, entry)| !entry.is_object() && !entry.is_array())
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(leaves.iter().collect::<Vec<_>>(), leaf_keys);
+        assert_eq!(leaves.len(), 4);
+    }
+
+    #[test]
+    fn replaced_containers_and_breaks_end_the_descent() {
+        let mut value = json!({ "a": { "secret": 1 }, "b": [1, 2, 3] });
+        let mut seen = Vec::new();
+
+        let flow = visit_nodes_mut(&mut value, |pointer, node| {
+            seen.push(pointer.to_owned());
+            if pointer == "/a" {
+                *node = Value::Null;
+            }
+            if pointer == "/b/1" {
+                *node = json!(20);
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(())
+        });
+
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(seen, vec!["", "/a", "/b", "/b/0", "/b/1"]);
+        assert_eq!(value, json!({ "a": null, "b": [1, 20, 3] }));
+    }
+}